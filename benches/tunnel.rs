@@ -0,0 +1,46 @@
+// Baseline timings for the hot paths a frontend hits every frame. Run with
+// `cargo bench`; compare against a saved baseline with
+// `cargo bench -- --save-baseline main` / `--baseline main`.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use tunnel::pattern::PatternBuilder;
+use tunnel::{Tunnel, TunnelIndex};
+
+const SIZES: [usize; 3] = [16, 64, 250];
+
+fn zigzag() -> PatternBuilder {
+    PatternBuilder::parse(">>>> <<<< == <<<< >>>> ==").unwrap()
+}
+
+fn bench_index<T: TunnelIndex>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+    for size in SIZES {
+        let n = T::from_usize(size).unwrap();
+        group.bench_with_input(BenchmarkId::new("new", size), &n, |b, &n| {
+            b.iter(|| Tunnel::<T>::new(&mut zigzag(), n, n));
+        });
+
+        let mut builder = zigzag();
+        let mut t = Tunnel::<T>::new(&mut builder, n, n);
+        group.bench_function(BenchmarkId::new("step", size), |b| {
+            b.iter(|| t.step(&mut builder));
+        });
+        group.bench_function(BenchmarkId::new("iter_count", size), |b| {
+            b.iter(|| black_box(&t).iter().count());
+        });
+        group.bench_function(BenchmarkId::new("is_collision", size), |b| {
+            b.iter(|| black_box(&t).is_collision());
+        });
+    }
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    bench_index::<u8>(c, "u8");
+    bench_index::<u16>(c, "u16");
+    bench_index::<u32>(c, "u32");
+}
+
+criterion_group!(tunnel, benches);
+criterion_main!(tunnel);
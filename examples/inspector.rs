@@ -0,0 +1,124 @@
+// cargo run --example inspector --features inspector
+//
+// A seeded game beside the inspector: arrow keys steer, Space pauses, and
+// the side panel tunes the rows still to be generated.
+
+use eframe::egui::{self, Color32, Key, Rect, pos2, vec2};
+use tunnel::difficulty::Difficulty;
+use tunnel::inspector::{self, Recording};
+use tunnel::seeded::Seeded;
+use tunnel::{Tunnel, TunnelCellType, TunnelConfig};
+
+const ROWS: u16 = 30;
+const COLS: u16 = 40;
+const CELL: f32 = 14.;
+const TICK: f64 = 0.15;
+
+struct App {
+    builder: Recording<Seeded>,
+    tunnel: Tunnel<u16>,
+    seed: u64,
+    score: u64,
+    paused: bool,
+    next_tick: f64,
+}
+
+impl App {
+    fn new(seed: u64, config: TunnelConfig) -> App {
+        let mut builder = Recording::new(Seeded::new(seed));
+        let tunnel = Tunnel::with_config(&mut builder, ROWS, COLS, config);
+        App {
+            builder,
+            tunnel,
+            seed,
+            score: 0,
+            paused: false,
+            next_tick: 0.,
+        }
+    }
+
+    fn play(&mut self, ctx: &egui::Context) {
+        let (now, left, right, pause) = ctx.input(|i| {
+            (
+                i.time,
+                i.key_pressed(Key::ArrowLeft),
+                i.key_pressed(Key::ArrowRight),
+                i.key_pressed(Key::Space),
+            )
+        });
+        self.paused ^= pause;
+        if self.paused || self.tunnel.is_collision() {
+            return;
+        }
+        if left {
+            self.tunnel.move_player_left();
+        }
+        if right {
+            self.tunnel.move_player_right();
+        }
+        if now >= self.next_tick {
+            self.tunnel.step(&mut self.builder);
+            if !self.tunnel.is_collision() {
+                self.score += 1;
+            }
+            self.next_tick = now + TICK / self.tunnel.speed_multiplier() as f64;
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        self.play(ctx);
+        egui::SidePanel::right("inspector").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                inspector::show(ui, &mut self.tunnel, self.builder.history());
+            });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("seed {} score {}", self.seed, self.score));
+                if self.tunnel.is_collision() {
+                    ui.colored_label(Color32::RED, "crashed");
+                }
+                // a new seed, keeping whatever was tuned
+                if ui.button("Restart").clicked() {
+                    let config = self.tunnel.config().clone();
+                    *self = App::new(self.seed + 1, config);
+                }
+            });
+            let origin = ui.cursor().min;
+            let painter = ui.painter();
+            for (row, col, cell_type) in self.tunnel.iter() {
+                let color = match cell_type {
+                    TunnelCellType::Wall => Color32::GRAY,
+                    TunnelCellType::Player => Color32::GREEN,
+                    TunnelCellType::Fuel | TunnelCellType::Key => {
+                        Color32::YELLOW
+                    }
+                    TunnelCellType::Door => Color32::from_rgb(200, 0, 200),
+                    TunnelCellType::Coin => Color32::from_rgb(255, 160, 0),
+                    TunnelCellType::Pillar => Color32::RED,
+                    TunnelCellType::Floor | TunnelCellType::Hidden => continue,
+                };
+                let min =
+                    origin + vec2(f32::from(col) * CELL, f32::from(row) * CELL);
+                let cell = Rect::from_min_size(min, vec2(CELL, CELL));
+                painter.rect_filled(cell, 0., color);
+            }
+            let size = vec2(f32::from(COLS), f32::from(ROWS)) * CELL;
+            ui.allocate_rect(
+                Rect::from_min_size(pos2(origin.x, origin.y), size),
+                egui::Sense::hover(),
+            );
+        });
+        ctx.request_repaint();
+    }
+}
+
+fn main() -> eframe::Result {
+    eframe::run_native(
+        "tunnel inspector",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(App::new(0, Difficulty::Normal.config())))),
+    )
+}
@@ -0,0 +1,81 @@
+// cargo run --example macroquad --features macroquad
+//
+// The terminal game in a window: arrow keys or A/D steer, Escape quits.
+// Rows glide up between steps, the one under the window's bottom edge
+// sliding into view.
+
+use macroquad::miniquad::date;
+use macroquad::prelude::*;
+use macroquad::rand;
+use tunnel::quad::{Atlas, DEFAULT_COLORS, QuadRenderer, read_direction};
+use tunnel::render::draw_frame_scrolled;
+use tunnel::{
+    Direction, Tunnel, TunnelBuilder, TunnelBuilderChoice, TunnelIndex,
+};
+
+const ROWS: u16 = 30;
+const COLS: u16 = 40;
+const CELL: f32 = 16.;
+const TICK: f64 = 0.15;
+
+struct RandomWalls;
+
+impl TunnelBuilder for RandomWalls {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        max / 2.into()
+    }
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        match rand::gen_range(0, 20) {
+            0 => TunnelBuilderChoice::SplitCorridor,
+            1 => TunnelBuilderChoice::MergeCorridor,
+            n if n % 2 == 0 => TunnelBuilderChoice::MoveLeftWall,
+            _ => TunnelBuilderChoice::MoveRightWall,
+        }
+    }
+}
+
+fn window() -> Conf {
+    Conf {
+        window_title: "tunnel".to_owned(),
+        window_width: i32::from(COLS) * CELL as i32,
+        window_height: i32::from(ROWS) * CELL as i32,
+        ..Conf::default()
+    }
+}
+
+#[macroquad::main(window)]
+async fn main() {
+    rand::srand(date::now() as u64);
+    let mut builder = RandomWalls;
+    // a row past the window's edge, waiting to slide in
+    let mut t = Tunnel::new(&mut builder, ROWS + 1, COLS);
+    let mut renderer =
+        QuadRenderer::new(Atlas::from_colors(DEFAULT_COLORS), CELL, BLACK);
+    let mut score = 0;
+    let mut step = TICK;
+    let mut next_tick = get_time() + step;
+
+    while !is_key_pressed(KeyCode::Escape) && !t.is_collision() {
+        match read_direction() {
+            Some(Direction::Left) => t.move_player_left(),
+            Some(Direction::Right) => t.move_player_right(),
+            None => {}
+        }
+        if get_time() >= next_tick {
+            t.step(&mut builder);
+            score += 1;
+            step = TICK / t.speed_multiplier() as f64;
+            next_tick += step;
+        }
+        let offset = 1. - (next_tick - get_time()) / step;
+        let Ok(()) = draw_frame_scrolled(
+            &mut renderer,
+            &t,
+            usize::MAX,
+            score,
+            offset as f32,
+        );
+        next_frame().await;
+    }
+    println!("Final score: {score}");
+}
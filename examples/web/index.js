@@ -0,0 +1,52 @@
+import init, { Tunnel } from "./pkg/tunnel_web.js";
+
+const ROWS = 30;
+const COLS = 40;
+const CELL = 16;
+const TICK_MS = 150;
+// indexed by the codes from Tunnel.cells(): floor, wall, player, fuel,
+// key, door, hidden
+const COLORS = ["#000", "#888", "#0c0", "#cc0", "#fc0", "#c0c", "#333"];
+
+await init();
+const canvas = document.getElementById("tunnel");
+const status = document.getElementById("status");
+const ctx = canvas.getContext("2d");
+canvas.width = COLS * CELL;
+canvas.height = ROWS * CELL;
+
+const tunnel = new Tunnel(ROWS, COLS, BigInt(Date.now()));
+let score = 0;
+
+document.addEventListener("keydown", (e) => {
+  if (tunnel.key(e.key)) e.preventDefault();
+});
+canvas.addEventListener("pointerdown", (e) => {
+  const rect = canvas.getBoundingClientRect();
+  tunnel.touch(e.clientX - rect.left, rect.width);
+});
+
+function draw() {
+  const cells = tunnel.cells();
+  const cols = tunnel.cols;
+  ctx.clearRect(0, 0, canvas.width, canvas.height);
+  for (let i = 0; i < cells.length; i++) {
+    ctx.fillStyle = COLORS[cells[i]];
+    ctx.fillRect((i % cols) * CELL, Math.floor(i / cols) * CELL, CELL, CELL);
+  }
+}
+
+function tick() {
+  tunnel.step();
+  draw();
+  if (tunnel.isCollision()) {
+    status.textContent = `Game over! Final score: ${score}`;
+    return;
+  }
+  score += 1;
+  status.textContent = `${score}`;
+  setTimeout(tick, TICK_MS / tunnel.speedMultiplier());
+}
+
+draw();
+setTimeout(tick, TICK_MS);
@@ -0,0 +1,3 @@
+// Links the bindings from tunnel::wasm into this cdylib; wasm-bindgen
+// generates the JS side of them (pkg/tunnel_web.js) from the result.
+pub use tunnel::wasm::WasmTunnel;
@@ -0,0 +1,24 @@
+// cargo +nightly fuzz run pattern
+//
+// Any text either parses as a pattern or is rejected with an error, and
+// whatever parses builds a tunnel that holds props::check_tunnel.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tunnel::Tunnel;
+use tunnel::pattern::PatternBuilder;
+use tunnel::props::check_tunnel;
+
+fuzz_target!(|pattern: &str| {
+    let Ok(mut b) = PatternBuilder::parse(pattern) else {
+        return;
+    };
+    let mut t = Tunnel::<u8>::new(&mut b, 12, 16);
+    for _ in 0..64 {
+        if let Err(e) = check_tunnel(&t) {
+            panic!("{pattern:?}: {e}");
+        }
+        t.step(&mut b);
+    }
+});
@@ -0,0 +1,128 @@
+// cargo +nightly fuzz run tunnel
+//
+// Arbitrary sizes, configs, builder choices and moves, played on u8 and
+// u16 tunnels side by side: both must hold props::check_tunnel after every
+// move and step, and draw the same cells, since nothing on a screen of at
+// most 255 columns should need to saturate.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use tunnel::pattern::PatternBuilder;
+use tunnel::props::check_tunnel;
+use tunnel::sim::Action;
+use tunnel::{
+    Attractor, FuelTank, Gravity, MaxSlope, RowStorage, Tunnel,
+    TunnelBuilderChoice, TunnelConfig, TunnelIndex,
+};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    rows: u8,
+    cols: u8,
+    max_slope: Option<(u8, u8)>,
+    grace_rows: u8,
+    min_gap: u8,
+    narrow_every: u8,
+    gravity: Option<(bool, u8)>,
+    fuel: Option<(u8, u8)>,
+    storage: u8,
+    choices: Vec<u8>,
+    moves: Vec<u8>,
+}
+
+const CHOICES: [TunnelBuilderChoice; 7] = [
+    TunnelBuilderChoice::MoveLeftWall,
+    TunnelBuilderChoice::MoveRightWall,
+    TunnelBuilderChoice::KeepWalls,
+    TunnelBuilderChoice::SplitCorridor,
+    TunnelBuilderChoice::MergeCorridor,
+    TunnelBuilderChoice::SteerPillarLeft,
+    TunnelBuilderChoice::SteerPillarRight,
+];
+
+impl Input {
+    fn config(&self) -> TunnelConfig {
+        let storage =
+            RowStorage::ALL[usize::from(self.storage) % RowStorage::ALL.len()];
+        TunnelConfig {
+            max_slope: self.max_slope.map(|(shift, rows)| MaxSlope {
+                shift: shift.into(),
+                rows: rows.into(),
+            }),
+            grace_rows: self.grace_rows.into(),
+            min_gap: self.min_gap.into(),
+            narrow_every: self.narrow_every.into(),
+            gravity: self.gravity.map(|(center, every)| Gravity {
+                toward: match center {
+                    true => Attractor::Center,
+                    false => Attractor::LastWall,
+                },
+                every: every.into(),
+            }),
+            fuel: self.fuel.map(|(capacity, pickup)| FuelTank {
+                capacity: capacity.into(),
+                pickup: pickup.into(),
+            }),
+            storage,
+            max_rows: None,
+        }
+    }
+
+    fn builder(&self) -> Option<PatternBuilder> {
+        let choices = self.choices.iter().map(|&c| CHOICES[usize::from(c) % 7]);
+        PatternBuilder::from_choices(choices).ok()
+    }
+}
+
+fn check<T: TunnelIndex>(t: &Tunnel<T>) {
+    if let Err(e) = check_tunnel(t) {
+        panic!("{e}");
+    }
+}
+
+fn cells<T: TunnelIndex>(t: &Tunnel<T>) -> Vec<(usize, usize, u8)> {
+    t.iter()
+        .map(|(row, col, cell)| {
+            let index = |i: T| i.to_usize().unwrap_or(usize::MAX);
+            (index(row), index(col), cell.code())
+        })
+        .collect()
+}
+
+fuzz_target!(|input: Input| {
+    let Some(mut narrow_builder) = input.builder() else {
+        return;
+    };
+    let mut wide_builder = narrow_builder.clone();
+    // the smallest screen with a floor column between two walls
+    let rows = input.rows.max(3);
+    let cols = input.cols.max(3);
+    let mut narrow =
+        Tunnel::with_config(&mut narrow_builder, rows, cols, input.config());
+    let mut wide = Tunnel::with_config(
+        &mut wide_builder,
+        u16::from(rows),
+        u16::from(cols),
+        input.config(),
+    );
+    check(&narrow);
+    check(&wide);
+    for &code in &input.moves {
+        if narrow.is_collision() {
+            break;
+        }
+        let action = Action::from_code(code % 5).unwrap_or(Action::Stay);
+        action.apply(&mut narrow);
+        action.apply(&mut wide);
+        check(&narrow);
+        check(&wide);
+        narrow.step(&mut narrow_builder);
+        wide.step(&mut wide_builder);
+        check(&narrow);
+        check(&wide);
+        assert_eq!(cells(&narrow), cells(&wide));
+        assert_eq!(narrow.is_collision(), wide.is_collision());
+    }
+});
@@ -0,0 +1,49 @@
+/* C interface to the tunnel engine; see src/ffi.rs. */
+#ifndef TUNNEL_H
+#define TUNNEL_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct TunnelHandle TunnelHandle;
+
+/* cell codes written by tunnel_cells() */
+enum {
+    TUNNEL_FLOOR = 0,
+    TUNNEL_WALL = 1,
+    TUNNEL_PLAYER = 2,
+    TUNNEL_FUEL = 3,
+    TUNNEL_KEY = 4,
+    TUNNEL_DOOR = 5,
+    TUNNEL_HIDDEN = 6,
+    TUNNEL_COIN = 7,
+    TUNNEL_PILLAR = 8,
+};
+
+/* difficulty: 0 chill, 1 normal, 2 hard, 3 nightmare; NULL if invalid */
+TunnelHandle *tunnel_new(uint16_t rows, uint16_t cols, uint64_t seed,
+                         uint8_t difficulty);
+void tunnel_free(TunnelHandle *h);
+
+void tunnel_step(TunnelHandle *h);
+void tunnel_move_left(TunnelHandle *h);
+void tunnel_move_right(TunnelHandle *h);
+
+bool tunnel_is_collision(const TunnelHandle *h);
+uint16_t tunnel_player(const TunnelHandle *h);
+size_t tunnel_rows(const TunnelHandle *h);
+uint16_t tunnel_cols(const TunnelHandle *h);
+
+/* writes up to len cell codes, row-major; returns rows * cols */
+size_t tunnel_cells(const TunnelHandle *h, uint8_t *buf, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,368 @@
+// The terminal game for a remote terminal at the other end of a byte
+// stream (ssh, telnet), where crossterm cannot reach: frames go out as ANSI
+// escape sequences, keys come back as the bytes the terminal sends.
+
+use crate::difficulty::Difficulty;
+use crate::render::{FrameDiff, Renderer, draw_frame};
+use crate::seeded::Seeded;
+use crate::{Direction, Floor, Hazard, RowInfo, Tunnel, TunnelCellType};
+use alloc::format;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::time::Duration;
+
+// terminals report any size; games are kept between these
+pub const MIN_SIZE: (u16, u16) = (8, 4);
+pub const MAX_SIZE: (u16, u16) = (512, 512);
+
+// the (cols, rows) of game that fits a cols x rows terminal
+pub fn fit(cols: u32, rows: u32) -> (u16, u16) {
+    let clamp = |n: u32, min: u16, max: u16| {
+        u16::try_from(n).unwrap_or(max).clamp(min, max)
+    };
+    (
+        clamp(cols, MIN_SIZE.0, MAX_SIZE.0),
+        clamp(rows, MIN_SIZE.1, MAX_SIZE.1),
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct GameSettings {
+    pub difficulty: Difficulty,
+    // between steps outside speed zones
+    pub tick: Duration,
+}
+
+impl Default for GameSettings {
+    fn default() -> GameSettings {
+        GameSettings {
+            difficulty: Difficulty::Normal,
+            tick: Duration::from_millis(100),
+        }
+    }
+}
+
+// switch to the alternate screen and hide the cursor, and back
+pub const ENTER: &[u8] = b"\x1b[?1049h\x1b[?25l";
+pub const LEAVE: &[u8] = b"\x1b[?25h\x1b[?1049l";
+
+// the glyphs and colours of the crossterm frontend, as SGR parameters
+pub fn glyph(cell_type: TunnelCellType, info: RowInfo) -> (u8, &'static str) {
+    match cell_type {
+        TunnelCellType::Player => (b'v', "92"),
+        TunnelCellType::Floor => match (info.floor, info.wind) {
+            _ if info.hazard == Some(Hazard::Warning) => (b'!', "91"),
+            (Floor::Ice, _) => (b'-', "96"),
+            (Floor::Sticky, _) => (b'~', "33"),
+            (Floor::Normal, Some(wind)) => match wind.direction {
+                Direction::Left => (b'<', "90"),
+                Direction::Right => (b'>', "90"),
+            },
+            (Floor::Normal, None) if info.speed_zone => (b'^', "90"),
+            (Floor::Normal, None) => (b' ', "0"),
+        },
+        TunnelCellType::Wall => (b'O', "0"),
+        TunnelCellType::Fuel => (b'+', "93"),
+        TunnelCellType::Key => (b'k', "93"),
+        TunnelCellType::Door => (b'#', "95"),
+        TunnelCellType::Hidden => (b'.', "90"),
+        TunnelCellType::Coin => (b'*', "93"),
+        TunnelCellType::Pillar => (b'X', "31"),
+    }
+}
+
+// collects one frame of ANSI output; take() it and send it. After the
+// first frame only the cells that changed are drawn, until redraw().
+pub struct AnsiRenderer {
+    pub out: Vec<u8>,
+    pub score_row: u16,
+    // where the cursor is after the last glyph, to skip redundant moves
+    cursor: Option<(u16, u16)>,
+    diff: FrameDiff,
+}
+
+impl AnsiRenderer {
+    pub fn new(score_row: u16) -> AnsiRenderer {
+        AnsiRenderer {
+            out: Vec::new(),
+            score_row,
+            cursor: None,
+            diff: FrameDiff::default(),
+        }
+    }
+
+    // the next frame clears the screen and draws every cell, for when
+    // something else drew on it
+    pub fn redraw(&mut self) {
+        self.diff.clear();
+    }
+
+    pub fn take(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.out)
+    }
+
+    fn move_to(&mut self, row: u16, col: u16) {
+        if self.cursor != Some((row, col)) {
+            let to =
+                format!("\x1b[{};{}H", u32::from(row) + 1, u32::from(col) + 1);
+            self.out.extend_from_slice(to.as_bytes());
+        }
+        self.cursor = Some((row, col.saturating_add(1)));
+    }
+}
+
+impl Renderer<u16> for AnsiRenderer {
+    type Error = Infallible;
+
+    fn begin_frame(&mut self) -> Result<(), Infallible> {
+        if self.diff.is_empty() {
+            self.out.extend_from_slice(b"\x1b[0m\x1b[2J");
+            self.cursor = None;
+        }
+        Ok(())
+    }
+
+    fn draw_cell(
+        &mut self,
+        row: u16,
+        col: u16,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> Result<(), Infallible> {
+        if !self.diff.changed(row.into(), col.into(), cell_type, info) {
+            return Ok(());
+        }
+        let (symbol, sgr) = glyph(cell_type, info);
+        self.move_to(row, col);
+        self.out
+            .extend_from_slice(format!("\x1b[{sgr}m").as_bytes());
+        self.out.push(symbol);
+        Ok(())
+    }
+
+    fn draw_hud(&mut self, score: u64) -> Result<(), Infallible> {
+        self.move_to(self.score_row, 0);
+        self.out.extend_from_slice(
+            format!("\x1b[92m{score}\x1b[0m\x1b[K").as_bytes(),
+        );
+        Ok(())
+    }
+
+    fn end_frame(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Left,
+    Right,
+    Quit,
+}
+
+// turns terminal input into keys; escape sequences may arrive split
+// across reads, so an unfinished one waits for the next feed()
+#[derive(Default)]
+pub struct KeyParser {
+    pending: Vec<u8>,
+}
+
+impl KeyParser {
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Key> {
+        self.pending.extend_from_slice(bytes);
+        let mut keys = Vec::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            let (key, len) = match self.pending[i..] {
+                // arrows, in normal or application cursor mode
+                [0x1b, b'[' | b'O', b'D', ..] => (Some(Key::Left), 3),
+                [0x1b, b'[' | b'O', b'C', ..] => (Some(Key::Right), 3),
+                // up, down and the like
+                [0x1b, b'[' | b'O', _, ..] => (None, 3),
+                [0x1b] | [0x1b, b'[' | b'O'] => break,
+                [b'a' | b'A' | b'h', ..] => (Some(Key::Left), 1),
+                [b'd' | b'D' | b'l', ..] => (Some(Key::Right), 1),
+                // q, ctrl-c, ctrl-d
+                [b'q' | b'Q' | 0x03 | 0x04, ..] => (Some(Key::Quit), 1),
+                _ => (None, 1),
+            };
+            keys.extend(key);
+            i += len;
+        }
+        self.pending.drain(..i);
+        keys
+    }
+}
+
+// One player's game: keys steer it, tick() advances it and returns the
+// frame to send. The caller owns the clock and the connection.
+pub struct RemoteGame {
+    tunnel: Tunnel<u16>,
+    builder: Seeded,
+    renderer: AnsiRenderer,
+    keys: KeyParser,
+    score: u64,
+    quit: bool,
+}
+
+impl RemoteGame {
+    pub fn new(
+        rows: u16,
+        cols: u16,
+        seed: u64,
+        difficulty: Difficulty,
+    ) -> RemoteGame {
+        let mut builder = Seeded::new(seed);
+        let tunnel =
+            Tunnel::with_config(&mut builder, rows, cols, difficulty.config());
+        RemoteGame {
+            tunnel,
+            builder,
+            renderer: AnsiRenderer::new(rows.saturating_sub(1)),
+            keys: KeyParser::default(),
+            score: 0,
+            quit: false,
+        }
+    }
+
+    // steers at once, like the local game; returns whether to keep playing
+    pub fn input(&mut self, bytes: &[u8]) -> bool {
+        for key in self.keys.feed(bytes) {
+            match key {
+                Key::Left => self.tunnel.move_player_left(),
+                Key::Right => self.tunnel.move_player_right(),
+                Key::Quit => self.quit = true,
+            }
+        }
+        !self.is_over()
+    }
+
+    pub fn frame(&mut self) -> Vec<u8> {
+        let Ok(()) = draw_frame(
+            &mut self.renderer,
+            &self.tunnel,
+            usize::MAX,
+            self.score,
+        );
+        self.renderer.take()
+    }
+
+    pub fn tick(&mut self) -> Vec<u8> {
+        if !self.is_over() {
+            self.tunnel.step(&mut self.builder);
+            if !self.tunnel.is_collision() {
+                self.score += 1;
+            }
+        }
+        self.frame()
+    }
+
+    // speed zones scroll faster by shortening the tick
+    pub fn tick_length(&self, base: Duration) -> Duration {
+        base / u32::try_from(self.tunnel.speed_multiplier()).unwrap_or(1)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.quit || self.tunnel.is_collision()
+    }
+
+    // what to print once the game is over and the screen restored
+    pub fn summary(&self) -> Vec<u8> {
+        let message = match self.quit {
+            true => "Quitting ...",
+            false => "Game over!",
+        };
+        format!("{message} Final score: {}\r\n", self.score).into_bytes()
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn renderer_draws_rows_without_redundant_moves() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u16>::new(&mut b, 5, 4);
+        let mut r = AnsiRenderer::new(4);
+        let Ok(()) = draw_frame(&mut r, &t, usize::MAX, 7);
+        let out = alloc::string::String::from_utf8(r.take()).unwrap();
+        assert!(out.starts_with("\x1b[0m\x1b[2J\x1b[1;1H"));
+        // one move per row, plus one for the score
+        assert_eq!(out.matches('H').count(), t.buffered_rows() + 1);
+        assert!(out.contains("\x1b[92mv"));
+        assert!(out.ends_with("\x1b[5;1H\x1b[92m7\x1b[0m\x1b[K"));
+        assert!(r.out.is_empty());
+    }
+
+    #[test]
+    fn renderer_redraws_only_changed_cells() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let mut t = Tunnel::<u16>::new(&mut b, 8, 10);
+        let mut r = AnsiRenderer::new(7);
+        let Ok(()) = draw_frame(&mut r, &t, usize::MAX, 0);
+        let full = r.take();
+        let Ok(()) = draw_frame(&mut r, &t, usize::MAX, 0);
+        assert_eq!(r.take(), b"\x1b[8;1H\x1b[92m0\x1b[0m\x1b[K");
+        t.step(&mut b);
+        let Ok(()) = draw_frame(&mut r, &t, usize::MAX, 1);
+        let step = r.take();
+        assert!(!step.starts_with(b"\x1b[0m\x1b[2J"));
+        assert!(step.len() < full.len());
+        r.redraw();
+        let Ok(()) = draw_frame(&mut r, &t, usize::MAX, 1);
+        assert!(r.take().starts_with(b"\x1b[0m\x1b[2J"));
+    }
+
+    #[test]
+    fn keys_survive_split_escape_sequences() {
+        let mut p = KeyParser::default();
+        assert_eq!(p.feed(b"a\x1b["), [Key::Left]);
+        assert_eq!(p.feed(b"C"), [Key::Right]);
+        assert_eq!(p.feed(b"\x1bOD\x1b[A d"), [Key::Left, Key::Right]);
+        assert_eq!(p.feed(b"\x1b"), []);
+        assert_eq!(p.feed(b"[Dq"), [Key::Left, Key::Quit]);
+        assert_eq!(p.feed(&[0x03]), [Key::Quit]);
+    }
+
+    #[test]
+    fn speed_zones_dont_look_like_the_player() {
+        let zone = RowInfo {
+            speed_zone: true,
+            ..RowInfo::default()
+        };
+        let (player, _) = glyph(TunnelCellType::Player, zone);
+        assert_ne!(glyph(TunnelCellType::Floor, zone).0, player);
+    }
+
+    #[test]
+    fn fit_clamps_terminal_sizes() {
+        assert_eq!(fit(80, 24), (80, 24));
+        assert_eq!(fit(0, 1), MIN_SIZE);
+        assert_eq!(fit(100_000, 600), MAX_SIZE);
+    }
+
+    #[test]
+    fn remote_game_plays_until_quit_or_crash() {
+        let mut g = RemoteGame::new(10, 8, 3, Difficulty::Normal);
+        let start = g.tunnel.player();
+        assert!(g.input(b"\x1b[D"));
+        assert_eq!(g.tunnel.player(), start - 1);
+        assert!(!g.tick().is_empty());
+        assert_eq!(g.score, 1);
+        assert!(!g.input(b"q"));
+        assert!(g.is_over());
+        g.tick();
+        assert_eq!(g.score, 1);
+        assert!(g.summary().starts_with(b"Quitting"));
+
+        let mut g = RemoteGame::new(10, 8, 3, Difficulty::Normal);
+        while !g.is_over() {
+            g.input(b"a");
+            g.tick();
+        }
+        assert!(g.summary().starts_with(b"Game over!"));
+    }
+}
@@ -0,0 +1,113 @@
+// A server hosting many short games would otherwise allocate and free a
+// row buffer per game. RowArena keeps finished games' buffers and hands
+// them to new ones, so once the pool is warm starting a game allocates
+// no rows at all. Only plain row storage is pooled; a config that picks
+// another storage builds its own as usual.
+
+use crate::rows::RowStore;
+use crate::{
+    RowLimitExceeded, Tunnel, TunnelBuilder, TunnelConfig, TunnelIndex,
+    TunnelWalls,
+};
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+pub struct RowArena<T> {
+    buffers: Mutex<Vec<VecDeque<TunnelWalls<T>>>>,
+}
+
+impl<T: TunnelIndex> RowArena<T> {
+    // warm the pool with `games` buffers big enough for `rows` screen rows
+    pub fn new(games: usize, rows: usize) -> RowArena<T> {
+        RowArena {
+            buffers: Mutex::new(
+                (0..games).map(|_| VecDeque::with_capacity(rows)).collect(),
+            ),
+        }
+    }
+
+    pub fn available(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn tunnel(
+        &self,
+        b: &mut impl TunnelBuilder,
+        rows: T,
+        cols: T,
+        config: TunnelConfig,
+    ) -> Result<Tunnel<T>, RowLimitExceeded> {
+        let walls = crate::row_store(&config, rows, cols, |capacity| {
+            let mut buffer = self.lock().pop().unwrap_or_default();
+            buffer.reserve(capacity);
+            buffer
+        });
+        Tunnel::build(b, rows, cols, config, walls)
+    }
+
+    // end a game, keeping its row buffer for the next one
+    pub fn recycle(&self, t: Tunnel<T>) {
+        if let RowStore::Plain(mut buffer) = t.walls {
+            buffer.clear();
+            self.lock().push(buffer);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<VecDeque<TunnelWalls<T>>>> {
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    fn buffer(t: &Tunnel<u16>) -> *const TunnelWalls<u16> {
+        match &t.walls {
+            RowStore::Plain(v) => v.as_slices().0.as_ptr(),
+            _ => panic!("arena tunnels use plain rows"),
+        }
+    }
+
+    #[test]
+    fn recycled_buffers_back_the_next_game() {
+        let arena = RowArena::new(1, 10);
+        let mut b = PatternBuilder::parse("L2 R2").unwrap();
+        let mut t = arena
+            .tunnel(&mut b, 10, 20, TunnelConfig::default())
+            .unwrap();
+        assert_eq!(arena.available(), 0);
+        let first = buffer(&t);
+        for _ in 0..30 {
+            t.step(&mut b);
+        }
+        arena.recycle(t);
+        assert_eq!(arena.available(), 1);
+
+        let mut b = PatternBuilder::parse("L2 R2").unwrap();
+        let u = arena
+            .tunnel(&mut b, 10, 20, TunnelConfig::default())
+            .unwrap();
+        assert_eq!(buffer(&u), first);
+        let mut b = PatternBuilder::parse("L2 R2").unwrap();
+        let plain =
+            Tunnel::with_config(&mut b, 10, 20, TunnelConfig::default());
+        assert!(u.iter().eq(plain.iter()));
+    }
+
+    #[test]
+    fn other_storage_is_not_pooled() {
+        let arena = RowArena::<u16>::new(1, 10);
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let config = TunnelConfig {
+            storage: crate::RowStorage::Runs,
+            ..TunnelConfig::default()
+        };
+        let t = arena.tunnel(&mut b, 10, 20, config).unwrap();
+        assert_eq!(arena.available(), 1);
+        arena.recycle(t);
+        assert_eq!(arena.available(), 1);
+    }
+}
@@ -0,0 +1,65 @@
+// ndarray exports of the screen as TunnelCellType::code()s, shaped
+// (rows, cols), for ML pipelines that batch observations as arrays.
+
+use crate::env::{Window, screen_codes, window_codes};
+use crate::{Tunnel, TunnelIndex};
+use ndarray::Array2;
+
+impl<T: TunnelIndex, const N: usize> Tunnel<T, N> {
+    pub fn to_array2(&self) -> Array2<u8> {
+        let cols = self.screen_width.to_usize().unwrap_or(0);
+        let shape = (self.buffered_rows(), cols);
+        Array2::from_shape_vec(shape, screen_codes(self))
+            .expect("one code per cell")
+    }
+
+    // the view env::Env observes through `window`: centred on the player,
+    // with columns past the screen edge as Wall
+    pub fn to_array2_window(&self, window: Window) -> Array2<u8> {
+        let shape = (window.ahead, window.width());
+        Array2::from_shape_vec(shape, window_codes(self, window))
+            .expect("one code per cell")
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use crate::env::{Env, EnvConfig, Window};
+    use crate::{Tunnel, TunnelCellType, seeded::Seeded};
+
+    #[test]
+    fn arrays_match_the_cell_iterator() {
+        let mut b = Seeded::new(4);
+        let t = Tunnel::<u8>::new(&mut b, 12, 9);
+        let a = t.to_array2();
+        assert_eq!(a.dim(), (t.buffered_rows(), 9));
+        for (row, col, cell_type) in t.iter() {
+            assert_eq!(
+                a[[usize::from(row), usize::from(col)]],
+                cell_type.code()
+            );
+        }
+        let player = usize::from(t.player());
+        assert_eq!(a[[0, player]], TunnelCellType::Player.code());
+    }
+
+    #[test]
+    fn window_matches_env_observations() {
+        let window = Window {
+            ahead: 4,
+            half_width: 6,
+        };
+        let config = EnvConfig {
+            rows: 12,
+            cols: 9,
+            window: Some(window),
+            ..EnvConfig::default()
+        };
+        let env = Env::new(config, 4);
+        let a = env.tunnel().to_array2_window(window);
+        assert_eq!(a.dim(), (4, 13));
+        assert_eq!(a.iter().copied().collect::<Vec<_>>(), env.observe().cells);
+        assert_eq!(a[[0, 6]], TunnelCellType::Player.code());
+    }
+}
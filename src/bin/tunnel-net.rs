@@ -0,0 +1,46 @@
+// Hosts a WebSocket multiplayer match; see tunnel::net for the protocol.
+//
+//   tunnel-net [--listen ADDR] [--rows N] [--cols N] [--seed N]
+//              [--difficulty NAME] [--tick-ms N]
+
+use std::env;
+use std::io;
+use std::net::TcpListener;
+use std::str::FromStr;
+use std::time::Duration;
+use tunnel::difficulty::Difficulty;
+use tunnel::net::{Match, serve};
+use tunnel::server::MAX_SIDE;
+
+fn arg_value(name: &str) -> Option<String> {
+    env::args().skip_while(|x| x != name).nth(1)
+}
+
+fn arg_or<T: FromStr<Err: ToString>>(name: &str, default: T) -> io::Result<T> {
+    match arg_value(name) {
+        Some(value) => value.parse().map_err(|e: T::Err| {
+            io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+        }),
+        None => Ok(default),
+    }
+}
+
+fn main() -> io::Result<()> {
+    let listen = arg_value("--listen").unwrap_or("127.0.0.1:7879".to_owned());
+    let rows: u16 = arg_or("--rows", 24)?;
+    let cols: u16 = arg_or("--cols", 40)?;
+    if rows > MAX_SIDE || cols > MAX_SIDE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("tunnels are at most {MAX_SIDE} a side"),
+        ));
+    }
+    let seed = arg_or("--seed", 0)?;
+    let difficulty = arg_or("--difficulty", Difficulty::Normal)?;
+    let tick = Duration::from_millis(arg_or("--tick-ms", 100)?);
+
+    let listener = TcpListener::bind(&listen)?;
+    eprintln!("hosting a match on ws://{}", listener.local_addr()?);
+    serve(listener, Match::new(rows, cols, seed, difficulty), tick);
+    Ok(())
+}
@@ -0,0 +1,27 @@
+// Hosts remote tunnel sessions; see tunnel::server for the protocol.
+//
+//   tunnel-server [--listen ADDR] [--max-sessions N]
+
+use std::env;
+use std::io;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use tunnel::server::{Server, serve};
+
+fn arg_value(name: &str) -> Option<String> {
+    env::args().skip_while(|x| x != name).nth(1)
+}
+
+fn main() -> io::Result<()> {
+    let listen = arg_value("--listen").unwrap_or("127.0.0.1:7878".to_owned());
+    let max_sessions = match arg_value("--max-sessions") {
+        Some(n) => n.parse().map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{e}"))
+        })?,
+        None => 1024,
+    };
+    let listener = TcpListener::bind(&listen)?;
+    eprintln!("serving tunnels on {}", listener.local_addr()?);
+    serve(listener, Arc::new(Mutex::new(Server::new(max_sessions))));
+    Ok(())
+}
@@ -0,0 +1,48 @@
+// Hosts the terminal game over SSH; see tunnel::ssh.
+//
+//   tunnel-ssh [--listen ADDR] [--host-key PATH] [--difficulty NAME]
+//
+// Without --host-key a fresh key is made on every start, so clients will
+// see the host key change between runs.
+
+use russh::keys::ssh_key::rand_core::OsRng;
+use russh::keys::{Algorithm, PrivateKey, load_secret_key};
+use russh::server::Server as _;
+use std::env;
+use std::io;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tunnel::ansi::GameSettings;
+use tunnel::ssh::{SshServer, config};
+
+fn arg_value(name: &str) -> Option<String> {
+    env::args().skip_while(|x| x != name).nth(1)
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+}
+
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    let listen = arg_value("--listen").unwrap_or("0.0.0.0:2222".to_owned());
+    let key = match arg_value("--host-key") {
+        Some(path) => load_secret_key(path, None).map_err(invalid)?,
+        None => PrivateKey::random(&mut OsRng, Algorithm::Ed25519)
+            .map_err(invalid)?,
+    };
+    let mut settings = GameSettings::default();
+    if let Some(d) = arg_value("--difficulty") {
+        settings.difficulty = d.parse().map_err(invalid)?;
+    }
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+
+    let listener = TcpListener::bind(&listen).await?;
+    eprintln!("serving tunnels over ssh on {}", listener.local_addr()?);
+    SshServer::new(settings, seed)
+        .run_on_socket(Arc::new(config(key)), &listener)
+        .await
+}
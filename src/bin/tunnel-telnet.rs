@@ -0,0 +1,38 @@
+// Hosts the terminal game for telnet and raw sockets; see tunnel::telnet.
+//
+//   tunnel-telnet [--listen ADDR] [--max-players N] [--difficulty NAME]
+
+use std::env;
+use std::io;
+use std::net::TcpListener;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tunnel::ansi::GameSettings;
+use tunnel::telnet::serve;
+
+fn arg_value(name: &str) -> Option<String> {
+    env::args().skip_while(|x| x != name).nth(1)
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+}
+
+fn main() -> io::Result<()> {
+    let listen = arg_value("--listen").unwrap_or("0.0.0.0:2323".to_owned());
+    let max_players = match arg_value("--max-players") {
+        Some(n) => n.parse().map_err(invalid)?,
+        None => 64,
+    };
+    let mut settings = GameSettings::default();
+    if let Some(d) = arg_value("--difficulty") {
+        settings.difficulty = d.parse().map_err(invalid)?;
+    }
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+
+    let listener = TcpListener::bind(&listen)?;
+    eprintln!("serving tunnels over telnet on {}", listener.local_addr()?);
+    serve(listener, settings, max_players, seed);
+    Ok(())
+}
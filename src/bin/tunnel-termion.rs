@@ -0,0 +1,86 @@
+// The terminal game on termion, for systems where crossterm will not build;
+// see tunnel::posix. Arrow keys, A/D or H/L steer, Q quits.
+//
+//   tunnel-termion [--seed N] [--difficulty NAME]
+
+use std::env;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::IntoAlternateScreen;
+use termion::{async_stdin, cursor, terminal_size};
+use tunnel::Tunnel;
+use tunnel::ansi::{Key, fit};
+use tunnel::difficulty::Difficulty;
+use tunnel::posix::{TermionRenderer, read_key};
+use tunnel::render::draw_frame;
+use tunnel::seeded::Seeded;
+
+// how often to look for keys while waiting out a tick
+const POLL: Duration = Duration::from_millis(5);
+
+fn arg_value(name: &str) -> Option<String> {
+    env::args().skip_while(|x| x != name).nth(1)
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+}
+
+fn main() -> io::Result<()> {
+    let seed = match arg_value("--seed") {
+        Some(seed) => seed.parse().map_err(invalid)?,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64),
+    };
+    let difficulty = match arg_value("--difficulty") {
+        Some(d) => d.parse().map_err(invalid)?,
+        None => Difficulty::Normal,
+    };
+    let (cols, rows) = terminal_size()?;
+    let (cols, rows) = fit(cols.into(), rows.into());
+
+    let mut builder = Seeded::new(seed);
+    let mut t =
+        Tunnel::with_config(&mut builder, rows, cols, difficulty.config());
+    let mut renderer = TermionRenderer {
+        out: io::stdout().into_raw_mode()?.into_alternate_screen()?,
+        score_row: rows - 1,
+    };
+    write!(renderer.out, "{}", cursor::Hide)?;
+    let mut keys = async_stdin().keys();
+    let mut score = 0;
+    let mut quit = false;
+
+    while !quit && !t.is_collision() {
+        draw_frame(&mut renderer, &t, usize::MAX, score)?;
+        // speed zones scroll faster by shortening the tick
+        let tick = difficulty.tick()
+            / u32::try_from(t.speed_multiplier()).unwrap_or(1);
+        let deadline = Instant::now() + tick;
+        // steer at once, like the crossterm game
+        while !quit && Instant::now() < deadline {
+            match keys.next().transpose()?.and_then(read_key) {
+                Some(Key::Left) => t.move_player_left(),
+                Some(Key::Right) => t.move_player_right(),
+                Some(Key::Quit) => quit = true,
+                None => thread::sleep(POLL),
+            }
+        }
+        if !quit {
+            t.step(&mut builder);
+            if !t.is_collision() {
+                score += 1;
+            }
+        }
+    }
+
+    write!(renderer.out, "{}", cursor::Show)?;
+    drop(renderer);
+    let message = if quit { "Quitting ..." } else { "Game over!" };
+    println!("{message} Final score: {score}");
+    Ok(())
+}
@@ -0,0 +1,85 @@
+// The line protocol for bots in other processes. Each tick the game sends
+// one line, the tick number, the player's column and then every row from
+// the player's outwards as TunnelCellType::code() digits, all separated by
+// spaces:
+//
+//     17 3 1002001 1000001 1100011
+//
+// and the bot answers with a line of LEFT, RIGHT, STAY, DASH_LEFT or
+// DASH_RIGHT.
+
+use crate::sim::Action;
+use crate::{Tunnel, TunnelIndex};
+use alloc::string::String;
+use core::fmt::Write;
+
+pub fn observation<T: TunnelIndex, const N: usize>(
+    t: &Tunnel<T, N>,
+    tick: u64,
+) -> String {
+    let mut line = String::new();
+    let player = t.player().to_u64().unwrap_or(u64::MAX);
+    let _ = write!(line, "{tick} {player}");
+    let mut last_row = None;
+    for (row, _, cell_type) in t.iter() {
+        if last_row != Some(row) {
+            line.push(' ');
+            last_row = Some(row);
+        }
+        line.push(char::from(b'0' + cell_type.code()));
+    }
+    line.push('\n');
+    line
+}
+
+// case and surrounding whitespace don't matter
+pub fn parse_action(line: &str) -> Option<Action> {
+    let word = line.trim();
+    [
+        Action::Left,
+        Action::Right,
+        Action::Stay,
+        Action::DashLeft,
+        Action::DashRight,
+    ]
+    .into_iter()
+    .zip(["LEFT", "RIGHT", "STAY", "DASH_LEFT", "DASH_RIGHT"])
+    .find(|(_, name)| word.eq_ignore_ascii_case(name))
+    .map(|(action, _)| action)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn observation_has_tick_player_and_every_row() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u8>::new(&mut b, 4, 5);
+        let line = observation(&t, 9);
+        let mut fields = line.trim_end().split(' ');
+        assert_eq!(fields.next(), Some("9"));
+        assert_eq!(
+            fields.next(),
+            Some(alloc::format!("{}", t.player()).as_str())
+        );
+        let rows: alloc::vec::Vec<_> = fields.collect();
+        assert_eq!(rows.len(), t.buffered_rows());
+        assert!(rows.iter().all(|row| row.len() == 5));
+        let player = usize::from(t.player());
+        assert_eq!(rows[0].as_bytes()[player], b'2');
+        assert!(line.ends_with('\n'));
+    }
+
+    #[test]
+    fn actions_ignore_case_and_whitespace() {
+        assert_eq!(parse_action("LEFT"), Some(Action::Left));
+        assert_eq!(parse_action(" right\r\n"), Some(Action::Right));
+        assert_eq!(parse_action("Stay"), Some(Action::Stay));
+        assert_eq!(parse_action("dash_right"), Some(Action::DashRight));
+        assert_eq!(parse_action("jump"), None);
+        assert_eq!(parse_action(""), None);
+    }
+}
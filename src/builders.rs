@@ -0,0 +1,898 @@
+// Wrappers that adapt an existing TunnelBuilder, so new variants of a level
+// come for free instead of each builder reimplementing them.
+
+use crate::seeded::Seeded;
+use crate::{
+    Direction, Event, Floor, RowInfo, Tunnel, TunnelBuilder,
+    TunnelBuilderChoice, TunnelIndex, Wind, one,
+};
+use alloc::{vec, vec::Vec};
+
+fn flip(choice: TunnelBuilderChoice) -> TunnelBuilderChoice {
+    match choice {
+        TunnelBuilderChoice::MoveLeftWall => TunnelBuilderChoice::MoveRightWall,
+        TunnelBuilderChoice::MoveRightWall => TunnelBuilderChoice::MoveLeftWall,
+        TunnelBuilderChoice::SteerPillarLeft => {
+            TunnelBuilderChoice::SteerPillarRight
+        }
+        TunnelBuilderChoice::SteerPillarRight => {
+            TunnelBuilderChoice::SteerPillarLeft
+        }
+        other => other,
+    }
+}
+
+fn flip_side(side: Direction) -> Direction {
+    match side {
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    }
+}
+
+pub struct Mirror<B> {
+    pub inner: B,
+}
+
+impl<B: TunnelBuilder> Mirror<B> {
+    pub fn new(inner: B) -> Mirror<B> {
+        Mirror { inner }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Mirror<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        let start = self.inner.choose_player_start(max);
+        max.saturating_sub(one()).saturating_sub(start)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        flip(self.inner.choose_step())
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        flip(self.inner.choose_step_for(t))
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        let mut info = self.inner.row_info();
+        info.wind = info.wind.map(|wind| Wind {
+            direction: flip_side(wind.direction),
+            ..wind
+        });
+        info
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        match self.inner.event()? {
+            Event::CaveIn {
+                side,
+                width,
+                rows,
+                warning,
+            } => Some(Event::CaveIn {
+                side: flip_side(side),
+                width,
+                rows,
+                warning,
+            }),
+        }
+    }
+}
+
+// Vetoes any choice after which a one-column-per-step player could no longer
+// reach the newest row, substituting the first choice that keeps the tunnel
+// winnable.
+pub struct Solvable<B> {
+    pub inner: B,
+    pub vetoes: usize,
+}
+
+impl<B: TunnelBuilder> Solvable<B> {
+    pub fn new(inner: B) -> Solvable<B> {
+        Solvable { inner, vetoes: 0 }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Solvable<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.inner.choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        let choice = self.inner.choose_step_for(t);
+        if !t.is_survivable() || t.is_survivable_after(choice) {
+            return choice;
+        }
+        let safe = [
+            TunnelBuilderChoice::KeepWalls,
+            TunnelBuilderChoice::MergeCorridor,
+            TunnelBuilderChoice::MoveLeftWall,
+            TunnelBuilderChoice::MoveRightWall,
+        ]
+        .into_iter()
+        .find(|c| t.is_survivable_after(*c));
+        match safe {
+            Some(safe) => {
+                self.vetoes += 1;
+                safe
+            }
+            None => choice,
+        }
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        self.inner.row_info()
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Eases off after close calls and tightens up while the player cruises:
+// `heat` out of `max_heat` is the share of the inner builder's choices that
+// pass through, the rest hold the corridor steady.
+pub struct Adaptive<B> {
+    pub inner: B,
+    pub max_heat: u32,
+    pub cruise_rows: u32,
+    heat: u32,
+    cruising: u32,
+    accumulator: u32,
+}
+
+impl<B: TunnelBuilder> Adaptive<B> {
+    pub fn new(inner: B) -> Adaptive<B> {
+        Adaptive {
+            inner,
+            max_heat: 4,
+            cruise_rows: 20,
+            heat: 2,
+            cruising: 0,
+            accumulator: 0,
+        }
+    }
+
+    pub fn heat(&self) -> u32 {
+        self.heat
+    }
+
+    pub fn report_near_miss(&mut self) {
+        self.heat = self.heat.saturating_sub(1);
+        self.cruising = 0;
+    }
+
+    fn report_cruising(&mut self) {
+        self.cruising += 1;
+        if self.cruising >= self.cruise_rows {
+            self.heat = self.max_heat.min(self.heat + 1);
+            self.cruising = 0;
+        }
+    }
+
+    fn temper(&mut self, choice: TunnelBuilderChoice) -> TunnelBuilderChoice {
+        self.accumulator += self.heat.min(self.max_heat);
+        if self.accumulator >= self.max_heat {
+            self.accumulator -= self.max_heat;
+            choice
+        } else {
+            TunnelBuilderChoice::KeepWalls
+        }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Adaptive<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        let choice = self.inner.choose_step();
+        self.temper(choice)
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        match t.clearance() {
+            Some(c) if c.is_zero() => self.report_near_miss(),
+            Some(_) => self.report_cruising(),
+            None => {}
+        }
+        let choice = self.inner.choose_step_for(t);
+        self.temper(choice)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        self.inner.row_info()
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Hands generation to one builder after another, each for a fixed number of
+// steps (calm, narrow, boss corridor, relief, ...); the last one runs for
+// the rest of the tunnel. Rows are tagged with the index of the segment that
+// built them.
+pub struct Segments<B> {
+    segments: Vec<(usize, B)>,
+    current: usize,
+    taken: usize,
+}
+
+impl<B: TunnelBuilder> Segments<B> {
+    pub fn new(steps: usize, first: B) -> Segments<B> {
+        Segments {
+            segments: vec![(steps, first)],
+            current: 0,
+            taken: 0,
+        }
+    }
+
+    pub fn then(mut self, steps: usize, next: B) -> Segments<B> {
+        self.segments.push((steps, next));
+        self
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    fn advance(&mut self) -> &mut B {
+        let last = self.segments.len() - 1;
+        while self.current < last && self.taken >= self.segments[self.current].0
+        {
+            self.current += 1;
+            self.taken = 0;
+        }
+        self.taken += 1;
+        &mut self.segments[self.current].1
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Segments<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.segments[0].1.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.advance().choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        self.advance().choose_step_for(t)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        let mut info = self.segments[self.current].1.row_info();
+        info.segment = self.current;
+        info
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.segments[self.current].1.event()
+    }
+}
+
+// Marks (or unmarks) every row the inner builder produces as a speed zone.
+pub struct SpeedZone<B> {
+    pub inner: B,
+    pub enabled: bool,
+}
+
+impl<B: TunnelBuilder> SpeedZone<B> {
+    pub fn new(inner: B, enabled: bool) -> SpeedZone<B> {
+        SpeedZone { inner, enabled }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for SpeedZone<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.inner.choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        self.inner.choose_step_for(t)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        let mut info = self.inner.row_info();
+        info.speed_zone = self.enabled;
+        info
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Lays the given floor under every row the inner builder produces.
+pub struct Surface<B> {
+    pub inner: B,
+    pub floor: Floor,
+}
+
+impl<B: TunnelBuilder> Surface<B> {
+    pub fn new(inner: B, floor: Floor) -> Surface<B> {
+        Surface { inner, floor }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Surface<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.inner.choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        self.inner.choose_step_for(t)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        let mut info = self.inner.row_info();
+        info.floor = self.floor;
+        info
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Blows the given wind (or calm, with None) across every row the inner
+// builder produces.
+pub struct Windy<B> {
+    pub inner: B,
+    pub wind: Option<Wind>,
+}
+
+impl<B: TunnelBuilder> Windy<B> {
+    pub fn new(inner: B, wind: Option<Wind>) -> Windy<B> {
+        Windy { inner, wind }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Windy<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.inner.choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        self.inner.choose_step_for(t)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        let mut info = self.inner.row_info();
+        info.wind = self.wind;
+        info
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Drops a fuel pickup on every `every`th row the inner builder produces
+// (zero behaves like one).
+pub struct FuelDrops<B> {
+    pub inner: B,
+    pub every: usize,
+    rows: usize,
+}
+
+impl<B: TunnelBuilder> FuelDrops<B> {
+    pub fn new(inner: B, every: usize) -> FuelDrops<B> {
+        FuelDrops {
+            inner,
+            every,
+            rows: 0,
+        }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for FuelDrops<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.inner.choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        self.inner.choose_step_for(t)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        let mut info = self.inner.row_info();
+        self.rows += 1;
+        info.fuel_pickup = self.rows.is_multiple_of(self.every.max(1));
+        info
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Drops a coin on about `percent` rows in a hundred, those without another
+// pickup, from a stream of its own seeded with `seed`: the walls stay the
+// inner builder's, coins or not.
+#[derive(Clone)]
+pub struct CoinDrops<B> {
+    pub inner: B,
+    pub percent: u8,
+    coins: Seeded,
+}
+
+impl<B: TunnelBuilder> CoinDrops<B> {
+    pub fn new(inner: B, percent: u8, seed: u64) -> CoinDrops<B> {
+        CoinDrops {
+            inner,
+            percent,
+            coins: Seeded::new(seed),
+        }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for CoinDrops<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.inner.choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        self.inner.choose_step_for(t)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        let mut info = self.inner.row_info();
+        let roll = self.coins.next() % 100;
+        info.coin = roll < u64::from(self.percent)
+            && !info.fuel_pickup
+            && info.key.is_none()
+            && info.door.is_none();
+        info
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Splits the corridor around a pillar (or widens the one there) on about
+// `percent` steps in a hundred, from a stream of its own seeded with `seed`,
+// in place of the inner builder's own splits. Where the inner builder
+// merges, say to keep a stretch of ice whole, it still does.
+#[derive(Clone)]
+pub struct Pillars<B> {
+    pub inner: B,
+    pub percent: u8,
+    splits: Seeded,
+}
+
+impl<B: TunnelBuilder> Pillars<B> {
+    pub fn new(inner: B, percent: u8, seed: u64) -> Pillars<B> {
+        Pillars {
+            inner,
+            percent,
+            splits: Seeded::new(seed),
+        }
+    }
+
+    fn split(&mut self, choice: TunnelBuilderChoice) -> TunnelBuilderChoice {
+        let roll = self.splits.next() % 100;
+        match choice {
+            TunnelBuilderChoice::MergeCorridor => choice,
+            _ if roll < u64::from(self.percent) => {
+                TunnelBuilderChoice::SplitCorridor
+            }
+            TunnelBuilderChoice::SplitCorridor => {
+                TunnelBuilderChoice::KeepWalls
+            }
+            _ => choice,
+        }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Pillars<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        let choice = self.inner.choose_step();
+        self.split(choice)
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        let choice = self.inner.choose_step_for(t);
+        self.split(choice)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        self.inner.row_info()
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// Fires each scheduled event once the inner builder has produced that many
+// rows; the tunnel delays any that land while another event is running.
+pub struct Events<B> {
+    pub inner: B,
+    schedule: Vec<(usize, Event)>,
+    rows: usize,
+}
+
+impl<B: TunnelBuilder> Events<B> {
+    pub fn new(
+        inner: B,
+        schedule: impl IntoIterator<Item = (usize, Event)>,
+    ) -> Events<B> {
+        let mut schedule: Vec<_> = schedule.into_iter().collect();
+        // latest first, so the next event due is always at the back
+        schedule.sort_by_key(|(row, _)| core::cmp::Reverse(*row));
+        Events {
+            inner,
+            schedule,
+            rows: 0,
+        }
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Events<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        self.inner.choose_step()
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        self.inner.choose_step_for(t)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        self.rows += 1;
+        self.inner.row_info()
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        match self.schedule.last() {
+            Some((row, _)) if *row <= self.rows => {
+                self.schedule.pop().map(|(_, event)| event)
+            }
+            _ => self.inner.event(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::lookahead::{DEPTH, best_action};
+    use crate::pattern::PatternBuilder;
+    use TunnelBuilderChoice::{KeepWalls, MoveLeftWall, MoveRightWall};
+
+    #[test]
+    fn mirror_flips_every_choice() {
+        let mut b = Mirror::new(PatternBuilder::parse("L R K").unwrap());
+        let steps: Vec<_> = (0..3).map(|_| b.choose_step()).collect();
+        assert_eq!(steps, vec![MoveRightWall, MoveLeftWall, KeepWalls]);
+    }
+
+    #[test]
+    fn mirror_reflects_player_start() {
+        struct Start(u8);
+        impl TunnelBuilder for Start {
+            fn choose_player_start<T: TunnelIndex>(&mut self, _: T) -> T {
+                self.0.into()
+            }
+            fn choose_step(&mut self) -> TunnelBuilderChoice {
+                KeepWalls
+            }
+        }
+        assert_eq!(Mirror::new(Start(1)).choose_player_start::<u8>(10), 8);
+        assert_eq!(Mirror::new(Start(0)).choose_player_start::<u8>(10), 9);
+        assert_eq!(Mirror::new(Start(4)).choose_player_start::<u8>(0), 0);
+        assert_eq!(Mirror::new(Start(12)).choose_player_start::<u8>(10), 0);
+    }
+
+    #[test]
+    fn mirror_forwards_tunnel_context() {
+        let mut b =
+            Mirror::new(Solvable::new(PatternBuilder::parse("L").unwrap()));
+        let t = Tunnel::<u8>::new(&mut b, 6, 6);
+        assert_eq!(b.choose_step_for(&t), MoveRightWall);
+    }
+
+    #[test]
+    fn solvable_passes_through_safe_choices() {
+        let pattern = PatternBuilder::parse("L4 R4 K").unwrap();
+        let mut plain = pattern.clone();
+        let mut b = Solvable::new(pattern);
+        let mut t = Tunnel::<u8>::new(&mut b, 10, 10);
+        let mut same = Tunnel::<u8>::new(&mut plain, 10, 10);
+        for _ in 0..40 {
+            let action = best_action(&t, DEPTH);
+            action.apply(&mut t);
+            action.apply(&mut same);
+            t.step(&mut b);
+            same.step(&mut plain);
+            assert!(!t.is_collision());
+            assert!(t.is_survivable());
+            assert!(t.iter().eq(same.iter()));
+        }
+        assert_eq!(b.vetoes, 0);
+        assert_eq!(b.choose_player_start::<u8>(10), 5);
+        assert_eq!(b.choose_step(), plain.choose_step());
+    }
+
+    #[test]
+    fn adaptive_heat_scales_pass_through_rate() {
+        let mut b = Adaptive::new(PatternBuilder::parse("L").unwrap());
+        let passed = |b: &mut Adaptive<PatternBuilder>| {
+            (0..8).filter(|_| b.choose_step() == MoveLeftWall).count()
+        };
+        assert_eq!(b.heat(), 2);
+        assert_eq!(passed(&mut b), 4);
+        b.report_near_miss();
+        b.report_near_miss();
+        b.report_near_miss();
+        assert_eq!(b.heat(), 0);
+        assert_eq!(passed(&mut b), 0);
+        b.heat = b.max_heat;
+        assert_eq!(passed(&mut b), 8);
+    }
+
+    #[test]
+    fn adaptive_reads_player_clearance() {
+        let mut b = Adaptive::new(PatternBuilder::parse("K").unwrap());
+        b.cruise_rows = 2;
+        let mut t = Tunnel::<u8>::new(&mut b.inner.clone(), 4, 9);
+        b.choose_step_for(&t);
+        b.choose_step_for(&t);
+        assert_eq!(b.heat(), 3);
+        for _ in 0..3 {
+            t.move_player_left();
+        }
+        assert_eq!(t.clearance(), Some(0));
+        b.choose_step_for(&t);
+        assert_eq!(b.heat(), 2);
+        t.move_player_left();
+        b.choose_step_for(&t);
+        assert_eq!(b.heat(), 2);
+        assert_eq!(b.choose_player_start::<u8>(9), 4);
+    }
+
+    #[test]
+    fn segments_switch_builders_at_thresholds() {
+        let mut b = Segments::new(2, PatternBuilder::parse("L").unwrap())
+            .then(3, PatternBuilder::parse("R").unwrap())
+            .then(1, PatternBuilder::parse("K").unwrap());
+        let steps: Vec<_> = (0..8)
+            .map(|_| (b.choose_step(), b.row_info().segment))
+            .collect();
+        let expected = vec![
+            (MoveLeftWall, 0),
+            (MoveLeftWall, 0),
+            (MoveRightWall, 1),
+            (MoveRightWall, 1),
+            (MoveRightWall, 1),
+            (KeepWalls, 2),
+            (KeepWalls, 2),
+            (KeepWalls, 2),
+        ];
+        assert_eq!(expected, steps);
+        assert_eq!(b.current(), 2);
+    }
+
+    #[test]
+    fn segments_tag_tunnel_rows() {
+        let mut b = Segments::new(3, PatternBuilder::parse("K").unwrap())
+            .then(2, PatternBuilder::parse("K").unwrap());
+        let mut t = Tunnel::<u8>::new(&mut b, 6, 20);
+        let segments = |t: &Tunnel<u8>| -> Vec<usize> {
+            (0..4).map(|r| t.row_info(r).unwrap().segment).collect()
+        };
+        assert_eq!(segments(&t), vec![0, 0, 0, 0]);
+        t.step(&mut b);
+        t.step(&mut b);
+        assert_eq!(segments(&t), vec![0, 0, 1, 1]);
+        assert_eq!(t.row_info(4), None);
+
+        let mut wrapped = Solvable::new(Adaptive::new(Mirror::new(b)));
+        assert_eq!(wrapped.row_info().segment, 1);
+    }
+
+    #[test]
+    fn tagging_wrappers_stack() {
+        let wind = Wind {
+            direction: crate::Direction::Right,
+            strength: 1,
+        };
+        let mut b = Windy::new(
+            Surface::new(
+                SpeedZone::new(PatternBuilder::parse("K").unwrap(), true),
+                Floor::Ice,
+            ),
+            Some(wind),
+        );
+        assert_eq!(b.choose_step(), KeepWalls);
+        let expected = RowInfo {
+            segment: 0,
+            speed_zone: true,
+            floor: Floor::Ice,
+            wind: Some(wind),
+            fuel_pickup: false,
+            coin: false,
+            key: None,
+            door: None,
+            hazard: None,
+        };
+        assert_eq!(b.row_info(), expected);
+    }
+
+    #[test]
+    fn fuel_drops_tag_every_nth_row() {
+        let mut b = FuelDrops::new(PatternBuilder::parse("K").unwrap(), 3);
+        let drops: Vec<_> = (0..6).map(|_| b.row_info().fuel_pickup).collect();
+        assert_eq!(drops, vec![false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn coin_drops_leave_the_walls_alone() {
+        let mut plain = FuelDrops::new(Seeded::new(4), 7);
+        let mut coins =
+            CoinDrops::new(FuelDrops::new(Seeded::new(4), 7), 30, 9);
+        let plain_steps: Vec<_> =
+            (0..200).map(|_| plain.choose_step()).collect();
+        let coin_steps: Vec<_> =
+            (0..200).map(|_| coins.choose_step()).collect();
+        assert_eq!(plain_steps, coin_steps);
+        let infos: Vec<_> = (0..200).map(|_| coins.row_info()).collect();
+        let dropped = infos.iter().filter(|info| info.coin).count();
+        assert!((30..90).contains(&dropped), "{dropped} coins");
+        assert!(!infos.iter().any(|info| info.coin && info.fuel_pickup));
+        let mut none = CoinDrops::new(Seeded::new(4), 0, 9);
+        assert!(!(0..200).any(|_| none.row_info().coin));
+    }
+
+    #[test]
+    fn pillars_come_as_often_as_asked() {
+        let pillar_rows = |percent| {
+            let mut b = Pillars::new(Seeded::new(4), percent, 2);
+            let mut t: Tunnel<u16> = Tunnel::new(&mut b, 40, 20);
+            let mut rows = 0;
+            for _ in 0..300 {
+                // there's always a way around them
+                best_action(&t, DEPTH).apply(&mut t);
+                t.step(&mut b);
+                assert!(!t.is_collision());
+                rows += usize::from(t.branch().is_some());
+            }
+            rows
+        };
+        assert_eq!(pillar_rows(0), 0);
+        assert!(pillar_rows(5) < pillar_rows(40));
+        // merges the inner builder asks for still come through
+        let mut b = Pillars::new(PatternBuilder::parse("M").unwrap(), 100, 2);
+        assert_eq!(b.choose_step(), TunnelBuilderChoice::MergeCorridor);
+    }
+
+    #[test]
+    fn events_fire_on_schedule_and_mirror() {
+        let cave_in = |side| Event::CaveIn {
+            side,
+            width: 1,
+            rows: 2,
+            warning: 3,
+        };
+        let mut b = Mirror::new(Events::new(
+            PatternBuilder::parse("K").unwrap(),
+            [
+                (3, cave_in(Direction::Right)),
+                (1, cave_in(Direction::Left)),
+            ],
+        ));
+        assert_eq!(b.event(), None);
+        b.row_info();
+        assert_eq!(b.event(), Some(cave_in(Direction::Right)));
+        assert_eq!(b.event(), None);
+        b.row_info();
+        b.row_info();
+        assert_eq!(b.event(), Some(cave_in(Direction::Left)));
+        b.row_info();
+        assert_eq!(b.event(), None);
+    }
+
+    #[test]
+    fn mirrored_wind_blows_the_other_way() {
+        fn drift(b: &mut impl TunnelBuilder) -> i32 {
+            let mut t = Tunnel::<u16>::new(b, 6, 21);
+            let start = i32::from(t.player());
+            for _ in 0..5 {
+                t.step(b);
+            }
+            i32::from(t.player()) - start
+        }
+        let wind = Some(Wind {
+            direction: Direction::Right,
+            strength: Wind::GUST,
+        });
+        let windy = || Windy::new(PatternBuilder::parse("K").unwrap(), wind);
+        let pushed = drift(&mut windy());
+        assert!(pushed > 0, "pushed {pushed}");
+        assert_eq!(drift(&mut Mirror::new(windy())), -pushed);
+    }
+
+    #[test]
+    fn mirror_of_mirror_is_identity() {
+        let pattern = PatternBuilder::parse("L3 R2 K <>=").unwrap();
+        let mut plain = pattern.clone();
+        let mut twice = Mirror::new(Mirror::new(pattern));
+        for _ in 0..20 {
+            assert_eq!(plain.choose_step(), twice.choose_step());
+        }
+        assert_eq!(
+            plain.choose_player_start::<u16>(80),
+            twice.choose_player_start::<u16>(80)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+// Campaigns: a directory of level files (see levels), played in file name
+// order, each finished by surviving to its exit (LevelSpec::exit_row), and
+// how far a player has got through each, kept between sessions in a file
+// of a line per campaign, the levels cleared and then the directory:
+//
+//     3 /home/player/tunnel/levels
+//
+// Lines that don't parse are left out, like the high-score file's.
+
+use crate::levels::{self, LevelError, LevelFormat, LevelSpec};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Campaign {
+    // the directory, as Progress knows it
+    pub name: String,
+    pub levels: Vec<LevelSpec>,
+}
+
+impl Campaign {
+    // every .ron and .json file in `dir`, in order of file name
+    pub fn load(dir: impl AsRef<Path>) -> Result<Campaign, LevelError> {
+        let dir = dir.as_ref().canonicalize()?;
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let level_file = match path.extension().and_then(|e| e.to_str()) {
+                Some(ext) => ["ron", "json"]
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(ext)),
+                None => false,
+            };
+            if level_file && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let levels = paths
+            .iter()
+            .map(|path| {
+                levels::from_str(
+                    &fs::read_to_string(path)?,
+                    LevelFormat::from_path(path),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if levels.is_empty() {
+            return Err(LevelError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no level files in {}", dir.display()),
+            )));
+        }
+        Ok(Campaign {
+            name: dir.display().to_string(),
+            levels,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    campaigns: Vec<(String, usize)>,
+}
+
+impl Progress {
+    pub fn parse(text: &str) -> Progress {
+        let campaigns = text
+            .lines()
+            .filter_map(|line| {
+                let (cleared, name) = line.split_once(' ')?;
+                Some((name.to_string(), cleared.parse().ok()?))
+            })
+            .collect();
+        Progress { campaigns }
+    }
+
+    // levels of the campaign `name` cleared so far
+    pub fn cleared(&self, name: &str) -> usize {
+        self.campaigns
+            .iter()
+            .find(|(n, _)| n == name)
+            .map_or(0, |&(_, cleared)| cleared)
+    }
+
+    // records `cleared` levels of the campaign `name` as cleared, unless
+    // more already were
+    pub fn clear(&mut self, name: &str, cleared: usize) {
+        match self.campaigns.iter_mut().find(|(n, _)| n == name) {
+            Some((_, best)) => *best = cleared.max(*best),
+            None => self.campaigns.push((name.to_string(), cleared)),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (name, cleared) in &self.campaigns {
+            let _ = writeln!(text, "{cleared} {name}");
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::levels::LevelLayout;
+
+    fn level(name: &str) -> LevelSpec {
+        LevelSpec {
+            name: name.to_string(),
+            author: None,
+            par_score: None,
+            rows: 10,
+            cols: 20,
+            layout: LevelLayout::Pattern(String::from("L2 R2")),
+            length: Some(50),
+        }
+    }
+
+    #[test]
+    fn levels_load_in_file_name_order() {
+        let dir = std::env::temp_dir()
+            .join(format!("tunnel-campaign-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        levels::save(&level("second"), dir.join("2.json")).unwrap();
+        levels::save(&level("first"), dir.join("1.ron")).unwrap();
+        fs::write(dir.join("notes.txt"), "not a level").unwrap();
+        let campaign = Campaign::load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<_> = campaign
+            .unwrap()
+            .levels
+            .into_iter()
+            .map(|l| l.name)
+            .collect();
+        assert_eq!(names, ["first", "second"]);
+        assert!(Campaign::load(&dir).is_err());
+    }
+
+    #[test]
+    fn progress_only_goes_forward() {
+        let mut progress = Progress::parse("2 /a b/levels\nbad\n-1 /c\n");
+        assert_eq!(progress.cleared("/a b/levels"), 2);
+        assert_eq!(progress.cleared("/c"), 0);
+        progress.clear("/a b/levels", 1);
+        progress.clear("/d", 4);
+        assert_eq!(progress.cleared("/a b/levels"), 2);
+        assert_eq!(progress.to_text(), "2 /a b/levels\n4 /d\n");
+        assert_eq!(Progress::parse(&progress.to_text()), progress);
+    }
+}
@@ -0,0 +1,90 @@
+// Asciinema v2 recordings of a run: a header line, then one line per frame
+// of ANSI output as [seconds, "o", text]. The frames are the ones ansi
+// sends to remote terminals, so a recording looks like the game.
+
+use crate::Tunnel;
+use crate::ansi::AnsiRenderer;
+use crate::render::draw_frame;
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+use core::time::Duration;
+
+// the terminal a player needs to replay a cols x rows game
+pub fn header(cols: u16, rows: u16) -> String {
+    format!("{{\"version\":2,\"width\":{cols},\"height\":{rows}}}\n")
+}
+
+// `at` is the time since the recording began
+pub fn output_event(at: Duration, data: &[u8]) -> String {
+    let mut line =
+        format!("[{}.{:06},\"o\",\"", at.as_secs(), at.subsec_micros());
+    for c in String::from_utf8_lossy(data).chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(line, "\\u{:04x}", u32::from(c));
+            }
+            c => line.push(c),
+        }
+    }
+    line.push_str("\"]\n");
+    line
+}
+
+// turns frames into event lines; write header() first
+pub struct Recorder {
+    renderer: AnsiRenderer,
+}
+
+impl Recorder {
+    pub fn new(rows: u16) -> Recorder {
+        Recorder {
+            renderer: AnsiRenderer::new(rows.saturating_sub(1)),
+        }
+    }
+
+    pub fn frame(
+        &mut self,
+        at: Duration,
+        t: &Tunnel<u16>,
+        fog: usize,
+        score: u64,
+    ) -> String {
+        let Ok(()) = draw_frame(&mut self.renderer, t, fog, score);
+        output_event(at, &self.renderer.take())
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn events_escape_terminal_output() {
+        assert_eq!(
+            header(80, 24),
+            "{\"version\":2,\"width\":80,\"height\":24}\n"
+        );
+        assert_eq!(
+            output_event(Duration::from_millis(1500), b"\x1b[2J\"a\\b\"\r\n"),
+            "[1.500000,\"o\",\"\\u001b[2J\\\"a\\\\b\\\"\\r\\n\"]\n"
+        );
+    }
+
+    #[test]
+    fn recorder_emits_one_line_per_frame() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u16>::new(&mut b, 5, 4);
+        let mut r = Recorder::new(5);
+        let line = r.frame(Duration::ZERO, &t, usize::MAX, 3);
+        assert!(line.starts_with("[0.000000,\"o\",\"\\u001b[0m"));
+        assert!(line.ends_with("3\\u001b[0m\\u001b[K\"]\n"));
+        assert_eq!(line.matches('\n').count(), 1);
+    }
+}
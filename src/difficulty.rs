@@ -0,0 +1,224 @@
+// Shared difficulty presets, so every frontend agrees on what "hard" means.
+
+use crate::{MaxSlope, TunnelConfig};
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Difficulty {
+    Chill,
+    #[default]
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Chill,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Nightmare,
+    ];
+
+    pub fn config(self) -> TunnelConfig {
+        let (min_gap, narrow_every, grace_rows, max_slope) = match self {
+            Difficulty::Chill => (5, 4, 20, Some((1, 3))),
+            Difficulty::Normal => (3, 2, 10, Some((1, 2))),
+            Difficulty::Hard => (2, 1, 5, None),
+            Difficulty::Nightmare => (1, 1, 0, None),
+        };
+        TunnelConfig {
+            max_slope: max_slope.map(|(shift, rows)| MaxSlope { shift, rows }),
+            grace_rows,
+            min_gap,
+            narrow_every,
+            ..TunnelConfig::default()
+        }
+    }
+
+    // time between tunnel steps, for frontends that scroll on a timer
+    pub fn tick(self) -> Duration {
+        Duration::from_millis(match self {
+            Difficulty::Chill => 400,
+            Difficulty::Normal => 250,
+            Difficulty::Hard => 150,
+            Difficulty::Nightmare => 90,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Chill => "chill",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Nightmare => "nightmare",
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Speeding up as the score grows: every `every` points is another level,
+// each scrolling `percent` faster than the last, down to `floor`. An
+// `every` of 0 keeps the game at level 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progression {
+    pub every: u64,
+    pub percent: u32,
+    pub floor: Duration,
+}
+
+impl Default for Progression {
+    fn default() -> Progression {
+        Progression {
+            every: 100,
+            percent: 10,
+            floor: Duration::from_millis(40),
+        }
+    }
+}
+
+impl Progression {
+    pub fn level(&self, score: u64) -> u64 {
+        match self.every {
+            0 => 1,
+            every => score / every + 1,
+        }
+    }
+
+    // `base` at level 1; a base already under the floor never slows down
+    pub fn tick(&self, base: Duration, score: u64) -> Duration {
+        let floor = self.floor.min(base);
+        let keep = 100 - self.percent.min(100);
+        let mut tick = base;
+        for _ in 1..self.level(score) {
+            if tick <= floor || keep == 100 {
+                break;
+            }
+            tick = tick * keep / 100;
+        }
+        tick.max(floor)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownDifficulty(pub String);
+
+impl fmt::Display for UnknownDifficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown difficulty {:?}", self.0)
+    }
+}
+
+impl core::error::Error for UnknownDifficulty {}
+
+impl FromStr for Difficulty {
+    type Err = UnknownDifficulty;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|d| d.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownDifficulty(s.to_string()))
+    }
+}
+
+impl From<Difficulty> for u8 {
+    fn from(d: Difficulty) -> u8 {
+        d as u8
+    }
+}
+
+impl TryFrom<u8> for Difficulty {
+    type Error = UnknownDifficulty;
+
+    fn try_from(n: u8) -> Result<Self, Self::Error> {
+        Difficulty::ALL
+            .get(usize::from(n))
+            .copied()
+            .ok_or_else(|| UnknownDifficulty(n.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::Tunnel;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn presets_get_strictly_harder() {
+        for pair in Difficulty::ALL.windows(2) {
+            let (easier, harder) = (pair[0].config(), pair[1].config());
+            assert!(easier.min_gap >= harder.min_gap);
+            assert!(easier.narrow_every >= harder.narrow_every);
+            assert!(easier.grace_rows > harder.grace_rows);
+            assert!(pair[0].tick() > pair[1].tick());
+        }
+    }
+
+    #[test]
+    fn progression_speeds_up_to_the_floor() {
+        let p = Progression::default();
+        let base = Duration::from_millis(200);
+        assert_eq!(p.level(99), 1);
+        assert_eq!(p.level(100), 2);
+        assert_eq!(p.tick(base, 99), base);
+        assert_eq!(p.tick(base, 100), Duration::from_millis(180));
+        assert_eq!(p.tick(base, 250), Duration::from_millis(162));
+        assert_eq!(p.tick(base, u64::MAX), p.floor);
+        assert_eq!(
+            p.tick(Duration::from_millis(20), 500),
+            Duration::from_millis(20)
+        );
+        let flat = Progression { every: 0, ..p };
+        assert_eq!(flat.level(1000), 1);
+        assert_eq!(flat.tick(base, 1000), base);
+    }
+
+    #[test]
+    fn names_and_bytes_round_trip() {
+        for d in Difficulty::ALL {
+            assert_eq!(d.to_string().parse(), Ok(d));
+            assert_eq!(Difficulty::try_from(u8::from(d)), Ok(d));
+        }
+        assert_eq!("HARD".parse(), Ok(Difficulty::Hard));
+        assert_eq!(
+            "easy".parse::<Difficulty>().unwrap_err().to_string(),
+            "unknown difficulty \"easy\""
+        );
+        assert!(Difficulty::try_from(4).is_err());
+        assert_eq!(Difficulty::default(), Difficulty::Normal);
+    }
+
+    #[test]
+    fn gap_never_drops_below_preset_floor() {
+        for d in Difficulty::ALL {
+            let mut b = PatternBuilder::parse("L3 R5 K").unwrap();
+            let mut t = Tunnel::<u16>::with_config(&mut b, 30, 40, d.config());
+            let min_gap = d.config().min_gap;
+            for _ in 0..200 {
+                t.step(&mut b);
+                assert!(
+                    t.walls
+                        .iter()
+                        .all(|w| usize::from(w.gap_to_right_wall) >= min_gap)
+                );
+            }
+            assert!(
+                t.walls
+                    .iter()
+                    .all(|w| usize::from(w.gap_to_right_wall) == min_gap)
+            );
+        }
+    }
+}
@@ -0,0 +1,266 @@
+// The reset/step loop reinforcement-learning code expects: reset(seed)
+// starts a fresh Seeded tunnel, step(action) moves, advances one row and
+// scores the result. Observations are TunnelCellType::code()s, row-major,
+// either the whole screen or a window that follows the player.
+
+use crate::seeded::Seeded;
+use crate::sim::Action;
+use crate::{Tunnel, TunnelCellType, TunnelConfig, TunnelIndex};
+use alloc::vec::Vec;
+
+// the rows just ahead of the player, `half_width` columns either side of
+// them; columns past the screen edge read as Wall
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Window {
+    pub ahead: usize,
+    pub half_width: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rewards {
+    // for every step survived
+    pub alive: f32,
+    // instead of `alive` on the step that crashes
+    pub crash: f32,
+    // times the floor cells between the player and the nearest wall
+    pub clearance: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            alive: 1.,
+            crash: -1.,
+            clearance: 0.,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvConfig {
+    pub rows: u16,
+    pub cols: u16,
+    pub tunnel: TunnelConfig,
+    pub window: Option<Window>,
+    pub rewards: Rewards,
+    // end episodes that survive this long
+    pub max_steps: Option<usize>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> EnvConfig {
+        EnvConfig {
+            rows: 24,
+            cols: 40,
+            tunnel: TunnelConfig::default(),
+            window: None,
+            rewards: Rewards::default(),
+            max_steps: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub cells: Vec<u8>,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+pub struct Env {
+    config: EnvConfig,
+    tunnel: Tunnel<u16>,
+    builder: Seeded,
+    steps: usize,
+    done: bool,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig, seed: u64) -> Env {
+        let mut builder = Seeded::new(seed);
+        let tunnel = Tunnel::with_config(
+            &mut builder,
+            config.rows,
+            config.cols,
+            config.tunnel.clone(),
+        );
+        Env {
+            config,
+            tunnel,
+            builder,
+            steps: 0,
+            done: false,
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        let config = self.config.clone();
+        *self = Env::new(config, seed);
+        self.observe()
+    }
+
+    // once done, further steps change nothing and score zero
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        if self.done {
+            return (self.observe(), 0., true);
+        }
+        action.apply(&mut self.tunnel);
+        self.tunnel.step(&mut self.builder);
+        self.steps += 1;
+        let rewards = self.config.rewards;
+        let reward = match self.tunnel.clearance() {
+            _ if self.tunnel.is_collision() => rewards.crash,
+            Some(clear) => rewards.alive + rewards.clearance * f32::from(clear),
+            None => rewards.alive,
+        };
+        self.done = self.tunnel.is_collision()
+            || self.config.max_steps.is_some_and(|max| self.steps >= max);
+        (self.observe(), reward, self.done)
+    }
+
+    pub fn tunnel(&self) -> &Tunnel<u16> {
+        &self.tunnel
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn observe(&self) -> Observation {
+        match self.config.window {
+            Some(window) => Observation {
+                cells: window_codes(&self.tunnel, window),
+                rows: window.ahead,
+                cols: window.width(),
+            },
+            None => {
+                let cells = screen_codes(&self.tunnel);
+                let cols = usize::from(self.tunnel.screen_width);
+                Observation {
+                    rows: cells.len() / cols.max(1),
+                    cols,
+                    cells,
+                }
+            }
+        }
+    }
+}
+
+impl Window {
+    pub fn width(&self) -> usize {
+        2 * usize::from(self.half_width) + 1
+    }
+}
+
+pub(crate) fn screen_codes<T: TunnelIndex, const N: usize>(
+    t: &Tunnel<T, N>,
+) -> Vec<u8> {
+    t.iter().map(|(_, _, cell_type)| cell_type.code()).collect()
+}
+
+// `window.ahead` rows of `window.width()` codes, row-major
+pub(crate) fn window_codes<T: TunnelIndex, const N: usize>(
+    t: &Tunnel<T, N>,
+    window: Window,
+) -> Vec<u8> {
+    let cells = screen_codes(t);
+    let cols = t.screen_width.to_usize().unwrap_or(0);
+    let player = t.player().to_usize().unwrap_or(0);
+    let half = usize::from(window.half_width);
+    let width = window.width();
+    let outside = TunnelCellType::Wall.code();
+    let mut windowed = Vec::with_capacity(window.ahead * width);
+    for row in 0..window.ahead {
+        for col in (player..player + width).map(|c| c.checked_sub(half)) {
+            let code = match col {
+                Some(col) if col < cols => cells.get(row * cols + col),
+                _ => None,
+            };
+            windowed.push(code.copied().unwrap_or(outside));
+        }
+    }
+    windowed
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_replays_the_same_episode() {
+        let mut env = Env::new(EnvConfig::default(), 0);
+        let play = |env: &mut Env| {
+            let first = env.reset(9);
+            let steps: Vec<_> =
+                (0..20).map(|_| env.step(Action::Stay)).collect();
+            (first, steps)
+        };
+        let a = play(&mut env);
+        assert_eq!(a, play(&mut env));
+        assert_eq!(a.0.cells.len(), a.0.rows * 40);
+        assert_ne!(a.0, env.reset(10));
+    }
+
+    #[test]
+    fn crashing_ends_the_episode() {
+        let config = EnvConfig {
+            rewards: Rewards {
+                alive: 0.5,
+                crash: -10.,
+                clearance: 0.,
+            },
+            ..EnvConfig::default()
+        };
+        let mut env = Env::new(config, 3);
+        let mut last = (env.observe(), 0., false);
+        while !last.2 {
+            last = env.step(Action::Left);
+            assert!(last.1 == 0.5 || last.2);
+        }
+        assert_eq!(last.1, -10.);
+        assert!(env.tunnel().is_collision());
+        let steps = env.steps();
+        assert_eq!(env.step(Action::Right).1, 0.);
+        assert_eq!(env.steps(), steps);
+    }
+
+    #[test]
+    fn max_steps_and_clearance_shape_the_episode() {
+        let config = EnvConfig {
+            rewards: Rewards {
+                alive: 0.,
+                crash: -1.,
+                clearance: 1.,
+            },
+            max_steps: Some(2),
+            ..EnvConfig::default()
+        };
+        let mut env = Env::new(config, 3);
+        let (_, reward, done) = env.step(Action::Stay);
+        let clearance = env.tunnel().clearance().unwrap();
+        assert_eq!(reward, f32::from(clearance));
+        assert!(!done);
+        assert!(env.step(Action::Stay).2);
+    }
+
+    #[test]
+    fn window_follows_the_player_and_pads_with_wall() {
+        let config = EnvConfig {
+            rows: 10,
+            cols: 8,
+            window: Some(Window {
+                ahead: 3,
+                half_width: 5,
+            }),
+            ..EnvConfig::default()
+        };
+        let env = Env::new(config, 1);
+        let obs = env.observe();
+        assert_eq!((obs.rows, obs.cols), (3, 11));
+        assert_eq!(obs.cells.len(), 33);
+        assert_eq!(obs.cells[5], TunnelCellType::Player.code());
+        // the player starts mid-screen, so the far left is off the edge
+        assert_eq!(obs.cells[0], TunnelCellType::Wall.code());
+    }
+}
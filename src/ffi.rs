@@ -0,0 +1,143 @@
+// A C ABI for embedding the engine: an opaque TunnelHandle grown by a Seeded
+// builder, driven one call at a time. include/tunnel.h declares it; build
+// the shared library with `cargo rustc --lib --release --no-default-features
+// --features ffi --crate-type cdylib`. Handles are not thread-safe: use one
+// from one thread at a time. Null handles are ignored.
+
+use crate::Tunnel;
+use crate::difficulty::Difficulty;
+use crate::seeded::Seeded;
+use alloc::boxed::Box;
+
+pub struct TunnelHandle {
+    tunnel: Tunnel<u16>,
+    builder: Seeded,
+}
+
+// `difficulty` indexes Difficulty::ALL; null if it is out of range
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_new(
+    rows: u16,
+    cols: u16,
+    seed: u64,
+    difficulty: u8,
+) -> Option<Box<TunnelHandle>> {
+    let difficulty = Difficulty::ALL.get(usize::from(difficulty))?;
+    let mut builder = Seeded::new(seed);
+    let tunnel =
+        Tunnel::try_with_config(&mut builder, rows, cols, difficulty.config())
+            .ok()?;
+    Some(Box::new(TunnelHandle { tunnel, builder }))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_free(_: Option<Box<TunnelHandle>>) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_step(h: Option<&mut TunnelHandle>) {
+    if let Some(h) = h {
+        h.tunnel.step(&mut h.builder);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_move_left(h: Option<&mut TunnelHandle>) {
+    if let Some(h) = h {
+        h.tunnel.move_player_left();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_move_right(h: Option<&mut TunnelHandle>) {
+    if let Some(h) = h {
+        h.tunnel.move_player_right();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_is_collision(h: Option<&TunnelHandle>) -> bool {
+    h.is_some_and(|h| h.tunnel.is_collision())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_player(h: Option<&TunnelHandle>) -> u16 {
+    h.map_or(0, |h| h.tunnel.player())
+}
+
+// the grid tunnel_cells() fills is rows x cols
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_rows(h: Option<&TunnelHandle>) -> usize {
+    h.map_or(0, |h| h.tunnel.buffered_rows())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn tunnel_cols(h: Option<&TunnelHandle>) -> u16 {
+    h.map_or(0, |h| h.tunnel.screen_width)
+}
+
+// Writes TunnelCellType::code()s row-major into `buf`, up to `len` of them,
+// and returns how many the whole grid needs.
+// Safety: `buf` must be valid for writes of `len` bytes, or null.
+#[allow(clippy::missing_safety_doc)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tunnel_cells(
+    h: Option<&TunnelHandle>,
+    buf: *mut u8,
+    len: usize,
+) -> usize {
+    let Some(h) = h else {
+        return 0;
+    };
+    let mut needed = 0;
+    for (_, _, cell_type) in h.tunnel.iter() {
+        if needed < len && !buf.is_null() {
+            unsafe { buf.add(needed).write(cell_type.code()) };
+        }
+        needed += 1;
+    }
+    needed
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use core::ptr;
+
+    #[test]
+    fn handles_step_and_fill_caller_buffers() {
+        let mut h = tunnel_new(12, 9, 5, 1).unwrap();
+        let start = tunnel_player(Some(&h));
+        tunnel_move_left(Some(&mut h));
+        tunnel_step(Some(&mut h));
+        assert_eq!(tunnel_player(Some(&h)), start - 1);
+        assert!(!tunnel_is_collision(Some(&h)));
+
+        let cells = tunnel_rows(Some(&h)) * usize::from(tunnel_cols(Some(&h)));
+        assert_eq!(
+            unsafe { tunnel_cells(Some(&h), ptr::null_mut(), 0) },
+            cells
+        );
+        // a short buffer gets a prefix and nothing past its end
+        let mut buf = vec![0xff; cells + 1];
+        let short = unsafe { tunnel_cells(Some(&h), buf.as_mut_ptr(), 9) };
+        assert_eq!(short, cells);
+        assert_eq!(buf[9], 0xff);
+        unsafe { tunnel_cells(Some(&h), buf.as_mut_ptr(), cells) };
+        let codes = h.tunnel.iter().map(|(_, _, c)| c.code());
+        assert!(codes.eq(buf[..cells].iter().copied()));
+        assert_eq!(buf[cells], 0xff);
+        tunnel_free(Some(h));
+    }
+
+    #[test]
+    fn bad_arguments_give_null_and_defaults() {
+        assert!(tunnel_new(12, 9, 5, 4).is_none());
+        tunnel_step(None);
+        assert!(!tunnel_is_collision(None));
+        assert_eq!(tunnel_rows(None), 0);
+        assert_eq!(unsafe { tunnel_cells(None, ptr::null_mut(), 0) }, 0);
+        tunnel_free(None);
+    }
+}
@@ -0,0 +1,79 @@
+// A tunnel whose size is fixed at compile time. Its rows live in an inline
+// ring of ROWS rows rather than on the heap, for embedded targets and for
+// hot loops that simulate many short games.
+
+use crate::rows::RowStore;
+use crate::{Tunnel, TunnelBuilder, TunnelConfig, TunnelIndex};
+use core::ops::{Deref, DerefMut};
+
+pub struct TunnelFixed<T, const ROWS: usize, const COLS: usize>(
+    Tunnel<T, ROWS>,
+);
+
+impl<T: TunnelIndex, const ROWS: usize, const COLS: usize>
+    TunnelFixed<T, ROWS, COLS>
+{
+    // iter() never yields more cells than this
+    pub const MAX_CELLS: usize = ROWS * COLS;
+
+    pub fn new(b: &mut impl TunnelBuilder) -> Self {
+        TunnelFixed::with_config(b, TunnelConfig::default())
+    }
+
+    pub fn with_config(
+        b: &mut impl TunnelBuilder,
+        config: TunnelConfig,
+    ) -> Self {
+        let size = |n| T::from_usize(n).unwrap_or_else(T::max_value);
+        let walls = RowStore::fixed();
+        let t = Tunnel::build(b, size(ROWS), size(COLS), config, walls);
+        TunnelFixed(t.expect("screen rows exceed config.max_rows"))
+    }
+}
+
+impl<T, const ROWS: usize, const COLS: usize> Deref
+    for TunnelFixed<T, ROWS, COLS>
+{
+    type Target = Tunnel<T, ROWS>;
+
+    fn deref(&self) -> &Tunnel<T, ROWS> {
+        &self.0
+    }
+}
+
+impl<T, const ROWS: usize, const COLS: usize> DerefMut
+    for TunnelFixed<T, ROWS, COLS>
+{
+    fn deref_mut(&mut self) -> &mut Tunnel<T, ROWS> {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::builders::Solvable;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn fixed_tunnel_matches_heap_tunnel() {
+        let pattern = PatternBuilder::parse("L3 R5 S K2 { } M").unwrap();
+        let mut heap_builder = Solvable::new(pattern.clone());
+        let mut heap = Tunnel::<u16>::new(&mut heap_builder, 12, 20);
+        let mut fixed_builder = Solvable::new(pattern);
+        let mut fixed = TunnelFixed::<u16, 12, 20>::new(&mut fixed_builder);
+        for _ in 0..60 {
+            assert!(heap.iter().eq(fixed.iter()));
+            assert!(fixed.fork().iter().eq(fixed.iter()));
+            assert!(
+                fixed.iter().count() <= TunnelFixed::<u16, 12, 20>::MAX_CELLS
+            );
+            heap.move_player_right();
+            fixed.move_player_right();
+            heap.step(&mut heap_builder);
+            fixed.step(&mut fixed_builder);
+        }
+        assert_eq!(heap.is_collision(), fixed.is_collision());
+    }
+}
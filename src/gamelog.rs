@@ -0,0 +1,146 @@
+// The game-event log: what happened in a run, as one JSON object per line
+// ({"ms":..,"tick":..,"event":..} and the event's fields), for analysis
+// tools and bug reports that need the exact timeline. Frontends build the
+// entries as they play and decide where the lines go.
+
+use crate::sim::Action;
+use crate::{Tunnel, TunnelIndex};
+use alloc::format;
+use alloc::string::String;
+use core::time::Duration;
+
+// a score milestone every this many points
+pub const MILESTONE: u64 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    // the row that last scrolled in at the far end; `right` is the column
+    // of the right wall, `pillar` its first column and width
+    Row {
+        left: u64,
+        right: u64,
+        pillar: Option<(u64, u64)>,
+    },
+    Input(Action),
+    Collision {
+        player: u64,
+    },
+    Milestone {
+        score: u64,
+    },
+}
+
+impl GameEvent {
+    pub fn newest_row<T: TunnelIndex, const N: usize>(
+        t: &Tunnel<T, N>,
+    ) -> Option<GameEvent> {
+        let walls = t.walls.back()?;
+        let index = |i: T| i.to_u64().unwrap_or(u64::MAX);
+        let left = index(walls.left_wall);
+        Some(GameEvent::Row {
+            left,
+            right: left
+                .saturating_add(index(walls.gap_to_right_wall))
+                .saturating_add(1),
+            pillar: walls
+                .pillar
+                .map(|(start, width)| (index(start), index(width))),
+        })
+    }
+
+    pub fn milestone(score: u64) -> Option<GameEvent> {
+        (score > 0 && score.is_multiple_of(MILESTONE))
+            .then_some(GameEvent::Milestone { score })
+    }
+
+    // `at` is the time since the run began, `tick` the steps taken so far
+    pub fn json_line(&self, at: Duration, tick: u64) -> String {
+        let fields = match *self {
+            GameEvent::Row {
+                left,
+                right,
+                pillar,
+            } => {
+                let pillar = match pillar {
+                    Some((start, width)) => format!("[{start},{width}]"),
+                    None => String::from("null"),
+                };
+                format!(
+                    "\"event\":\"row\",\"left\":{left},\"right\":{right},\
+                     \"pillar\":{pillar}"
+                )
+            }
+            GameEvent::Input(action) => {
+                let action = match action {
+                    Action::Stay => "stay",
+                    Action::Left => "left",
+                    Action::Right => "right",
+                    Action::DashLeft => "dash_left",
+                    Action::DashRight => "dash_right",
+                };
+                format!("\"event\":\"input\",\"action\":\"{action}\"")
+            }
+            GameEvent::Collision { player } => {
+                format!("\"event\":\"collision\",\"player\":{player}")
+            }
+            GameEvent::Milestone { score } => {
+                format!("\"event\":\"milestone\",\"score\":{score}")
+            }
+        };
+        format!("{{\"ms\":{},\"tick\":{tick},{fields}}}\n", at.as_millis())
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn newest_row_is_the_far_end() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let mut t = Tunnel::<u8>::new(&mut b, 6, 9);
+        t.step(&mut b);
+        let far = (t.buffered_rows() - 1) as u8;
+        let walls: alloc::vec::Vec<_> = t
+            .iter()
+            .filter(|&(row, _, ty)| {
+                row == far && ty == crate::TunnelCellType::Wall
+            })
+            .map(|(_, col, _)| u64::from(col))
+            .collect();
+        let Some(GameEvent::Row { left, right, .. }) =
+            GameEvent::newest_row(&t)
+        else {
+            panic!("no rows");
+        };
+        assert!(walls.contains(&left) && walls.contains(&right));
+        assert!(walls.iter().all(|&col| col <= left || col >= right));
+    }
+
+    #[test]
+    fn lines_are_json_objects() {
+        let at = Duration::from_millis(1500);
+        assert_eq!(
+            GameEvent::Input(Action::Left).json_line(at, 15),
+            "{\"ms\":1500,\"tick\":15,\"event\":\"input\",\"action\":\"left\"}\n"
+        );
+        let row = GameEvent::Row {
+            left: 2,
+            right: 9,
+            pillar: Some((5, 1)),
+        };
+        assert_eq!(
+            row.json_line(Duration::ZERO, 0),
+            "{\"ms\":0,\"tick\":0,\"event\":\"row\",\"left\":2,\"right\":9,\
+             \"pillar\":[5,1]}\n"
+        );
+        assert_eq!(GameEvent::milestone(0), None);
+        assert_eq!(GameEvent::milestone(150), None);
+        assert_eq!(
+            GameEvent::milestone(200),
+            Some(GameEvent::Milestone { score: 200 })
+        );
+    }
+}
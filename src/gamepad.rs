@@ -0,0 +1,100 @@
+// Gamepads through gilrs, as keymap commands, for couch play: the d-pad
+// and left stick steer, Start pauses and Select quits; in menus the d-pad
+// moves and the south face button (A, or cross) selects. A frontend polls
+// next_command() alongside its keyboard.
+
+use crate::keymap::Command;
+use gilrs::{Axis, Button, Event, EventType, Gilrs};
+
+// how far the stick tilts before it steers
+pub const DEADZONE: f32 = 0.5;
+
+pub fn button_command(button: Button) -> Option<Command> {
+    match button {
+        Button::DPadLeft => Some(Command::Left),
+        Button::DPadRight => Some(Command::Right),
+        Button::DPadUp => Some(Command::Up),
+        Button::DPadDown => Some(Command::Down),
+        Button::South => Some(Command::Select),
+        Button::Start => Some(Command::Pause),
+        Button::Select => Some(Command::Quit),
+        _ => None,
+    }
+}
+
+// a stick steers once as it tilts out of the deadzone, like a d-pad press;
+// it has to come back before it steers again
+#[derive(Default)]
+pub struct Stick {
+    tilt: Option<Command>,
+}
+
+impl Stick {
+    pub fn moved(&mut self, x: f32) -> Option<Command> {
+        let tilt = match x {
+            x if x <= -DEADZONE => Some(Command::Left),
+            x if x >= DEADZONE => Some(Command::Right),
+            _ => None,
+        };
+        let changed = tilt != self.tilt;
+        self.tilt = tilt;
+        tilt.filter(|_| changed)
+    }
+}
+
+pub struct Gamepads {
+    gilrs: Gilrs,
+    stick: Stick,
+}
+
+impl Gamepads {
+    pub fn new() -> Result<Gamepads, gilrs::Error> {
+        Ok(Gamepads {
+            gilrs: Gilrs::new()?,
+            stick: Stick::default(),
+        })
+    }
+
+    // the next command from any gamepad, without waiting for one
+    pub fn next_command(&mut self) -> Option<Command> {
+        while let Some(Event { event, .. }) = self.gilrs.next_event() {
+            let command = match event {
+                EventType::ButtonPressed(button, _) => button_command(button),
+                EventType::AxisChanged(Axis::LeftStickX, x, _) => {
+                    self.stick.moved(x)
+                }
+                _ => None,
+            };
+            if command.is_some() {
+                return command;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dpad_steers_and_start_pauses() {
+        assert_eq!(button_command(Button::DPadLeft), Some(Command::Left));
+        assert_eq!(button_command(Button::DPadRight), Some(Command::Right));
+        assert_eq!(button_command(Button::Start), Some(Command::Pause));
+        assert_eq!(button_command(Button::South), Some(Command::Select));
+        assert_eq!(button_command(Button::West), None);
+    }
+
+    #[test]
+    fn stick_steers_once_per_tilt() {
+        let mut stick = Stick::default();
+        assert_eq!(stick.moved(0.2), None);
+        assert_eq!(stick.moved(-0.8), Some(Command::Left));
+        assert_eq!(stick.moved(-1.0), None);
+        assert_eq!(stick.moved(0.9), Some(Command::Right));
+        assert_eq!(stick.moved(0.0), None);
+        assert_eq!(stick.moved(0.6), Some(Command::Right));
+    }
+}
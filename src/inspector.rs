@@ -0,0 +1,262 @@
+// egui panels for tuning a builder while it runs: where the walls of each
+// buffered row are, how wide the gaps come out, what the builder chose
+// lately, and the TunnelConfig knobs that only shape rows still to come, so
+// changing them mid-run is safe. examples/inspector.rs puts them next to a
+// running game.
+
+use crate::{
+    Event, MaxSlope, RowInfo, Tunnel, TunnelBuilder, TunnelBuilderChoice,
+    TunnelCellType, TunnelConfig, TunnelIndex,
+};
+use egui::{Grid, Rect, Sense, Slider, Ui, pos2, vec2};
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+// how many choices a Recording keeps
+pub const HISTORY: usize = 64;
+
+// wraps a builder to remember its latest choices, oldest first
+pub struct Recording<B> {
+    pub inner: B,
+    history: VecDeque<TunnelBuilderChoice>,
+}
+
+impl<B> Recording<B> {
+    pub fn new(inner: B) -> Recording<B> {
+        Recording {
+            inner,
+            history: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    pub fn history(&self) -> &VecDeque<TunnelBuilderChoice> {
+        &self.history
+    }
+
+    fn record(&mut self, choice: TunnelBuilderChoice) -> TunnelBuilderChoice {
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(choice);
+        choice
+    }
+}
+
+impl<B: TunnelBuilder> TunnelBuilder for Recording<B> {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        self.inner.choose_player_start(max)
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        let choice = self.inner.choose_step();
+        self.record(choice)
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        let choice = self.inner.choose_step_for(t);
+        self.record(choice)
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        self.inner.row_info()
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        self.inner.event()
+    }
+}
+
+// `left` and `right` are the columns of the walls around a row's floor,
+// `gap` how many floor columns lie between them (a pillar is not floor)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RowBounds {
+    pub left: usize,
+    pub right: usize,
+    pub gap: usize,
+}
+
+// every buffered row, front row first
+pub fn row_bounds<T: TunnelIndex, const N: usize>(
+    t: &Tunnel<T, N>,
+) -> Vec<RowBounds> {
+    let mut rows: Vec<Option<RowBounds>> = Vec::new();
+    for (row, col, cell_type) in t.iter() {
+        let row = row.to_usize().unwrap_or(0);
+        let col = col.to_usize().unwrap_or(0);
+        if rows.len() <= row {
+            rows.push(None);
+        }
+        if matches!(cell_type, TunnelCellType::Wall | TunnelCellType::Pillar) {
+            continue;
+        }
+        let bounds = rows[row].get_or_insert(RowBounds {
+            left: col.saturating_sub(1),
+            ..RowBounds::default()
+        });
+        bounds.right = col + 1;
+        bounds.gap += 1;
+    }
+    rows.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+// how many rows have each gap width, indexed by width
+pub fn gap_histogram(rows: &[RowBounds]) -> Vec<usize> {
+    let widest = rows.iter().map(|r| r.gap).max().unwrap_or(0);
+    let mut counts = vec![0; widest + 1];
+    for r in rows {
+        counts[r.gap] += 1;
+    }
+    counts
+}
+
+// the inspector, inside any egui container
+pub fn show<T: TunnelIndex, const N: usize>(
+    ui: &mut Ui,
+    t: &mut Tunnel<T, N>,
+    history: &VecDeque<TunnelBuilderChoice>,
+) {
+    let cols = t.screen_width.to_usize().unwrap_or(0);
+    let rows = row_bounds(t);
+    ui.collapsing("Tuning", |ui| tuning(ui, &mut t.config, cols));
+    ui.collapsing("Gap widths", |ui| histogram(ui, &gap_histogram(&rows)));
+    ui.collapsing("Wall bounds", |ui| {
+        Grid::new("wall bounds").striped(true).show(ui, |ui| {
+            for label in ["row", "left", "right", "gap"] {
+                ui.strong(label);
+            }
+            ui.end_row();
+            for (row, r) in rows.iter().enumerate() {
+                for value in [row, r.left, r.right, r.gap] {
+                    ui.label(value.to_string());
+                }
+                ui.end_row();
+            }
+        });
+    });
+    ui.collapsing("Builder choices", |ui| choices(ui, history));
+}
+
+fn tuning(ui: &mut Ui, config: &mut TunnelConfig, cols: usize) {
+    ui.add(Slider::new(&mut config.min_gap, 0..=cols).text("min gap"));
+    ui.add(Slider::new(&mut config.narrow_every, 0..=200).text("narrow every"));
+    ui.add(Slider::new(&mut config.grace_rows, 0..=200).text("grace rows"));
+    let mut limited = config.max_slope.is_some();
+    ui.checkbox(&mut limited, "max slope");
+    config.max_slope = match (limited, config.max_slope) {
+        (false, _) => None,
+        (true, slope) => Some(slope.unwrap_or(MaxSlope { shift: 1, rows: 1 })),
+    };
+    if let Some(slope) = &mut config.max_slope {
+        ui.add(Slider::new(&mut slope.shift, 0..=cols).text("shift"));
+        ui.add(Slider::new(&mut slope.rows, 1..=50).text("per rows"));
+    }
+}
+
+fn histogram(ui: &mut Ui, counts: &[usize]) {
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+    let size = vec2(ui.available_width(), 80.);
+    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let rect = response.rect;
+    let bar = rect.width() / counts.len().max(1) as f32;
+    let fill = ui.visuals().selection.bg_fill;
+    for (gap, &n) in counts.iter().enumerate() {
+        let x = rect.left() + bar * gap as f32;
+        let top = rect.bottom() - rect.height() * n as f32 / most as f32;
+        let bar = Rect::from_min_max(
+            pos2(x, top),
+            pos2(x + (bar - 1.).max(1.), rect.bottom()),
+        );
+        painter.rect_filled(bar, 0., fill);
+    }
+    ui.label(format!(
+        "gaps 0 to {} columns wide; the tallest bar is {most} rows",
+        counts.len().saturating_sub(1)
+    ));
+}
+
+fn choices(ui: &mut Ui, history: &VecDeque<TunnelBuilderChoice>) {
+    let mut counts: Vec<(TunnelBuilderChoice, usize)> = Vec::new();
+    for &choice in history {
+        match counts.iter_mut().find(|(c, _)| *c == choice) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((choice, 1)),
+        }
+    }
+    for (choice, n) in counts {
+        ui.label(format!("{choice:?}: {n}"));
+    }
+    ui.separator();
+    ui.label("latest first");
+    ui.horizontal_wrapped(|ui| {
+        for choice in history.iter().rev() {
+            ui.monospace(format!("{choice:?}"));
+        }
+    });
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn recording_keeps_the_latest_choices() {
+        let mut b = Recording::new(PatternBuilder::parse("L1 R2").unwrap());
+        let mut t = Tunnel::<u16>::new(&mut b, 6, 9);
+        for _ in 0..HISTORY {
+            t.step(&mut b);
+        }
+        assert_eq!(b.history().len(), HISTORY);
+        let rights = b
+            .history()
+            .iter()
+            .filter(|&&c| c == TunnelBuilderChoice::MoveRightWall);
+        assert!(
+            (HISTORY * 2 / 3..=HISTORY * 2 / 3 + 1).contains(&rights.count())
+        );
+    }
+
+    #[test]
+    fn bounds_and_histogram_follow_the_walls() {
+        let mut b = PatternBuilder::parse("K1").unwrap();
+        let t = Tunnel::<u16>::new(&mut b, 4, 7);
+        let rows = row_bounds(&t);
+        assert_eq!(rows.len(), t.buffered_rows());
+        for (row, r) in rows.iter().enumerate() {
+            let floor = t
+                .iter()
+                .filter(|&(y, _, c)| {
+                    usize::from(y) == row
+                        && !matches!(
+                            c,
+                            TunnelCellType::Wall | TunnelCellType::Pillar
+                        )
+                })
+                .count();
+            assert_eq!(r.gap, floor);
+            assert!(r.left < r.right);
+        }
+        let counts = gap_histogram(&rows);
+        assert_eq!(counts.iter().sum::<usize>(), rows.len());
+        assert_eq!(gap_histogram(&[]), [0]);
+    }
+
+    #[test]
+    fn panels_draw_and_tune_headless() {
+        let mut b = Recording::new(PatternBuilder::parse("L1 R1").unwrap());
+        let mut t = Tunnel::<u16>::new(&mut b, 6, 9);
+        t.step(&mut b);
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                show(ui, &mut t, b.history());
+                tuning(ui, &mut t.config, 9);
+            });
+        });
+        assert_eq!(t.config().max_slope, None);
+    }
+}
@@ -0,0 +1,339 @@
+// Which keys do what, for frontends that let players rebind them. A keymap
+// starts from a preset and a config file can rebind any command, one line
+// per command, replacing the preset's keys for it:
+//
+//     # move with z and x, pause on the space bar
+//     left = z, Left
+//     right = x, Right
+//     pause = space
+//
+// Keys are single characters, ctrl-<char>, or one of left, right,
+// shift-left, shift-right, up, down, enter, esc, tab and space. Commands
+// are left, right, dash_left, dash_right, quit, pause, restart and rewind
+// (for practice), and up, down and select for menus.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyName {
+    Char(char),
+    Ctrl(char),
+    Left,
+    Right,
+    ShiftLeft,
+    ShiftRight,
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Tab,
+}
+
+impl KeyName {
+    pub fn parse(name: &str) -> Option<KeyName> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(KeyName::Char(c));
+        }
+        let lower = name.to_ascii_lowercase();
+        if let Some(c) = lower.strip_prefix("ctrl-") {
+            let mut chars = c.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(KeyName::Ctrl(c)),
+                _ => None,
+            };
+        }
+        Some(match lower.as_str() {
+            "left" => KeyName::Left,
+            "right" => KeyName::Right,
+            "shift-left" => KeyName::ShiftLeft,
+            "shift-right" => KeyName::ShiftRight,
+            "up" => KeyName::Up,
+            "down" => KeyName::Down,
+            "enter" => KeyName::Enter,
+            "esc" => KeyName::Esc,
+            "tab" => KeyName::Tab,
+            "space" => KeyName::Char(' '),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    Left,
+    Right,
+    DashLeft,
+    DashRight,
+    Quit,
+    Pause,
+    Restart,
+    Rewind,
+    Up,
+    Down,
+    Select,
+}
+
+impl Command {
+    fn parse(name: &str) -> Option<Command> {
+        Some(match name {
+            "left" => Command::Left,
+            "right" => Command::Right,
+            "dash_left" => Command::DashLeft,
+            "dash_right" => Command::DashRight,
+            "quit" => Command::Quit,
+            "pause" => Command::Pause,
+            "restart" => Command::Restart,
+            "rewind" => Command::Rewind,
+            "up" => Command::Up,
+            "down" => Command::Down,
+            "select" => Command::Select,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeymapError {
+    MissingEquals { line: usize },
+    UnknownCommand { line: usize, found: String },
+    UnknownKey { line: usize, found: String },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::MissingEquals { line } => {
+                write!(f, "line {line}: expected `command = keys`")
+            }
+            KeymapError::UnknownCommand { line, found } => {
+                write!(f, "line {line}: unknown command {found:?}")
+            }
+            KeymapError::UnknownKey { line, found } => {
+                write!(f, "line {line}: unknown key {found:?}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for KeymapError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(KeyName, Command)>,
+}
+
+// the names preset() knows, the default first
+pub const PRESETS: [&str; 2] = ["arrows", "wasd"];
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::preset("arrows").expect("arrows is a preset")
+    }
+}
+
+impl Keymap {
+    pub fn preset(name: &str) -> Option<Keymap> {
+        let moves: &[(KeyName, Command)] = match name {
+            // with a and d for terminals where arrows misbehave in raw mode
+            "arrows" => &[
+                (KeyName::Left, Command::Left),
+                (KeyName::Right, Command::Right),
+                (KeyName::Char('a'), Command::Left),
+                (KeyName::Char('d'), Command::Right),
+                (KeyName::ShiftLeft, Command::DashLeft),
+                (KeyName::ShiftRight, Command::DashRight),
+            ],
+            "wasd" => &[
+                (KeyName::Char('a'), Command::Left),
+                (KeyName::Char('d'), Command::Right),
+                (KeyName::Char('A'), Command::DashLeft),
+                (KeyName::Char('D'), Command::DashRight),
+            ],
+            _ => return None,
+        };
+        let mut bindings = moves.to_vec();
+        // vim's h and l everywhere; j and k stay unbound, saved for up and
+        // down should the game ever move that way
+        bindings.extend([
+            (KeyName::Char('h'), Command::Left),
+            (KeyName::Char('l'), Command::Right),
+            (KeyName::Char('H'), Command::DashLeft),
+            (KeyName::Char('L'), Command::DashRight),
+            (KeyName::Char('q'), Command::Quit),
+            (KeyName::Char('c'), Command::Quit),
+            (KeyName::Ctrl('c'), Command::Quit),
+            (KeyName::Char('p'), Command::Pause),
+            (KeyName::Char('r'), Command::Restart),
+            (KeyName::Char('u'), Command::Rewind),
+            (KeyName::Up, Command::Up),
+            (KeyName::Down, Command::Down),
+            (KeyName::Enter, Command::Select),
+        ]);
+        Some(Keymap { bindings })
+    }
+
+    // rebinds the commands `config` names, leaving the rest as they were
+    pub fn apply(&mut self, config: &str) -> Result<(), KeymapError> {
+        for (i, line) in config.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.split_once('#').map_or(line, |(line, _)| line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (command, keys) = line
+                .split_once('=')
+                .ok_or(KeymapError::MissingEquals { line: line_no })?;
+            let command = command.trim();
+            let command = Command::parse(command).ok_or_else(|| {
+                KeymapError::UnknownCommand {
+                    line: line_no,
+                    found: command.to_string(),
+                }
+            })?;
+            let keys = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| {
+                    KeyName::parse(key).ok_or_else(|| KeymapError::UnknownKey {
+                        line: line_no,
+                        found: key.to_string(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            self.bindings.retain(|&(_, c)| c != command);
+            self.bindings
+                .extend(keys.into_iter().map(|key| (key, command)));
+        }
+        Ok(())
+    }
+
+    pub fn command(&self, key: KeyName) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|&&(k, _)| k == key)
+            .map(|&(_, command)| command)
+    }
+}
+
+// two players at one keyboard: the first moves with a and d (A and D
+// dash), the second with the arrows (shift dashes); either one can pause
+// or quit, and the config file doesn't rebind them
+pub fn versus() -> [Keymap; 2] {
+    let shared = [
+        (KeyName::Char('q'), Command::Quit),
+        (KeyName::Ctrl('c'), Command::Quit),
+        (KeyName::Esc, Command::Quit),
+        (KeyName::Char('p'), Command::Pause),
+        (KeyName::Char(' '), Command::Pause),
+        (KeyName::Char('r'), Command::Restart),
+    ];
+    let one = [
+        (KeyName::Char('a'), Command::Left),
+        (KeyName::Char('d'), Command::Right),
+        (KeyName::Char('A'), Command::DashLeft),
+        (KeyName::Char('D'), Command::DashRight),
+    ];
+    let two = [
+        (KeyName::Left, Command::Left),
+        (KeyName::Right, Command::Right),
+        (KeyName::ShiftLeft, Command::DashLeft),
+        (KeyName::ShiftRight, Command::DashRight),
+    ];
+    [one, two].map(|moves| Keymap {
+        bindings: moves.into_iter().chain(shared).collect(),
+    })
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_move_quit_and_pause() {
+        let arrows = Keymap::default();
+        assert_eq!(arrows.command(KeyName::Left), Some(Command::Left));
+        assert_eq!(arrows.command(KeyName::Char('a')), Some(Command::Left));
+        assert_eq!(arrows.command(KeyName::Char('d')), Some(Command::Right));
+        assert_eq!(arrows.command(KeyName::Ctrl('c')), Some(Command::Quit));
+        let wasd = Keymap::preset("wasd").unwrap();
+        assert_eq!(wasd.command(KeyName::Char('d')), Some(Command::Right));
+        assert_eq!(wasd.command(KeyName::Left), None);
+        assert_eq!(wasd.command(KeyName::Char('p')), Some(Command::Pause));
+        assert_eq!(wasd.command(KeyName::Char('r')), Some(Command::Restart));
+        assert_eq!(wasd.command(KeyName::Char('u')), Some(Command::Rewind));
+        assert_eq!(wasd.command(KeyName::Enter), Some(Command::Select));
+        for name in PRESETS {
+            let keys = Keymap::preset(name).unwrap();
+            assert_eq!(keys.command(KeyName::Char('h')), Some(Command::Left));
+            assert_eq!(keys.command(KeyName::Char('l')), Some(Command::Right));
+            let dash = keys.command(KeyName::Char('L'));
+            assert_eq!(dash, Some(Command::DashRight));
+            assert_eq!(keys.command(KeyName::Char('j')), None);
+            assert_eq!(keys.command(KeyName::Char('k')), None);
+        }
+        assert_eq!(Keymap::preset("dvorak"), None);
+    }
+
+    #[test]
+    fn versus_splits_the_moves_and_shares_the_rest() {
+        let [one, two] = versus();
+        assert_eq!(one.command(KeyName::Char('a')), Some(Command::Left));
+        assert_eq!(one.command(KeyName::Left), None);
+        assert_eq!(two.command(KeyName::Left), Some(Command::Left));
+        assert_eq!(two.command(KeyName::Char('d')), None);
+        for keys in [one, two] {
+            assert_eq!(keys.command(KeyName::Char('p')), Some(Command::Pause));
+            assert_eq!(keys.command(KeyName::Char('q')), Some(Command::Quit));
+        }
+    }
+
+    #[test]
+    fn config_rebinds_only_the_commands_it_names() {
+        let mut keys = Keymap::default();
+        keys.apply("# comment\n\nleft = z, Left\npause = space # spacebar\n")
+            .unwrap();
+        assert_eq!(keys.command(KeyName::Char('z')), Some(Command::Left));
+        assert_eq!(keys.command(KeyName::Char('h')), None);
+        assert_eq!(keys.command(KeyName::Left), Some(Command::Left));
+        assert_eq!(keys.command(KeyName::Char(' ')), Some(Command::Pause));
+        assert_eq!(keys.command(KeyName::Char('p')), None);
+        assert_eq!(keys.command(KeyName::Right), Some(Command::Right));
+    }
+
+    #[test]
+    fn config_errors_name_the_line() {
+        let mut keys = Keymap::default();
+        assert_eq!(
+            keys.apply("left = a\njump = w"),
+            Err(KeymapError::UnknownCommand {
+                line: 2,
+                found: "jump".to_string()
+            })
+        );
+        assert_eq!(
+            keys.apply("right = ctrl-xy"),
+            Err(KeymapError::UnknownKey {
+                line: 1,
+                found: "ctrl-xy".to_string()
+            })
+        );
+        assert_eq!(
+            keys.apply("\nquit"),
+            Err(KeymapError::MissingEquals { line: 2 })
+        );
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(KeyName::parse("x"), Some(KeyName::Char('x')));
+        assert_eq!(KeyName::parse("Ctrl-C"), Some(KeyName::Ctrl('c')));
+        assert_eq!(KeyName::parse("ESC"), Some(KeyName::Esc));
+        assert_eq!(KeyName::parse("Shift-Left"), Some(KeyName::ShiftLeft));
+        assert_eq!(KeyName::parse("home"), None);
+    }
+}
@@ -0,0 +1,238 @@
+// Shareable level files. A level is stored as RON (the default) or JSON,
+// chosen by file extension, so any frontend can load the same file.
+
+use crate::TunnelBuilderChoice;
+use crate::pattern::{PatternBuilder, PatternError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LevelSpec {
+    pub name: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub par_score: Option<u64>,
+    pub rows: u16,
+    pub cols: u16,
+    pub layout: LevelLayout,
+    // rows from the start to the exit, for campaigns (see campaign); the
+    // layout loops, so without one the exit is a pass through it
+    #[serde(default)]
+    pub length: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum LevelLayout {
+    Pattern(String),
+    Steps(Vec<TunnelBuilderChoice>),
+}
+
+impl LevelSpec {
+    pub fn builder(&self) -> Result<PatternBuilder, PatternError> {
+        match &self.layout {
+            LevelLayout::Pattern(pattern) => PatternBuilder::parse(pattern),
+            LevelLayout::Steps(steps) => {
+                PatternBuilder::from_choices(steps.iter().copied())
+            }
+        }
+    }
+
+    // how many rows the player has to survive to finish the level
+    pub fn exit_row(&self) -> Result<u64, PatternError> {
+        match self.length {
+            Some(length) => Ok(length),
+            None => Ok(self.builder()?.len() as u64),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LevelFormat {
+    Ron,
+    Json,
+}
+
+impl LevelFormat {
+    pub fn from_path(path: &Path) -> LevelFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => LevelFormat::Json,
+            _ => LevelFormat::Ron,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Ron(ron::Error),
+    Json(serde_json::Error),
+    Pattern(PatternError),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(e) => write!(f, "level file: {e}"),
+            LevelError::Ron(e) => write!(f, "level RON: {e}"),
+            LevelError::Json(e) => write!(f, "level JSON: {e}"),
+            LevelError::Pattern(e) => write!(f, "level pattern: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(e: io::Error) -> Self {
+        LevelError::Io(e)
+    }
+}
+
+impl From<ron::Error> for LevelError {
+    fn from(e: ron::Error) -> Self {
+        LevelError::Ron(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for LevelError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        LevelError::Ron(e.code)
+    }
+}
+
+impl From<serde_json::Error> for LevelError {
+    fn from(e: serde_json::Error) -> Self {
+        LevelError::Json(e)
+    }
+}
+
+impl From<PatternError> for LevelError {
+    fn from(e: PatternError) -> Self {
+        LevelError::Pattern(e)
+    }
+}
+
+pub fn from_str(s: &str, format: LevelFormat) -> Result<LevelSpec, LevelError> {
+    let spec: LevelSpec = match format {
+        LevelFormat::Ron => ron::from_str(s)?,
+        LevelFormat::Json => serde_json::from_str(s)?,
+    };
+    // reject unusable layouts up front rather than at first step()
+    spec.builder()?;
+    Ok(spec)
+}
+
+pub fn to_string(
+    spec: &LevelSpec,
+    format: LevelFormat,
+) -> Result<String, LevelError> {
+    Ok(match format {
+        LevelFormat::Ron => {
+            ron::ser::to_string_pretty(spec, ron::ser::PrettyConfig::new())?
+        }
+        LevelFormat::Json => serde_json::to_string_pretty(spec)?,
+    })
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<LevelSpec, LevelError> {
+    let path = path.as_ref();
+    from_str(&fs::read_to_string(path)?, LevelFormat::from_path(path))
+}
+
+pub fn save(
+    spec: &LevelSpec,
+    path: impl AsRef<Path>,
+) -> Result<(), LevelError> {
+    let path = path.as_ref();
+    fs::write(path, to_string(spec, LevelFormat::from_path(path))?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::{Tunnel, TunnelBuilder};
+
+    fn sample() -> LevelSpec {
+        LevelSpec {
+            name: String::from("zigzag"),
+            author: Some(String::from("etwoo")),
+            par_score: Some(120),
+            rows: 12,
+            cols: 20,
+            layout: LevelLayout::Pattern(String::from("L3 R3")),
+            length: Some(90),
+        }
+    }
+
+    #[test]
+    fn round_trip_through_both_formats() {
+        for format in [LevelFormat::Ron, LevelFormat::Json] {
+            let text = to_string(&sample(), format).unwrap();
+            assert_eq!(sample(), from_str(&text, format).unwrap());
+        }
+    }
+
+    #[test]
+    fn round_trip_through_files() {
+        let dir = std::env::temp_dir();
+        for file in ["tunnel-level-test.ron", "tunnel-level-test.JSON"] {
+            let path = dir.join(format!("{}-{file}", std::process::id()));
+            save(&sample(), &path).unwrap();
+            let loaded = load(&path);
+            fs::remove_file(&path).unwrap();
+            assert_eq!(sample(), loaded.unwrap());
+        }
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        let json = Path::new("a/level.json");
+        assert_eq!(LevelFormat::from_path(json), LevelFormat::Json);
+        let ron = Path::new("a/level.ron");
+        assert_eq!(LevelFormat::from_path(ron), LevelFormat::Ron);
+        let bare = Path::new("a/level");
+        assert_eq!(LevelFormat::from_path(bare), LevelFormat::Ron);
+    }
+
+    #[test]
+    fn explicit_steps_drive_builder() {
+        let text = r#"(
+            name: "steps",
+            rows: 5,
+            cols: 5,
+            layout: Steps([MoveLeftWall, KeepWalls]),
+        )"#;
+        let spec = from_str(text, LevelFormat::Ron).unwrap();
+        assert_eq!(spec.author, None);
+        assert_eq!(spec.par_score, None);
+        assert_eq!(spec.exit_row(), Ok(2));
+        let mut b = spec.builder().unwrap();
+        assert_eq!(b.choose_step(), TunnelBuilderChoice::MoveLeftWall);
+        assert_eq!(b.choose_step(), TunnelBuilderChoice::KeepWalls);
+        let t = Tunnel::new(&mut b, spec.rows, spec.cols);
+        assert!(t.iter().next().is_some());
+    }
+
+    #[test]
+    fn invalid_levels_are_rejected() {
+        let mut spec = sample();
+        spec.layout = LevelLayout::Pattern(String::from("Q"));
+        let text = to_string(&spec, LevelFormat::Json).unwrap();
+        let err = from_str(&text, LevelFormat::Json).unwrap_err();
+        assert!(matches!(err, LevelError::Pattern(_)));
+        assert!(err.to_string().starts_with("level pattern: "));
+
+        let err = from_str("(name: 3)", LevelFormat::Ron).unwrap_err();
+        assert!(matches!(err, LevelError::Ron(_)));
+        let err = from_str("{}", LevelFormat::Json).unwrap_err();
+        assert!(matches!(err, LevelError::Json(_)));
+        let err = load("/nonexistent/tunnel/level.ron").unwrap_err();
+        assert!(matches!(err, LevelError::Io(_)));
+    }
+}
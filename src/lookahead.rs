@@ -0,0 +1,139 @@
+// A player that looks ahead: it plays each move out over the rows already
+// built, ice, wind, doors and all, and picks one it can still be alive
+// after as many steps on as it can see. The tunnel only knows its buffered
+// rows, so the search stops short of the newest one; past that, it prefers
+// moves that Tunnel::is_survivable says leave a way through.
+
+use crate::sim::Action;
+use crate::{
+    Direction, Tunnel, TunnelBuilder, TunnelBuilderChoice, TunnelIndex,
+};
+use alloc::vec::Vec;
+
+// rows ahead a search goes, at most; each one adds a step per position the
+// player could be in by then, not per way of getting there
+pub const DEPTH: usize = 8;
+
+// builds the rows past the ones searched, which nothing looks at
+struct Steady;
+
+impl TunnelBuilder for Steady {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        max / 2.into()
+    }
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        TunnelBuilderChoice::KeepWalls
+    }
+}
+
+// what's left to tell two searched games apart; the same state plays out
+// the same whichever move led there
+#[derive(PartialEq)]
+struct State<T> {
+    player: T,
+    momentum: Option<Direction>,
+    dash_cooldown: usize,
+    fuel: Option<usize>,
+    keys: Vec<u8>,
+}
+
+impl<T: TunnelIndex, const N: usize> From<&Tunnel<T, N>> for State<T> {
+    fn from(t: &Tunnel<T, N>) -> State<T> {
+        State {
+            player: t.player(),
+            momentum: t.momentum(),
+            dash_cooldown: t.dash_cooldown(),
+            fuel: t.fuel(),
+            keys: t.keys().to_vec(),
+        }
+    }
+}
+
+// the move to make before the next step, out of staying, left and right:
+// one that survives the longest, up to `depth` steps, and of those the one
+// with the most ways through after; staying put when it's all the same
+pub fn best_action<T: TunnelIndex, const N: usize>(
+    t: &Tunnel<T, N>,
+    depth: usize,
+) -> Action {
+    const MOVES: [Action; 3] = [Action::Stay, Action::Left, Action::Right];
+    let depth = depth.min(t.buffered_rows().saturating_sub(1)).max(1);
+    // every state reachable so far, with the first move that got there
+    let mut frontier: Vec<(Action, Tunnel<T, N>)> =
+        MOVES.iter().map(|&first| (first, t.fork())).collect();
+    for ahead in 0..depth {
+        let mut next: Vec<(Action, Tunnel<T, N>)> = Vec::new();
+        let mut seen: Vec<(Action, State<T>)> = Vec::new();
+        for (first, game) in &frontier {
+            // the first step's move is the one being chosen
+            let moves = match ahead {
+                0 => core::slice::from_ref(first),
+                _ => &MOVES[..],
+            };
+            for action in moves {
+                let mut game = game.fork();
+                // only the row stepped onto can crash the player
+                action.apply(&mut game);
+                game.step(&mut Steady);
+                if game.is_collision() {
+                    continue;
+                }
+                let state = (*first, State::from(&game));
+                if !seen.contains(&state) {
+                    seen.push(state);
+                    next.push((*first, game));
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    // more ways through keeps the player clear of walls; max_by_key takes
+    // the last of equals, so Stay goes last
+    let ways = |action| {
+        frontier
+            .iter()
+            .filter(|(first, game)| *first == action && game.is_survivable())
+            .count()
+    };
+    MOVES
+        .into_iter()
+        .rev()
+        .max_by_key(|&action| ways(action))
+        .unwrap_or(Action::Stay)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::pattern::PatternBuilder;
+    use crate::seeded::Seeded;
+
+    #[test]
+    fn zig_zags_are_followed() {
+        let mut b = PatternBuilder::parse("L4 R4").unwrap();
+        let mut t: Tunnel<u16> =
+            Tunnel::with_config(&mut b, 12, 9, Difficulty::Chill.config());
+        for _ in 0..200 {
+            best_action(&t, DEPTH).apply(&mut t);
+            t.step(&mut b);
+            assert!(!t.is_collision());
+        }
+    }
+
+    #[test]
+    fn outlasts_a_seeded_tunnel() {
+        let mut b = Seeded::new(5);
+        let mut t: Tunnel<u16> =
+            Tunnel::with_config(&mut b, 20, 40, Difficulty::Normal.config());
+        for _ in 0..500 {
+            best_action(&t, DEPTH).apply(&mut t);
+            t.step(&mut b);
+            assert!(!t.is_collision());
+        }
+    }
+}
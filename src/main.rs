@@ -1,25 +1,79 @@
+use clap::{Parser, ValueEnum};
 use crossterm::{
     QueueableCommand, cursor,
-    event::{self, KeyCode},
-    style::{PrintStyledContent, Stylize},
+    event::{
+        self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
+    style::{self, PrintStyledContent, Stylize},
     terminal::{
         self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
     },
 };
-use rand::{Rng, rngs::ThreadRng};
+use rand::Rng;
+use std::cell::Cell;
 use std::cmp;
-use std::env;
-use std::io::{self, Write};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tunnel::bot;
+use tunnel::builders::{CoinDrops, Pillars};
+#[cfg(feature = "levels")]
+use tunnel::campaign::{Campaign, Progress};
+use tunnel::cast::{self, Recorder};
+use tunnel::chacha::ChaChaBuilder;
+use tunnel::difficulty::{Difficulty, Progression};
+use tunnel::gamelog::GameEvent;
+#[cfg(feature = "gamepad")]
+use tunnel::gamepad::Gamepads;
+use tunnel::keymap::{self, Command as KeyCommand, KeyName, Keymap};
+use tunnel::lookahead;
+use tunnel::mode::{COIN_POINTS, Mode};
+#[cfg(feature = "net")]
+use tunnel::net::{Client, LaneView};
+#[cfg(feature = "online")]
+use tunnel::online::Leaderboard;
+#[cfg(feature = "levels")]
+use tunnel::pattern::PatternBuilder;
+use tunnel::render::{
+    Beside, FrameDiff, Renderer, draw_frame, draw_frame_with_ghost,
+};
+use tunnel::replay::Replay;
+use tunnel::rewind::Rewind;
+use tunnel::runcode::{self, RunCode, RunConfig};
+use tunnel::scores::{self, Board, Entry, HighScores};
+use tunnel::sim::Action;
+use tunnel::ttyrec;
 use tunnel::{
-    Tunnel, TunnelBuilder, TunnelBuilderChoice, TunnelCellType, TunnelIndex,
+    Direction, Event, EventNotice, Floor, Hazard, RowInfo, Tunnel,
+    TunnelBuilder, TunnelBuilderChoice, TunnelCellType, TunnelIndex, Wind,
 };
 
 type Idx = u16; // for interop with crossterm::terminal::size()
 
+// zones on top of ChaChaBuilder, drawn from its stream so a seed or run
+// code gives the same tunnel everywhere
+#[derive(Clone)]
 struct SimpleBuilder {
-    rng: ThreadRng,
+    steps: ChaChaBuilder,
+    zone: RowInfo,
+    zone_rows: u32,
+}
+
+impl SimpleBuilder {
+    fn new(seed: u64) -> SimpleBuilder {
+        SimpleBuilder {
+            steps: ChaChaBuilder::new(seed),
+            zone: RowInfo::default(),
+            zone_rows: 0,
+        }
+    }
 }
 
 impl TunnelBuilder for SimpleBuilder {
@@ -27,149 +81,3237 @@ impl TunnelBuilder for SimpleBuilder {
         max / 2.into()
     }
     fn choose_step(&mut self) -> TunnelBuilderChoice {
-        if self.rng.random_bool(0.5) {
-            TunnelBuilderChoice::MoveLeftWall
-        } else {
-            TunnelBuilderChoice::MoveRightWall
+        // hold the corridor steady (and whole) across slippery, sticky or
+        // windy stretches, and for one row past their end so the player can
+        // step off cleanly
+        if self.zone.floor != Floor::Normal || self.zone.wind.is_some() {
+            return TunnelBuilderChoice::MergeCorridor;
+        }
+        self.steps.choose_step()
+    }
+    fn row_info(&mut self) -> RowInfo {
+        if self.zone_rows > 0 {
+            self.zone_rows -= 1;
+            return self.zone;
+        }
+        let rng = self.steps.rng();
+        self.zone = match rng.random_range(0..100_u32) {
+            0 => RowInfo {
+                speed_zone: true,
+                ..RowInfo::default()
+            },
+            1 => RowInfo {
+                floor: Floor::Ice,
+                ..RowInfo::default()
+            },
+            2 => RowInfo {
+                floor: Floor::Sticky,
+                ..RowInfo::default()
+            },
+            3 => RowInfo {
+                wind: Some(Wind {
+                    direction: if rng.random_bool(0.5) {
+                        Direction::Left
+                    } else {
+                        Direction::Right
+                    },
+                    strength: 2,
+                }),
+                ..RowInfo::default()
+            },
+            _ => RowInfo::default(),
+        };
+        if self.zone != RowInfo::default() {
+            self.zone_rows = 10;
+        }
+        self.zone
+    }
+}
+
+// where a round's tunnel comes from: a seed, or a campaign level's layout
+#[derive(Clone)]
+enum Builder {
+    // with --coins' coins, drawn from a stream of their own
+    Seeded(Box<CoinDrops<SimpleBuilder>>),
+    // and with --hazards, pillars as often as it asks, from another
+    Pillars(Box<Pillars<CoinDrops<SimpleBuilder>>>),
+    #[cfg(feature = "levels")]
+    Level(PatternBuilder),
+}
+
+impl TunnelBuilder for Builder {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        match self {
+            Builder::Seeded(b) => b.choose_player_start(max),
+            Builder::Pillars(b) => b.choose_player_start(max),
+            #[cfg(feature = "levels")]
+            Builder::Level(b) => b.choose_player_start(max),
+        }
+    }
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        match self {
+            Builder::Seeded(b) => b.choose_step(),
+            Builder::Pillars(b) => b.choose_step(),
+            #[cfg(feature = "levels")]
+            Builder::Level(b) => b.choose_step(),
+        }
+    }
+    fn row_info(&mut self) -> RowInfo {
+        match self {
+            Builder::Seeded(b) => b.row_info(),
+            Builder::Pillars(b) => b.row_info(),
+            #[cfg(feature = "levels")]
+            Builder::Level(b) => b.row_info(),
+        }
+    }
+}
+
+// stdout, and with --record-tty a ttyrec file that gets everything
+// written between flushes as one chunk
+struct Screen {
+    stdout: io::Stdout,
+    tty: Option<(BufWriter<File>, Vec<u8>)>,
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.stdout.write(buf)?;
+        if let Some((_, chunk)) = &mut self.tty {
+            chunk.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()?;
+        if let Some((file, chunk)) = &mut self.tty
+            && !chunk.is_empty()
+        {
+            let at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            file.write_all(&ttyrec::record(at, chunk))?;
+            file.flush()?;
+            chunk.clear();
         }
+        Ok(())
     }
 }
 
-fn display(t: &Tunnel<Idx>, score_row: Idx, game_score: u64) -> io::Result<()> {
-    let mut stdout = io::stdout();
-    stdout.queue(Clear(ClearType::All))?;
-    for (row, col, cell_type) in t.iter() {
-        stdout.queue(cursor::MoveTo(col, row))?;
-        match cell_type {
-            TunnelCellType::Player => {
-                stdout.queue(PrintStyledContent("v".green()))?;
+struct TerminalRenderer {
+    stdout: Screen,
+    // the line under the game, for the status bar
+    status_row: Idx,
+    columns: Idx,
+    // the terminal (row, column) of the game's top-left corner, which is
+    // off the origin when --rows or --cols centre a smaller game
+    origin: (Idx, Idx),
+    // draw the board faded, behind a banner
+    dim: bool,
+    theme: Theme,
+    status: Status,
+    // what's on screen, so a frame only draws the cells that changed
+    diff: FrameDiff,
+    // a banner over every frame, such as the attract mode's
+    caption: Option<&'static str>,
+}
+
+// what the status bar shows beside the score; there are no lives to count,
+// a crash ending the round, so a run's fuel is what it has left instead
+#[derive(Default)]
+struct Status {
+    // only while the game speeds up
+    level: Option<u64>,
+    tick: Duration,
+    // only for runs with a fuel tank
+    fuel: Option<usize>,
+    // in the round, leaving out pauses
+    elapsed: Duration,
+    // in a timed round, counting down in place of the time elapsed
+    left: Option<Duration>,
+    // rows to a campaign level's exit
+    exit: Option<u64>,
+    // both players' scores, in versus, in place of the one
+    versus: Option<[u64; 2]>,
+    // coins picked up, in a round with coins, and whether one just was
+    coins: Option<u64>,
+    coin_tick: bool,
+    // --connect: yours and the opponent's, once there is one
+    online: Option<(u64, Option<u64>)>,
+    // --spectate: how many players there are to watch
+    watching: Option<usize>,
+    seed: u64,
+}
+
+impl Status {
+    fn line(&self, score: u64) -> String {
+        let mut line = match (self.versus, self.online) {
+            (Some([one, two]), _) => format!(" P1 {one}  P2 {two}"),
+            (None, Some((mine, Some(theirs)))) => {
+                format!(" you {mine}  them {theirs}")
             }
-            TunnelCellType::Floor => {
-                stdout.queue(PrintStyledContent(" ".reset()))?;
+            (None, Some((mine, None))) => {
+                format!(" you {mine}  no opponent yet")
             }
-            TunnelCellType::Wall => {
-                stdout.queue(PrintStyledContent("O".reset()))?;
+            (None, None) => match self.watching {
+                Some(1) => " watching 1 player".to_owned(),
+                Some(players) => format!(" watching {players} players"),
+                None => format!(" {score}"),
+            },
+        };
+        if let Some(level) = self.level {
+            line += &format!("  level {level}");
+        }
+        if !self.tick.is_zero() {
+            let speed = 1. / self.tick.as_secs_f64();
+            line += &format!("  {speed:.1} rows/s");
+        }
+        if let Some(fuel) = self.fuel {
+            line += &format!("  fuel {fuel}");
+        }
+        if let Some(coins) = self.coins {
+            line += &format!("  * {coins}");
+            if self.coin_tick {
+                line += &format!(" +{COIN_POINTS}");
             }
         }
+        if let Some(exit) = self.exit {
+            line += &format!("  exit in {exit}");
+        }
+        let (secs, left) = match self.left {
+            // a second shows until it's all gone
+            Some(left) => (left.as_millis().div_ceil(1000) as u64, " left"),
+            None => (self.elapsed.as_secs(), ""),
+        };
+        line += &format!(
+            "  {}:{:02}{left}  seed {} ",
+            secs / 60,
+            secs % 60,
+            self.seed
+        );
+        line
+    }
+}
+
+// how the game looks: in colour, or in the terminal's own colours for
+// terminals (and players) that do without
+#[derive(Clone, Copy, PartialEq)]
+enum Theme {
+    Color,
+    Mono,
+}
+
+impl Theme {
+    const ALL: [Theme; 2] = [Theme::Color, Theme::Mono];
+
+    fn name(self) -> &'static str {
+        match self {
+            Theme::Color => "color",
+            Theme::Mono => "mono",
+        }
+    }
+}
+
+impl TerminalRenderer {
+    // a rows x columns game over its status bar, centred in a terminal of
+    // `screen` (columns, rows)
+    fn layout(&mut self, rows: Idx, columns: Idx, screen: (Idx, Idx)) {
+        let (screen_columns, screen_rows) = screen;
+        self.status_row = rows;
+        self.columns = columns;
+        self.origin = (
+            screen_rows.saturating_sub(rows + 1) / 2,
+            screen_columns.saturating_sub(columns) / 2,
+        );
+        self.diff.clear();
+    }
+
+    // the frame faded, to go behind a banner
+    fn draw_dimmed(
+        &mut self,
+        t: &Tunnel<Idx>,
+        fog: usize,
+        score: u64,
+    ) -> io::Result<()> {
+        self.dim = true;
+        self.diff.clear();
+        let drawn = draw_frame(self, t, fog, score);
+        self.dim = false;
+        drawn
+    }
+
+    // `text` across the middle of the game, over the frame already drawn;
+    // the frame after it is drawn in full again
+    fn draw_banner(&mut self, text: &str) -> io::Result<()> {
+        self.diff.clear();
+        self.queue_banner(text)?;
+        self.stdout.flush()
+    }
+
+    // the banner, with the cells it covers left to draw again
+    fn queue_banner(&mut self, text: &str) -> io::Result<()> {
+        let width = Idx::try_from(text.len()).unwrap_or(Idx::MAX);
+        let offset = self.columns.saturating_sub(width) / 2;
+        self.queue_label(self.status_row / 2, offset, text)
+    }
+
+    // `text` over the game from (row, col), as far as the game goes
+    fn queue_label(
+        &mut self,
+        row: Idx,
+        col: Idx,
+        text: &str,
+    ) -> io::Result<()> {
+        let (top, left) = self.origin;
+        let width = usize::from(self.columns.saturating_sub(col));
+        let text: String = text.chars().take(width).collect();
+        let end = col.saturating_add(Idx::try_from(text.len()).unwrap_or(0));
+        for c in col..end {
+            self.diff.forget(row.into(), c.into());
+        }
+        self.stdout.queue(cursor::MoveTo(left + col, top + row))?;
+        self.stdout
+            .queue(PrintStyledContent(text.bold().reverse()))?;
+        Ok(())
+    }
+
+    // a title over lines of text, centred where the game goes, with the
+    // selected line highlighted
+    fn draw_menu(
+        &mut self,
+        title: &str,
+        lines: &[String],
+        selected: Option<usize>,
+    ) -> io::Result<()> {
+        let (top, left) = self.origin;
+        let columns = self.columns;
+        let col = |text: &str| {
+            let width = Idx::try_from(text.len()).unwrap_or(Idx::MAX);
+            left + columns.saturating_sub(width) / 2
+        };
+        let height = Idx::try_from(lines.len() + 2).unwrap_or(Idx::MAX);
+        let first = top + self.status_row.saturating_sub(height) / 2;
+        self.diff.clear();
+        self.stdout.queue(Clear(ClearType::All))?;
+        self.stdout.queue(cursor::MoveTo(col(title), first))?;
+        self.stdout.queue(PrintStyledContent(title.bold()))?;
+        for (i, (line, row)) in lines.iter().zip(first + 2..).enumerate() {
+            let text = match selected == Some(i) {
+                true => line.as_str().reverse(),
+                false => line.as_str().reset(),
+            };
+            self.stdout.queue(cursor::MoveTo(col(line), row))?;
+            self.stdout.queue(PrintStyledContent(text))?;
+        }
+        self.stdout.flush()
+    }
+}
+
+impl Renderer<Idx> for TerminalRenderer {
+    type Error = io::Error;
+
+    fn begin_frame(&mut self) -> io::Result<()> {
+        if self.diff.is_empty() {
+            self.stdout.queue(Clear(ClearType::All))?;
+        }
+        Ok(())
+    }
+
+    fn draw_cell(
+        &mut self,
+        row: Idx,
+        col: Idx,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> io::Result<()> {
+        if !self.diff.changed(row.into(), col.into(), cell_type, info) {
+            return Ok(());
+        }
+        let glyph = match cell_type {
+            TunnelCellType::Player => "v".green(),
+            TunnelCellType::Floor => match (info.floor, info.wind) {
+                _ if info.hazard == Some(Hazard::Warning) => "!".red(),
+                (Floor::Ice, _) => "-".cyan(),
+                (Floor::Sticky, _) => "~".dark_yellow(),
+                (Floor::Normal, Some(wind)) => match wind.direction {
+                    Direction::Left => "<".dark_grey(),
+                    Direction::Right => ">".dark_grey(),
+                },
+                (Floor::Normal, None) if info.speed_zone => "^".dark_grey(),
+                (Floor::Normal, None) => " ".reset(),
+            },
+            TunnelCellType::Wall => "O".reset(),
+            TunnelCellType::Fuel => "+".yellow(),
+            TunnelCellType::Key => "k".yellow(),
+            TunnelCellType::Door => "#".magenta(),
+            TunnelCellType::Hidden => ".".dark_grey(),
+            TunnelCellType::Coin => "*".yellow(),
+            TunnelCellType::Pillar => "X".dark_red(),
+        };
+        let glyph = match self.theme {
+            Theme::Color => glyph,
+            Theme::Mono => style::style(*glyph.content()),
+        };
+        let glyph = match self.dim {
+            true => glyph.dim(),
+            false => glyph,
+        };
+        let (top, left) = self.origin;
+        self.stdout.queue(cursor::MoveTo(left + col, top + row))?;
+        self.stdout.queue(PrintStyledContent(glyph))?;
+        Ok(())
+    }
+
+    // the player's glyph faded; the cell under it is drawn again next frame
+    fn draw_ghost(&mut self, row: Idx, col: Idx) -> io::Result<()> {
+        self.diff.forget(row.into(), col.into());
+        let glyph = match self.theme {
+            Theme::Color => "v".dark_green().dim(),
+            Theme::Mono => "v".dim(),
+        };
+        let (top, left) = self.origin;
+        self.stdout.queue(cursor::MoveTo(left + col, top + row))?;
+        self.stdout.queue(PrintStyledContent(glyph))?;
+        Ok(())
+    }
+
+    fn draw_hud(&mut self, score: u64) -> io::Result<()> {
+        let (top, left) = self.origin;
+        self.stdout
+            .queue(cursor::MoveTo(left, top + self.status_row))?;
+        // the full width of the game, cut short if need be, so a shorter
+        // line covers the last
+        let width = usize::from(self.columns);
+        let line = self.status.line(score);
+        let line: String =
+            format!("{line:width$}").chars().take(width).collect();
+        let bar = match self.theme {
+            Theme::Color => line.black().on_green(),
+            Theme::Mono => line.reverse(),
+        };
+        self.stdout.queue(PrintStyledContent(bar))?;
+        Ok(())
+    }
+
+    fn end_frame(&mut self) -> io::Result<()> {
+        if let Some(caption) = self.caption {
+            self.queue_banner(caption)?;
+        }
+        self.stdout.flush()
+    }
+}
+
+// one line of JSON per frame for --output json: cells are rows of
+// TunnelCellType::code() digits, hash is FNV-1a over the player and cells
+fn json_frame(t: &mut Tunnel<Idx>, score: u64) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut fnv = |byte: u8| {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3);
+    };
+    t.player().to_le_bytes().into_iter().for_each(&mut fnv);
+    let mut rows: Vec<String> = Vec::new();
+    for (row, _, cell_type) in t.iter() {
+        let code = cell_type.code();
+        fnv(code);
+        if rows.len() <= usize::from(row) {
+            rows.push(String::new());
+        }
+        rows[usize::from(row)].push(char::from(b'0' + code));
+    }
+    let cells: Vec<String> = rows.iter().map(|r| format!("\"{r}\"")).collect();
+    let events: Vec<String> = t
+        .take_notices()
+        .into_iter()
+        .map(|notice| {
+            let (
+                name,
+                Event::CaveIn {
+                    side,
+                    width,
+                    rows,
+                    warning,
+                },
+            ) = match notice {
+                EventNotice::Warned(e) => ("warned", e),
+                EventNotice::Started(e) => ("started", e),
+                EventNotice::Ended(e) => ("ended", e),
+            };
+            let side = match side {
+                Direction::Left => "left",
+                Direction::Right => "right",
+            };
+            format!(
+                "{{\"notice\":\"{name}\",\"event\":\"cave_in\",\
+                 \"side\":\"{side}\",\"width\":{width},\"rows\":{rows},\
+                 \"warning\":{warning}}}"
+            )
+        })
+        .collect();
+    let fuel = match t.fuel() {
+        Some(fuel) => fuel.to_string(),
+        None => "null".to_owned(),
+    };
+    format!(
+        "{{\"hash\":\"{hash:016x}\",\"score\":{score},\"player\":{},\
+         \"crashed\":{},\"fuel\":{fuel},\"cells\":[{}],\"events\":[{}]}}",
+        t.player(),
+        t.is_collision(),
+        cells.join(","),
+        events.join(",")
+    )
+}
+
+// for --output json: one of left, right, stay (or an empty line) or quit
+// per frame; end of input quits
+fn json_step(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+) -> PlayerInput {
+    match lines.next() {
+        Some(Ok(line)) => match line.trim() {
+            "left" => PlayerInput::MoveLeft,
+            "right" => PlayerInput::MoveRight,
+            "quit" => PlayerInput::Quit,
+            _ => PlayerInput::Empty,
+        },
+        _ => PlayerInput::Quit,
     }
-    stdout.queue(cursor::MoveTo(0, score_row))?;
-    stdout.queue(PrintStyledContent(format!("{game_score}").green()))?;
-    stdout.flush()?;
-    Ok(())
 }
 
+// the demo plays out a few rows ahead before each move (see lookahead)
 fn demo_step(t: &Tunnel<Idx>, timeout: Duration) -> PlayerInput {
     thread::sleep(timeout);
+    match lookahead::best_action(t, lookahead::DEPTH) {
+        Action::Left => PlayerInput::MoveLeft,
+        Action::Right => PlayerInput::MoveRight,
+        _ => PlayerInput::Empty,
+    }
+}
+
+// crossterm's key as the keymap names it
+fn key_name(key: KeyEvent) -> Option<KeyName> {
+    Some(match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            KeyName::Ctrl(c.to_ascii_lowercase())
+        }
+        KeyCode::Char(c) => KeyName::Char(c),
+        KeyCode::Left if key.modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyName::ShiftLeft
+        }
+        KeyCode::Right if key.modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyName::ShiftRight
+        }
+        KeyCode::Left => KeyName::Left,
+        KeyCode::Right => KeyName::Right,
+        KeyCode::Up => KeyName::Up,
+        KeyCode::Down => KeyName::Down,
+        KeyCode::Enter => KeyName::Enter,
+        KeyCode::Esc => KeyName::Esc,
+        KeyCode::Tab => KeyName::Tab,
+        _ => return None,
+    })
+}
+
+fn key_command(
+    keys: &Keymap,
+    event: &event::Event,
+) -> Option<(KeyCommand, KeyEventKind)> {
+    event
+        .as_key_event()
+        .and_then(|key| Some((keys.command(key_name(key)?)?, key.kind)))
+}
+
+// gilrs can't wait alongside crossterm, so with a gamepad the keyboard is
+// polled in slices this long
+#[cfg(feature = "gamepad")]
+const GAMEPAD_POLL: Duration = Duration::from_millis(10);
 
-    let mut player = 0;
-    let mut safe_min = Idx::MAX;
-    let mut safe_max = 0;
+// the keyboard through the keymap, and any gamepads
+struct Controls {
+    keys: Keymap,
+    // whether the terminal reports key releases; without them a held key
+    // looks like the same key pressed over and over, at the terminal's
+    // own repeat rate
+    releases: bool,
+    // with releases, a held move repeats this often
+    repeat: Duration,
+    // the move being held, and when it next repeats
+    held: Option<(KeyCommand, Instant)>,
+    #[cfg(feature = "gamepad")]
+    pads: Option<Gamepads>,
+    // the terminal's latest (columns, rows), once it changes size
+    resized: Option<(Idx, Idx)>,
+}
 
-    for (row, col, cell_type) in t.iter() {
-        if cell_type == TunnelCellType::Player {
-            player = col;
+impl Controls {
+    // for players who don't use the keyboard: notes a resize among
+    // whatever is waiting, and drops the rest
+    fn drain(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            if let event::Event::Resize(cols, rows) = event::read()? {
+                self.resized = Some((cols, rows));
+            }
         }
-        if row == 1
-            && (cell_type == TunnelCellType::Player
-                || cell_type == TunnelCellType::Floor)
-        {
-            safe_min = cmp::min(safe_min, col);
-            safe_max = cmp::max(safe_max, col);
+        Ok(())
+    }
+
+    // the next command, or None once `deadline` passes or the terminal
+    // changes size
+    fn next(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> io::Result<Option<KeyCommand>> {
+        loop {
+            let now = Instant::now();
+            if let Some((command, at)) = &mut self.held
+                && *at <= now
+            {
+                *at += self.repeat;
+                return Ok(Some(*command));
+            }
+            let wait = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(now),
+                None => Duration::from_secs(60),
+            };
+            let wait = match self.held {
+                Some((_, at)) => wait.min(at.saturating_duration_since(now)),
+                None => wait,
+            };
+            #[cfg(feature = "gamepad")]
+            let wait = match &mut self.pads {
+                Some(pads) => match pads.next_command() {
+                    Some(command) => return Ok(Some(command)),
+                    None => wait.min(GAMEPAD_POLL),
+                },
+                None => wait,
+            };
+            if event::poll(wait)? {
+                let event = event::read()?;
+                if let event::Event::Resize(cols, rows) = event {
+                    self.resized = Some((cols, rows));
+                    return Ok(None);
+                }
+                match key_command(&self.keys, &event) {
+                    Some((command, KeyEventKind::Press)) => {
+                        if self.releases
+                            && matches!(
+                                command,
+                                KeyCommand::Left | KeyCommand::Right
+                            )
+                        {
+                            self.held =
+                                Some((command, Instant::now() + self.repeat));
+                        }
+                        return Ok(Some(command));
+                    }
+                    Some((command, KeyEventKind::Release)) => {
+                        if self.held.is_some_and(|(held, _)| held == command) {
+                            self.held = None;
+                        }
+                    }
+                    // held moves repeat at our rate, not the terminal's
+                    Some((_, KeyEventKind::Repeat)) | None => {}
+                }
+            } else if deadline.is_some_and(|d| Instant::now() >= d) {
+                return Ok(None);
+            }
         }
     }
+}
 
-    let safe_goal = safe_min + safe_max.saturating_sub(safe_min) / 2;
+impl Controls {
+    // whether any key (or button) is pressed before `wait` is up, bound
+    // to a command or not
+    fn any_key(&mut self, wait: Duration) -> io::Result<bool> {
+        let deadline = Instant::now() + wait;
+        loop {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            #[cfg(feature = "gamepad")]
+            let wait = match &mut self.pads {
+                Some(pads) => match pads.next_command() {
+                    Some(_) => return Ok(true),
+                    None => wait.min(GAMEPAD_POLL),
+                },
+                None => wait,
+            };
+            if event::poll(wait)? {
+                match event::read()? {
+                    event::Event::Resize(cols, rows) => {
+                        self.resized = Some((cols, rows));
+                    }
+                    event::Event::Key(key)
+                        if key.kind == KeyEventKind::Press =>
+                    {
+                        return Ok(true);
+                    }
+                    _ => {}
+                }
+            } else if Instant::now() >= deadline {
+                return Ok(false);
+            }
+        }
+    }
+}
 
-    if player > safe_goal {
-        PlayerInput::MoveLeft
-    } else if player < safe_goal {
-        PlayerInput::MoveRight
-    } else {
-        PlayerInput::Empty
+// waits out `wait`, keeping every move pressed meanwhile
+fn keyboard_step(controls: &mut Controls, wait: Duration) -> PlayerInput {
+    let deadline = Instant::now() + wait;
+    let mut moves = Vec::new();
+    loop {
+        match controls.next(Some(deadline)) {
+            // a resize; the game lays itself out again next tick
+            Ok(None) if Instant::now() < deadline => {}
+            Ok(None) if moves.is_empty() => return PlayerInput::Empty,
+            Ok(None) => return PlayerInput::Moves(moves),
+            Ok(Some(KeyCommand::Quit)) | Err(_) => return PlayerInput::Quit,
+            Ok(Some(KeyCommand::Pause)) => return PlayerInput::Pause,
+            Ok(Some(KeyCommand::Rewind)) => return PlayerInput::Rewind,
+            Ok(Some(KeyCommand::Left)) => moves.push(Action::Left),
+            Ok(Some(KeyCommand::Right)) => moves.push(Action::Right),
+            Ok(Some(KeyCommand::DashLeft)) => moves.push(Action::DashLeft),
+            Ok(Some(KeyCommand::DashRight)) => moves.push(Action::DashRight),
+            Ok(Some(
+                KeyCommand::Restart
+                | KeyCommand::Up
+                | KeyCommand::Down
+                | KeyCommand::Select,
+            )) => {}
+        }
     }
 }
 
-fn keyboard_step(timeout: Duration) -> PlayerInput {
-    if let Ok(true) = event::poll(timeout)
-        && let Ok(event) = event::read()
-        && let Some(key) = event.as_key_press_event()
-    {
-        match key.code {
-            KeyCode::Char('c' | 'q') => PlayerInput::Quit,
-            KeyCode::Left => PlayerInput::MoveLeft,
-            KeyCode::Right => PlayerInput::MoveRight,
-            _ => PlayerInput::Empty,
+// blocks until pause or a move is pressed (Empty) or quit is (Quit); the
+// move only resumes the game, it doesn't move the player
+fn paused(controls: &mut Controls) -> PlayerInput {
+    controls.held = None;
+    loop {
+        match controls.next(None) {
+            Ok(Some(KeyCommand::Quit)) | Err(_) => return PlayerInput::Quit,
+            Ok(Some(_)) => return PlayerInput::Empty,
+            Ok(None) => {}
+        }
+    }
+}
+
+// blocks at the end of a round until restart (or pause, for Start on a
+// gamepad) plays another or quit leaves
+fn play_again(controls: &mut Controls) -> bool {
+    controls.held = None;
+    loop {
+        match controls.next(None) {
+            Ok(Some(KeyCommand::Restart | KeyCommand::Pause)) => return true,
+            Ok(Some(KeyCommand::Quit)) | Err(_) => return false,
+            Ok(_) => {}
         }
-    } else {
-        PlayerInput::Empty
     }
 }
 
 #[derive(PartialEq)]
+enum Phase {
+    Running,
+    Paused,
+    // a practice round waiting to be rewound
+    Crashed,
+}
+
+// time since the run began, less the time spent paused, for recordings
+// and the event log
+struct Clock {
+    started: Instant,
+    paused: Cell<Duration>,
+}
+
+impl Clock {
+    fn elapsed(&self) -> Duration {
+        self.started.elapsed().saturating_sub(self.paused.get())
+    }
+
+    // leaves out the time since `since`, spent outside the game
+    fn skip(&self, since: Instant) {
+        self.paused.set(self.paused.get() + since.elapsed());
+    }
+}
+
+// the wall clock, for dates and the daily challenge
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// $XDG_CONFIG_HOME/tunnel/config, or ~/.config/tunnel/config
+fn default_config() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("tunnel").join("config"))
+}
+
+// $XDG_DATA_HOME/tunnel/scores, or wherever the platform keeps data:
+// ~/.local/share, ~/Library/Application Support or %APPDATA%
+fn default_scores() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?),
+        _ if cfg!(target_os = "macos") => {
+            home()?.join("Library").join("Application Support")
+        }
+        _ => home()?.join(".local").join("share"),
+    };
+    Some(dir.join("tunnel").join("scores"))
+}
+
+// a missing or unreadable file is no scores yet
+fn load_scores(path: &Path) -> HighScores {
+    match fs::read(path) {
+        Ok(bytes) => HighScores::parse(&String::from_utf8_lossy(&bytes)),
+        Err(_) => HighScores::default(),
+    }
+}
+
+// kept beside the high scores
+#[cfg(feature = "levels")]
+fn default_progress() -> Option<PathBuf> {
+    Some(default_scores()?.with_file_name("campaign"))
+}
+
+#[cfg(feature = "levels")]
+fn load_progress(path: &Path) -> Progress {
+    match fs::read(path) {
+        Ok(bytes) => Progress::parse(&String::from_utf8_lossy(&bytes)),
+        Err(_) => Progress::default(),
+    }
+}
+
+// a high-score table, a line per place
+fn table(scores: &HighScores, board: Board<'_>) -> Vec<String> {
+    let mut lines: Vec<String> = scores
+        .board(board)
+        .zip(1..)
+        .map(|(e, place)| {
+            let seed = e.seed.map_or("-".to_owned(), |s| s.to_string());
+            format!(
+                "{place:>2}. {:<3} {:>7}  {:<10}  {seed:<20}",
+                e.initials, e.score, e.date
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("No scores yet".to_owned());
+    }
+    lines
+}
+
+// through a temporary file, so a crash mid-write can't lose the old
+// scores (or campaign progress)
+fn save_text(path: &Path, text: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, text)?;
+    fs::rename(temp, path)
+}
+
+// --bot-cmd: a program speaking bot::observation and bot::parse_action
+// over its stdin and stdout. An answer that misses its tick counts as
+// STAY, and is dropped when it turns up late.
+struct Bot {
+    child: Child,
+    stdin: ChildStdin,
+    replies: Receiver<String>,
+    unanswered: usize,
+}
+
+impl Bot {
+    fn spawn(command: &str) -> io::Result<Bot> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "empty --bot-cmd")
+        })?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) =
+            (child.stdin.take(), child.stdout.take())
+        else {
+            return Err(io::Error::other("--bot-cmd has no pipes"));
+        };
+        let (sender, replies) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Bot {
+            child,
+            stdin,
+            replies,
+            unanswered: 0,
+        })
+    }
+
+    // the bot quitting, or closing its end, quits the game
+    fn step(
+        &mut self,
+        t: &Tunnel<Idx>,
+        tick: u64,
+        timeout: Duration,
+    ) -> PlayerInput {
+        if !self.ask(t, tick) {
+            return PlayerInput::Quit;
+        }
+        self.answer(timeout)
+    }
+
+    // sends the bot what it sees; false once it has gone
+    fn ask(&mut self, t: &Tunnel<Idx>, tick: u64) -> bool {
+        let observation = bot::observation(t, tick);
+        let sent = self
+            .stdin
+            .write_all(observation.as_bytes())
+            .and_then(|()| self.stdin.flush())
+            .is_ok();
+        if sent {
+            self.unanswered += 1;
+        }
+        sent
+    }
+
+    // the answer to the latest observation, waiting up to `timeout` for it
+    fn answer(&mut self, timeout: Duration) -> PlayerInput {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            match self.replies.recv_timeout(wait) {
+                Ok(line) => {
+                    self.unanswered = self.unanswered.saturating_sub(1);
+                    if self.unanswered > 0 {
+                        continue;
+                    }
+                    return match bot::parse_action(&line) {
+                        Some(action) => PlayerInput::Moves(vec![action]),
+                        None => PlayerInput::Empty,
+                    };
+                }
+                Err(RecvTimeoutError::Timeout) => return PlayerInput::Empty,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return PlayerInput::Quit;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Output {
+    Terminal,
+    Json,
+}
+
 enum PlayerType {
     SelfDemo,
+    // the demo the menu starts when left idle, which any key stops
+    Attract,
     Keyboard,
+    Bot(Bot),
+    Replay(Playback),
+}
+
+// --replay: a recorded round's moves, a step's worth at a time
+struct Playback {
+    replay: Replay,
+    next: usize,
+    // how many times faster than recorded
+    speed: f64,
+}
+
+impl Playback {
+    fn moves(&mut self) -> Option<Vec<Action>> {
+        let moves = self.replay.steps.get(self.next)?.clone();
+        self.next += 1;
+        Some(moves)
+    }
+}
+
+// --replay-speed: any factor over nothing
+fn speed(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed > 0. && speed.is_finite() => Ok(speed),
+        _ => Err("expected a number over 0".to_owned()),
+    }
+}
+
+// --replay and --verify
+fn load_replay(path: &Path) -> io::Result<Replay> {
+    Replay::parse(&fs::read_to_string(path)?).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("replay: {e}"))
+    })
+}
+
+// --versus: the blank columns between the two games
+const VERSUS_GAP: Idx = 2;
+
+// --connect: how many times smaller the opponent's lane is drawn
+#[cfg(feature = "net")]
+const MINIATURE: Idx = 2;
+
+// --spectate: how many times smaller `count` lanes `cols` wide are drawn
+// to fit side by side in `screen_columns`, at the least
+#[cfg(feature = "net")]
+fn fit_lanes(cols: Idx, count: Idx, screen_columns: Idx) -> Idx {
+    let cols = cols.max(1);
+    (1..cols)
+        .find(|&scale| {
+            let width = cols.div_ceil(scale);
+            count.saturating_mul(width)
+                + count.saturating_sub(1).saturating_mul(VERSUS_GAP)
+                <= screen_columns
+        })
+        .unwrap_or(cols)
+}
+
+enum VersusInput {
+    // each player's moves, in order
+    Moves([Vec<Action>; 2]),
+    Pause,
+    Restart,
+    Quit,
+}
+
+// both players' moves pressed before `wait` is up, each through their own
+// half of keymap::versus(); a resize is noted for the game to lay itself
+// out again, as with one player
+fn versus_step(
+    controls: &mut Controls,
+    keys: &[Keymap; 2],
+    wait: Duration,
+) -> VersusInput {
+    let deadline = Instant::now() + wait;
+    let mut moves = [Vec::new(), Vec::new()];
+    loop {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        let event = match event::poll(wait).and_then(|ready| match ready {
+            true => event::read().map(Some),
+            false => Ok(None),
+        }) {
+            Ok(Some(event)) => event,
+            Ok(None) => return VersusInput::Moves(moves),
+            Err(_) => return VersusInput::Quit,
+        };
+        if let event::Event::Resize(cols, rows) = event {
+            controls.resized = Some((cols, rows));
+            continue;
+        }
+        // the terminal's own repeats stand in for held keys, there being
+        // one held move to keep per player
+        let Some((player, command)) = keys.iter().enumerate().find_map(
+            |(player, keys)| match key_command(keys, &event)? {
+                (command, KeyEventKind::Press | KeyEventKind::Repeat) => {
+                    Some((player, command))
+                }
+                (_, KeyEventKind::Release) => None,
+            },
+        ) else {
+            continue;
+        };
+        match command {
+            KeyCommand::Quit => return VersusInput::Quit,
+            KeyCommand::Pause => return VersusInput::Pause,
+            KeyCommand::Restart => return VersusInput::Restart,
+            KeyCommand::Left => moves[player].push(Action::Left),
+            KeyCommand::Right => moves[player].push(Action::Right),
+            KeyCommand::DashLeft => moves[player].push(Action::DashLeft),
+            KeyCommand::DashRight => moves[player].push(Action::DashRight),
+            KeyCommand::Rewind
+            | KeyCommand::Up
+            | KeyCommand::Down
+            | KeyCommand::Select => {}
+        }
+    }
 }
 
 enum PlayerInput {
     Empty,
     MoveLeft,
     MoveRight,
+    // every move made during the tick, in order
+    Moves(Vec<Action>),
+    Pause,
+    // back a few rows, in practice
+    Rewind,
     Quit,
 }
 
-fn main() -> io::Result<()> {
-    let (player_type, timeout) = if env::args().any(|x| x == "--demo") {
-        (PlayerType::SelfDemo, Duration::from_millis(100))
-    } else {
-        (PlayerType::Keyboard, Duration::from_secs(1))
-    };
+#[derive(Parser)]
+#[command(about = "Steer through a never-ending tunnel")]
+struct Args {
+    /// Let the built-in bot play
+    #[arg(long)]
+    demo: bool,
+    /// Let a program play over stdin/stdout (see tunnel::bot)
+    #[arg(long, value_name = "PROGRAM", conflicts_with = "demo")]
+    bot_cmd: Option<String>,
+    /// Draw to the terminal, or print JSON frames for another program
+    #[arg(long, value_enum, default_value_t = Output::Terminal)]
+    output: Output,
+    /// Seed for the tunnel, to replay or share a run
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Replay a shared run code; takes its seed, size and difficulty
+    #[arg(long, value_parser = RunCode::decode, conflicts_with = "seed")]
+    code: Option<RunCode>,
+    /// Play today's daily challenge: the same tunnel for everyone all UTC
+    /// day, at a fixed size, difficulty and speed, on its own high-score
+    /// table
+    #[arg(long, conflicts_with_all = [
+        "seed", "code", "difficulty", "rows", "cols", "tick_ms",
+        "level_every", "level_speedup", "min_tick_ms", "mode",
+    ])]
+    daily: bool,
+    /// Play the levels in DIR (see tunnel::campaign) in turn, picking up
+    /// after the last one cleared
+    #[cfg(feature = "levels")]
+    #[arg(long, value_name = "DIR", conflicts_with_all = [
+        "daily", "seed", "code", "mode", "rows", "cols", "replay", "versus",
+    ])]
+    #[cfg_attr(feature = "net", arg(conflicts_with_all = ["connect", "spectate"]))]
+    campaign: Option<PathBuf>,
+    /// Practice: u takes the game back a few rows, and a crash waits for
+    /// it rather than ending the round; scores aren't kept
+    #[arg(long, conflicts_with = "daily")]
+    practice: bool,
+    /// Two players at one keyboard, each in the same tunnel side by side:
+    /// a and d steer the left one, the arrows the right, and the last one
+    /// still going wins
+    #[arg(long, conflicts_with_all = [
+        "demo", "bot_cmd", "output", "daily", "practice", "mode", "replay",
+        "record",
+    ])]
+    versus: bool,
+    /// Join the multiplayer match at HOST:PORT (see tunnel-net): your
+    /// tunnel beside a miniature of an opponent's, until one of you
+    /// crashes
+    #[cfg(feature = "net")]
+    #[arg(long, value_name = "HOST:PORT", conflicts_with_all = [
+        "demo", "bot_cmd", "output", "seed", "code", "daily", "practice",
+        "versus", "mode", "difficulty", "rows", "cols", "tick_ms", "replay",
+        "record",
+    ])]
+    connect: Option<String>,
+    /// Watch the multiplayer match at HOST:PORT without playing: every
+    /// player's tunnel side by side, each under its score
+    #[cfg(feature = "net")]
+    #[arg(long, value_name = "HOST:PORT", conflicts_with_all = [
+        "demo", "bot_cmd", "output", "seed", "code", "daily", "practice",
+        "versus", "mode", "difficulty", "rows", "cols", "tick_ms", "replay",
+        "record", "connect",
+    ])]
+    spectate: Option<String>,
+    /// Coins on about PERCENT rows in a hundred, worth 10 points each;
+    /// rounds with coins aren't recorded and keep no high scores
+    #[arg(long, value_name = "PERCENT", default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=100), conflicts_with_all = [
+        "daily", "replay", "record",
+    ])]
+    coins: u8,
+    /// Split the corridor around a pillar on about PERCENT steps in a
+    /// hundred, where the usual tunnel splits on 5; rounds with --hazards
+    /// aren't recorded and keep no high scores
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100), conflicts_with_all = [
+        "daily", "replay", "record",
+    ])]
+    hazards: Option<u8>,
+    /// endless, or time-attack: two minutes to score what you can, with
+    /// bonuses for brushing walls and riding speed zones [default: endless]
+    #[arg(long, value_parser = Mode::from_str)]
+    mode: Option<Mode>,
+    /// How narrow and steep the tunnel gets, and how fast it scrolls: chill,
+    /// normal, hard or nightmare [default: normal]
+    #[arg(long, value_parser = Difficulty::from_str, conflicts_with = "code")]
+    difficulty: Option<Difficulty>,
+    /// Milliseconds per row the tunnel scrolls [default: the difficulty's,
+    /// or 100 for the demo and --bot-cmd]
+    #[arg(long, visible_alias = "speed", value_name = "MS", value_parser = clap::value_parser!(u64).range(1..))]
+    tick_ms: Option<u64>,
+    /// Points per level; each level scrolls faster, and 0 keeps one speed
+    #[arg(long, value_name = "POINTS", default_value_t = 100)]
+    level_every: u64,
+    /// How much faster each level scrolls than the last, in percent
+    #[arg(long, value_name = "PERCENT", default_value_t = 10, value_parser = clap::value_parser!(u32).range(0..=100))]
+    level_speedup: u32,
+    /// Milliseconds per row that levels never go under
+    #[arg(long, value_name = "MS", default_value_t = 40, value_parser = clap::value_parser!(u64).range(1..))]
+    min_tick_ms: u64,
+    /// Milliseconds between moves while a move key is held, on terminals
+    /// that report key releases
+    #[arg(long, value_name = "MS", default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    repeat_ms: u64,
+    /// Seconds the menu waits untouched before a demo plays, until a key
+    /// is pressed; 0 never starts one
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    attract: u64,
+    /// The keys to start from, before the config file rebinds any
+    #[arg(long, value_name = "PRESET", default_value = "arrows", value_parser = keymap::PRESETS)]
+    keys: String,
+    /// Key bindings, one `command = keys` line each (see tunnel::keymap)
+    /// [default: ~/.config/tunnel/config, if it exists]
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Where to keep high scores [default: ~/.local/share/tunnel/scores,
+    /// or the platform's own data directory]
+    #[arg(long, value_name = "FILE")]
+    scores: Option<PathBuf>,
+    /// Send scores that make the high-score table to the online
+    /// leaderboard at URL, replay and all, and show its table too
+    #[cfg(feature = "online")]
+    #[arg(long, value_name = "URL")]
+    leaderboard: Option<String>,
+    /// Save each round's replay (see tunnel::replay) to FILE, the latest
+    /// round's in place of the one before
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// Watch a replay saved with --record, move for move
+    #[arg(long, value_name = "FILE", conflicts_with_all = [
+        "demo", "bot_cmd", "seed", "code", "daily", "difficulty", "rows",
+        "cols", "mode", "practice",
+    ])]
+    replay: Option<PathBuf>,
+    /// How many times faster than recorded --replay plays
+    #[arg(long, value_name = "FACTOR", default_value_t = 1., value_parser = speed, requires = "replay")]
+    replay_speed: f64,
+    /// Play back a replay (see tunnel::replay) and print its score and
+    /// hash as JSON, for leaderboard servers checking submissions
+    #[arg(long, value_name = "FILE")]
+    verify: Option<PathBuf>,
+    /// Play in this many rows, centred in the terminal
+    #[arg(long, value_parser = clap::value_parser!(u16).range(3..), conflicts_with = "code")]
+    rows: Option<u16>,
+    /// Play in this many columns, centred in the terminal
+    #[arg(long, value_parser = clap::value_parser!(u16).range(3..), conflicts_with = "code")]
+    cols: Option<u16>,
+    /// Only show this many rows ahead of the player
+    #[arg(long, value_name = "ROWS")]
+    fog: Option<usize>,
+    /// Record the run as an asciinema cast
+    #[arg(long, value_name = "FILE")]
+    export_cast: Option<PathBuf>,
+    /// Record the run as a GIF, with agg
+    #[arg(long, value_name = "FILE")]
+    export_gif: Option<PathBuf>,
+    /// Record the terminal output in ttyrec format
+    #[arg(long, value_name = "FILE")]
+    record_tty: Option<PathBuf>,
+    /// Write game events as JSON lines
+    #[arg(long, value_name = "FILE")]
+    event_log: Option<PathBuf>,
+}
 
-    let game_over_message;
-    let mut game_score = 0;
+// --export-gif hands the recording to agg (github.com/asciinema/agg)
+fn encode_gif(cast: &Path, gif: &Path) -> io::Result<()> {
+    let status =
+        Command::new("agg")
+            .arg(cast)
+            .arg(gif)
+            .status()
+            .map_err(|e| {
+                io::Error::new(e.kind(), format!("--export-gif runs agg: {e}"))
+            })?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!("agg failed: {status}"))),
+    }
+}
 
-    let mut level_builder = SimpleBuilder { rng: rand::rng() };
+const QUITTING: &str = "Quitting ...";
+const ATTRACT: &str = " DEMO  press any key ";
+const TIME_UP: &str = "Time's up!";
+const REPLAY_OVER: &str = "Replay over!";
+#[cfg(feature = "levels")]
+const LEVEL_CLEARED: &str = "Level cleared!";
+#[cfg(feature = "levels")]
+const CAMPAIGN_COMPLETE: &str = "Campaign complete!";
 
-    let (columns, rows) = terminal::size()?;
-    terminal::enable_raw_mode()?;
-    crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+// --campaign: its levels, the one up next, and what's been cleared
+#[cfg(feature = "levels")]
+struct CampaignRun {
+    campaign: Campaign,
+    level: usize,
+    progress: Progress,
+    // None when there's nowhere to keep it
+    path: Option<PathBuf>,
+}
 
-    let mut game_state = Tunnel::new(&mut level_builder, rows, columns);
-    loop {
-        display(&game_state, rows - 1, game_score)?;
+// a round in play
+struct Game {
+    tunnel: Tunnel<Idx>,
+    builder: Builder,
+    score: u64,
+    // for a campaign level, the score that reaches its exit, and the
+    // round's message when it does
+    exit: Option<(u64, &'static str)>,
+    // the tick before levels and speed zones shorten it
+    base_tick: Duration,
+    seed: u64,
+    // the clock's time when the round began
+    started: Duration,
+    // None once the round can't be played back, after a resize
+    replay: Option<Replay>,
+    // made since the last step
+    moves: Vec<Action>,
+    // --practice: the game as it was before each of the latest steps
+    rewind: Option<Rewind<Snapshot>>,
+    // the best run of this tunnel so far, a column per step (see
+    // Replay::columns), to race against
+    ghost: Option<Vec<Idx>>,
+    // the tunnel's coins already scored, and the clock's time at the last
+    coins: u64,
+    coin_at: Option<Duration>,
+}
 
-        if player_type == PlayerType::SelfDemo && game_score == 200 {
-            game_over_message = "Demo complete!";
-            break;
+impl Game {
+    // scores the coins picked up since the last call; a rewind can take
+    // some back, and their points with the score it restores
+    fn bank_coins(&mut self, now: Duration) {
+        let coins = self.tunnel.coins();
+        if coins > self.coins {
+            self.score += (coins - self.coins) * COIN_POINTS;
+            self.coin_at = Some(now);
         }
+        self.coins = coins;
+    }
 
-        let player_input = match player_type {
-            PlayerType::SelfDemo => demo_step(&game_state, timeout),
-            PlayerType::Keyboard => keyboard_step(timeout),
-        };
+    // where the ghost is now, while its run lasts and this one can keep
+    // count of steps
+    fn ghost(&self) -> Option<Idx> {
+        let steps = self.replay.as_ref()?.steps.len();
+        self.ghost.as_ref()?.get(steps).copied()
+    }
+}
 
-        match player_input {
-            PlayerInput::Empty => {}
-            PlayerInput::MoveLeft => game_state.move_player_left(),
-            PlayerInput::MoveRight => game_state.move_player_right(),
-            PlayerInput::Quit => {
-                game_over_message = "Quitting ...";
-                break;
-            }
-        }
+// a practice round to go back to
+struct Snapshot {
+    tunnel: Tunnel<Idx>,
+    builder: Builder,
+    score: u64,
+}
+
+// how far back the rewind key goes, and how far back repeated presses can
+const REWIND_STEPS: usize = 5;
+const REWIND_KEEP: usize = 100;
+
+// how often the terminal game draws and reads keys, whatever the tick
+const FRAME: Duration = Duration::from_millis(33);
+
+// how long the status bar shows a coin's points after it's picked up
+const COIN_TICK: Duration = Duration::from_millis(600);
+
+// a --versus round as it ended
+struct Match {
+    message: &'static str,
+    scores: [u64; 2],
+    run: RunCode,
+    tunnels: [Tunnel<Idx>; 2],
+}
+
+// a --connect round as it ended
+#[cfg(feature = "net")]
+struct Duel {
+    message: &'static str,
+    mine: LaneView,
+    theirs: Option<LaneView>,
+    seed: u64,
+    elapsed: Duration,
+    // open through the end screen, so the opponent sees the crash before
+    // the lane leaves
+    client: Option<Client>,
+}
+
+// a round as it ended
+struct Round {
+    message: &'static str,
+    score: u64,
+    run: RunCode,
+    tunnel: Tunnel<Idx>,
+    // for --record and the leaderboard
+    replay: Option<Replay>,
+    // the day, for a daily challenge
+    daily: Option<String>,
+    mode: Mode,
+}
 
-        game_state.step(&mut level_builder);
-        if game_state.is_collision() {
-            game_over_message = "Game over!";
-            break;
+impl Round {
+    // the high-score table it goes on
+    fn board(&self) -> Board<'_> {
+        Board {
+            config: self.run.config,
+            daily: self.daily.as_deref(),
+            mode: self.mode,
         }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuItem {
+    Start,
+    Demo,
+    Mode,
+    Difficulty,
+    Theme,
+    HighScores,
+    Quit,
+}
 
-        game_score += 1;
+const MENU: [MenuItem; 7] = [
+    MenuItem::Start,
+    MenuItem::Demo,
+    MenuItem::Mode,
+    MenuItem::Difficulty,
+    MenuItem::Theme,
+    MenuItem::HighScores,
+    MenuItem::Quit,
+];
+
+// the next of `all` after `current`, or the one before, wrapping around
+fn turn<T: Copy + PartialEq>(all: &[T], current: T, back: bool) -> T {
+    let i = all.iter().position(|&t| t == current).unwrap_or(0);
+    let step = if back { all.len() - 1 } else { 1 };
+    all[(i + step) % all.len()]
+}
+
+// the screen main() is on; each draws itself and picks the next
+enum State {
+    Menu,
+    HighScores,
+    Play(PlayerType),
+    // the demo, when the menu is left idle
+    Attract,
+    Versus,
+    #[cfg(feature = "net")]
+    Online,
+    #[cfg(feature = "net")]
+    Spectate,
+}
+
+// everything that outlasts a round: the screen, the controls, the
+// recordings and the settings rounds are played with
+struct Session {
+    output: Output,
+    renderer: TerminalRenderer,
+    controls: Controls,
+    lines: io::Lines<io::StdinLock<'static>>,
+    cast: Option<(BufWriter<File>, Recorder)>,
+    events: Option<BufWriter<File>>,
+    clock: Clock,
+    fog: usize,
+    tick_ms: Option<u64>,
+    progression: Progression,
+    rows: Idx,
+    columns: Idx,
+    // the --rows and --cols (or the run code's size) to keep to, as far
+    // as the terminal allows, whenever it changes size
+    size: (Option<Idx>, Option<Idx>),
+    config: RunConfig,
+    // --seed or --code, to play the same tunnel every round
+    seed: Option<u64>,
+    // --daily: every round is the day's challenge, whatever the settings
+    daily: bool,
+    practice: bool,
+    mode: Mode,
+    // whether rounds start from the menu, and end back at it
+    menu: bool,
+    // the menu's highlighted line
+    selected: usize,
+    // how long the menu waits untouched before playing the demo, if it does
+    attract: Option<Duration>,
+    // --versus: each player's keys
+    versus: Option<[Keymap; 2]>,
+    // --coins: how many rows in a hundred have one
+    coins: u8,
+    // --hazards: how many steps in a hundred split the corridor
+    hazards: Option<u8>,
+    // --connect: the match's address, and the connection made to check it
+    // before the first round; --spectate's, to watch it
+    #[cfg(feature = "net")]
+    connect: Option<String>,
+    #[cfg(feature = "net")]
+    client: Option<Client>,
+    scores: HighScores,
+    // None when there's nowhere to keep them
+    scores_path: Option<PathBuf>,
+    #[cfg(feature = "online")]
+    leaderboard: Option<Leaderboard>,
+    #[cfg(feature = "levels")]
+    campaign: Option<CampaignRun>,
+}
+
+impl Session {
+    // lays the game out again for a terminal that changed size since the
+    // last call, if it did
+    fn fit_terminal(&mut self) -> bool {
+        let Some((screen_columns, screen_rows)) = self.controls.resized.take()
+        else {
+            return false;
+        };
+        let fit = |size: Option<Idx>, screen: Idx| {
+            size.map_or(screen, |size| size.min(screen)).max(3)
+        };
+        self.rows = fit(self.size.0, screen_rows.saturating_sub(1));
+        // in versus, each game gets half the terminal
+        let (game_columns, width) = match self.versus {
+            Some(_) => {
+                let half = screen_columns.saturating_sub(VERSUS_GAP) / 2;
+                let columns = fit(self.size.1, half);
+                (columns, columns * 2 + VERSUS_GAP)
+            }
+            None => {
+                let columns = fit(self.size.1, screen_columns);
+                (columns, columns)
+            }
+        };
+        self.columns = game_columns;
+        self.renderer
+            .layout(self.rows, width, (screen_columns, screen_rows));
+        if let Some((_, recorder)) = &mut self.cast {
+            *recorder = Recorder::new(self.rows);
+        }
+        true
     }
 
-    crossterm::execute!(io::stdout(), LeaveAlternateScreen)?;
-    terminal::disable_raw_mode()?;
+    fn log(&mut self, event: Option<GameEvent>, tick: u64) -> io::Result<()> {
+        match (&mut self.events, event) {
+            (Some(file), Some(event)) => file.write_all(
+                event.json_line(self.clock.elapsed(), tick).as_bytes(),
+            ),
+            _ => Ok(()),
+        }
+    }
 
-    println!("{game_over_message} Final score: {game_score}");
+    // the state the player picks; difficulty and theme change in place
+    fn menu(&mut self) -> io::Result<Option<State>> {
+        let since = Instant::now();
+        self.controls.held = None;
+        let mut touched = Instant::now();
+        let state = loop {
+            self.fit_terminal();
+            let lines: Vec<String> = MENU
+                .iter()
+                .map(|item| match item {
+                    MenuItem::Start => "Start".to_owned(),
+                    MenuItem::Demo => "Demo".to_owned(),
+                    MenuItem::Mode => format!("Mode: {}", self.mode),
+                    MenuItem::Difficulty if self.daily => {
+                        format!(
+                            "Difficulty: {} (daily)",
+                            self.config.difficulty
+                        )
+                    }
+                    MenuItem::Difficulty => {
+                        format!("Difficulty: {}", self.config.difficulty)
+                    }
+                    MenuItem::Theme => {
+                        format!("Theme: {}", self.renderer.theme.name())
+                    }
+                    MenuItem::HighScores => "High Scores".to_owned(),
+                    MenuItem::Quit => "Quit".to_owned(),
+                })
+                .collect();
+            self.renderer
+                .draw_menu("TUNNEL", &lines, Some(self.selected))?;
+            let item = MENU[self.selected];
+            let idle = self.attract.map(|after| touched + after);
+            let command = self.controls.next(idle)?;
+            match command {
+                Some(_) => touched = Instant::now(),
+                // a resize doesn't count as being touched, nor as idle
+                None if idle.is_some_and(|idle| Instant::now() >= idle) => {
+                    break Some(State::Attract);
+                }
+                None => {}
+            }
+            // select, left and right all turn through a setting
+            let back = command == Some(KeyCommand::Left);
+            match command {
+                Some(KeyCommand::Up) => {
+                    self.selected =
+                        (self.selected + MENU.len() - 1) % MENU.len();
+                }
+                Some(KeyCommand::Down) => {
+                    self.selected = (self.selected + 1) % MENU.len();
+                }
+                Some(KeyCommand::Quit) => break None,
+                Some(
+                    KeyCommand::Select | KeyCommand::Left | KeyCommand::Right,
+                ) if item == MenuItem::Difficulty && !self.daily => {
+                    self.config.difficulty =
+                        turn(&Difficulty::ALL, self.config.difficulty, back);
+                }
+                Some(
+                    KeyCommand::Select | KeyCommand::Left | KeyCommand::Right,
+                ) if item == MenuItem::Mode && !self.daily => {
+                    self.mode = turn(&Mode::ALL, self.mode, back);
+                }
+                Some(
+                    KeyCommand::Select | KeyCommand::Left | KeyCommand::Right,
+                ) if item == MenuItem::Theme => {
+                    self.renderer.theme =
+                        turn(&Theme::ALL, self.renderer.theme, back);
+                }
+                Some(KeyCommand::Select) => match item {
+                    MenuItem::Start => {
+                        break Some(State::Play(PlayerType::Keyboard));
+                    }
+                    MenuItem::Demo => {
+                        break Some(State::Play(PlayerType::SelfDemo));
+                    }
+                    MenuItem::HighScores => break Some(State::HighScores),
+                    MenuItem::Quit => break None,
+                    MenuItem::Mode | MenuItem::Difficulty | MenuItem::Theme => {
+                    }
+                },
+                _ => {}
+            }
+        };
+        self.clock.skip(since);
+        Ok(state)
+    }
+
+    // the table for the difficulty and size the menu would play (today's
+    // with --daily), and with --leaderboard the online one too, a key away
+    fn high_scores(&mut self) -> io::Result<()> {
+        let since = Instant::now();
+        let today = self.daily.then(|| scores::date(unix_secs()));
+        let board = Board {
+            config: self.config,
+            daily: today.as_deref(),
+            mode: self.mode,
+        };
+        let mut name = format!(
+            "{} {}x{}",
+            self.config.difficulty, self.config.rows, self.config.cols
+        );
+        if self.mode != Mode::Endless {
+            name = format!("{} {name}", self.mode);
+        }
+        if let Some(day) = &today {
+            name = format!("DAILY {day}  {name}");
+        }
+        let mut pages =
+            vec![(format!("HIGH SCORES  {name}"), table(&self.scores, board))];
+        self.fit_terminal();
+        self.renderer.draw_menu(&pages[0].0, &pages[0].1, None)?;
+        if let Some(lines) = self.online_table(board) {
+            pages.push((format!("ONLINE  {name}"), lines));
+        }
+        let mut page = 0;
+        self.controls.held = None;
+        loop {
+            self.fit_terminal();
+            let (title, lines) = &pages[page];
+            self.renderer.draw_menu(title, lines, None)?;
+            match self.controls.next(None)? {
+                Some(KeyCommand::Left | KeyCommand::Right) => {
+                    page = (page + 1) % pages.len();
+                }
+                Some(_) => break,
+                None => {}
+            }
+        }
+        self.clock.skip(since);
+        Ok(())
+    }
+
+    // the leaderboard's `board`, with --leaderboard
+    #[cfg(feature = "online")]
+    fn online_table(&self, board: Board<'_>) -> Option<Vec<String>> {
+        let leaderboard = self.leaderboard.as_ref()?;
+        Some(match leaderboard.scores(board) {
+            Ok(scores) => table(&scores, board),
+            Err(_) => vec!["Can't reach the leaderboard".to_owned()],
+        })
+    }
+
+    #[cfg(not(feature = "online"))]
+    fn online_table(&self, _board: Board<'_>) -> Option<Vec<String>> {
+        None
+    }
+
+    fn play(&mut self, player_type: &mut PlayerType) -> io::Result<Round> {
+        // the day's challenge is picked as the round starts, so a session
+        // left running overnight moves on to the next day's
+        let now = unix_secs();
+        let run = match self.daily {
+            true => RunCode::daily(now),
+            false => RunCode {
+                seed: self.seed.unwrap_or_else(rand::random),
+                config: self.config,
+            },
+        };
+        let (mut builder, exit) = self.builder(&run)?;
+        let base_tick = match (self.tick_ms, &player_type) {
+            (Some(ms), _) => Duration::from_millis(ms),
+            (None, PlayerType::Keyboard | PlayerType::Replay(_)) => {
+                run.config.difficulty.tick()
+            }
+            (
+                None,
+                PlayerType::SelfDemo | PlayerType::Attract | PlayerType::Bot(_),
+            ) => Duration::from_millis(100),
+        };
+        let base_tick = match player_type {
+            PlayerType::Replay(playback) => {
+                playback.next = 0;
+                base_tick.div_f64(playback.speed)
+            }
+            _ => base_tick,
+        };
+        let tunnel = Tunnel::with_config(
+            &mut builder,
+            self.rows,
+            self.columns,
+            run.config.difficulty.config(),
+        );
+        let mut game = Game {
+            tunnel,
+            builder,
+            score: 0,
+            exit,
+            base_tick,
+            seed: run.seed,
+            started: self.clock.elapsed(),
+            // at the size actually played, which may be smaller than asked;
+            // levels aren't played back, having no seed, nor is practice,
+            // nor coins or --hazards, which the replay has no place for
+            replay: (exit.is_none()
+                && !self.practice
+                && self.coins == 0
+                && self.hazards.is_none())
+            .then(|| {
+                Replay::new(
+                    RunCode {
+                        seed: run.seed,
+                        config: RunConfig {
+                            rows: self.rows,
+                            cols: self.columns,
+                            ..run.config
+                        },
+                    },
+                    self.mode,
+                )
+            }),
+            moves: Vec::new(),
+            rewind: self.practice.then(|| Rewind::new(REWIND_KEEP)),
+            ghost: None,
+            coins: 0,
+            coin_at: None,
+        };
+        // only the player's own runs are raced, and only of a tunnel that
+        // comes round again
+        if matches!(player_type, PlayerType::Keyboard)
+            && let Some(replay) = &game.replay
+            && let Some(best) = self.best_run(replay)
+        {
+            game.ghost =
+                Some(best.columns(&mut SimpleBuilder::new(best.run.seed)));
+        }
+        let message = match self.output {
+            Output::Terminal => self.play_live(&mut game, player_type)?,
+            Output::Json => self.play_lockstep(&mut game, player_type)?,
+        };
+
+        // the crash ends the round's recording, so viewers see what happened
+        if game.tunnel.is_collision() {
+            self.record(&game)?;
+        }
+        // the crash is a frame too; then a summary in place of the text
+        if self.output == Output::Json && game.tunnel.is_collision() {
+            println!("{}", json_frame(&mut game.tunnel, game.score));
+        }
+        Ok(Round {
+            message,
+            score: game.score,
+            run,
+            tunnel: game.tunnel,
+            replay: game.replay,
+            daily: self.daily.then(|| scores::date(now)),
+            mode: self.mode,
+        })
+    }
+
+    // for --output json: a frame, then the player's move, then a step; the
+    // program on the other end of the pipe sets the pace
+    fn play_lockstep(
+        &mut self,
+        game: &mut Game,
+        player_type: &mut PlayerType,
+    ) -> io::Result<&'static str> {
+        loop {
+            println!("{}", json_frame(&mut game.tunnel, game.score));
+            self.record(game)?;
+
+            if matches!(player_type, PlayerType::SelfDemo | PlayerType::Attract)
+                && game.score >= 200
+            {
+                return Ok("Demo complete!");
+            }
+            if let Some(message) = self.finished(game) {
+                return Ok(message);
+            }
+
+            let player_input = match player_type {
+                PlayerType::SelfDemo | PlayerType::Attract => {
+                    demo_step(&game.tunnel, Duration::ZERO)
+                }
+                PlayerType::Keyboard => json_step(&mut self.lines),
+                PlayerType::Bot(bot) => {
+                    bot.step(&game.tunnel, game.score, self.tick(game))
+                }
+                PlayerType::Replay(playback) => match playback.moves() {
+                    Some(moves) => PlayerInput::Moves(moves),
+                    None => return Ok(REPLAY_OVER),
+                },
+            };
+            let actions = match player_input {
+                PlayerInput::Empty
+                | PlayerInput::Pause
+                | PlayerInput::Rewind => vec![Action::Stay],
+                PlayerInput::MoveLeft => vec![Action::Left],
+                PlayerInput::MoveRight => vec![Action::Right],
+                PlayerInput::Moves(moves) => moves,
+                PlayerInput::Quit => return Ok(QUITTING),
+            };
+            self.apply(game, actions)?;
+            if self.step(game)? {
+                return Ok("Game over!");
+            }
+        }
+    }
+
+    // for the terminal: frames (and keys) every FRAME, and a step whenever
+    // a tick's worth of time has built up, so moves show as they're made
+    // while the tunnel scrolls at its own pace
+    fn play_live(
+        &mut self,
+        game: &mut Game,
+        player_type: &mut PlayerType,
+    ) -> io::Result<&'static str> {
+        let mut lag = Duration::ZERO;
+        let mut last = Instant::now();
+        let mut recorded = None;
+        let mut phase = Phase::Running;
+        // a bot thinks about each row for the tick before its step
+        if let PlayerType::Bot(bot) = player_type
+            && !bot.ask(&game.tunnel, game.score)
+        {
+            return Ok(QUITTING);
+        }
+        loop {
+            if matches!(player_type, PlayerType::SelfDemo | PlayerType::Bot(_))
+            {
+                self.controls.drain()?;
+            }
+            if self.fit_terminal() {
+                game.tunnel
+                    .resize(&mut game.builder, self.rows, self.columns)
+                    .map_err(io::Error::other)?;
+                game.replay = None;
+                // snapshots from before are the wrong size
+                if let Some(rewind) = &mut game.rewind {
+                    rewind.clear();
+                }
+            }
+
+            // nothing steps or scores until the game resumes
+            if phase == Phase::Paused {
+                if self.pause(game)? {
+                    return Ok(QUITTING);
+                }
+                last = Instant::now();
+                phase = Phase::Running;
+            }
+            if let Some(message) = self.finished(game) {
+                return Ok(message);
+            }
+
+            let now = Instant::now();
+            lag += now - last;
+            last = now;
+            loop {
+                let tick = self.tick(game);
+                if lag < tick {
+                    break;
+                }
+                lag -= tick;
+                // keyboard moves went in as they were pressed
+                let actions = match player_type {
+                    PlayerType::Keyboard => Vec::new(),
+                    PlayerType::SelfDemo | PlayerType::Attract => {
+                        match demo_step(&game.tunnel, Duration::ZERO) {
+                            PlayerInput::MoveLeft => vec![Action::Left],
+                            PlayerInput::MoveRight => vec![Action::Right],
+                            _ => vec![Action::Stay],
+                        }
+                    }
+                    PlayerType::Bot(bot) => match bot.answer(Duration::ZERO) {
+                        PlayerInput::Moves(moves) => moves,
+                        PlayerInput::Quit => return Ok(QUITTING),
+                        _ => vec![Action::Stay],
+                    },
+                    PlayerType::Replay(playback) => match playback.moves() {
+                        Some(moves) => moves,
+                        None => return Ok(REPLAY_OVER),
+                    },
+                };
+                self.apply(game, actions)?;
+                // in practice a crash waits for a rewind
+                if self.step(game)? {
+                    if game.rewind.is_none() {
+                        return Ok("Game over!");
+                    }
+                    phase = Phase::Crashed;
+                    break;
+                }
+                if let PlayerType::Bot(bot) = player_type
+                    && !bot.ask(&game.tunnel, game.score)
+                {
+                    return Ok(QUITTING);
+                }
+                if matches!(
+                    player_type,
+                    PlayerType::SelfDemo | PlayerType::Attract
+                ) && game.score >= 200
+                {
+                    return Ok("Demo complete!");
+                }
+            }
+            if phase == Phase::Crashed {
+                if !self.crashed(game)? {
+                    return Ok("Game over!");
+                }
+                last = Instant::now();
+                lag = Duration::ZERO;
+                phase = Phase::Running;
+            }
+
+            self.renderer.status = Status {
+                // a level only means something while the game speeds up
+                level: (self.progression.every > 0)
+                    .then(|| self.progression.level(game.score)),
+                tick: self.tick(game),
+                fuel: game.tunnel.fuel(),
+                coins: (self.coins > 0).then_some(game.coins),
+                coin_tick: game.coin_at.is_some_and(|at| {
+                    self.clock.elapsed().saturating_sub(at) < COIN_TICK
+                }),
+                elapsed: self.clock.elapsed().saturating_sub(game.started),
+                left: self.time_left(game),
+                exit: game.exit.map(|(row, _)| row.saturating_sub(game.score)),
+                versus: None,
+                online: None,
+                watching: None,
+                seed: game.seed,
+            };
+            draw_frame_with_ghost(
+                &mut self.renderer,
+                &game.tunnel,
+                self.fog,
+                game.score,
+                game.ghost(),
+            )?;
+            // recordings only need the frames where something moved
+            let shown = (game.tunnel.generation(), game.score);
+            if recorded != Some(shown) {
+                self.record(game)?;
+                recorded = Some(shown);
+            }
+
+            let wait = FRAME.min(self.tick(game).saturating_sub(lag));
+            match player_type {
+                PlayerType::Keyboard => {
+                    match keyboard_step(&mut self.controls, wait) {
+                        PlayerInput::Moves(moves) => self.apply(game, moves)?,
+                        PlayerInput::Pause => phase = Phase::Paused,
+                        PlayerInput::Rewind => self.rewind(game),
+                        PlayerInput::Quit => return Ok(QUITTING),
+                        _ => {}
+                    }
+                }
+                // a replay can be paused or left, but it does the steering
+                PlayerType::Replay(_) => {
+                    match keyboard_step(&mut self.controls, wait) {
+                        PlayerInput::Pause => phase = Phase::Paused,
+                        PlayerInput::Quit => return Ok(QUITTING),
+                        _ => {}
+                    }
+                }
+                PlayerType::SelfDemo | PlayerType::Bot(_) => {
+                    thread::sleep(wait)
+                }
+                PlayerType::Attract => {
+                    if self.controls.any_key(wait)? {
+                        return Ok(QUITTING);
+                    }
+                }
+            }
+        }
+    }
+
+    // --versus: two games of one tunnel, each stepping at its own pace,
+    // until one of them crashes
+    fn versus(&mut self) -> io::Result<Match> {
+        let keys = self.versus.clone().unwrap_or_else(keymap::versus);
+        // laid out for two from the start
+        self.controls.resized = Some(terminal::size()?);
+        self.fit_terminal();
+        // the code shares the tunnel at the size it was played
+        let run = RunCode {
+            seed: self.seed.unwrap_or_else(rand::random),
+            config: RunConfig {
+                rows: self.rows,
+                cols: self.columns,
+                ..self.config
+            },
+        };
+        let base_tick = self
+            .tick_ms
+            .map_or(run.config.difficulty.tick(), Duration::from_millis);
+        let mut game = || -> io::Result<Game> {
+            let (mut builder, exit) = self.builder(&run)?;
+            let tunnel = Tunnel::with_config(
+                &mut builder,
+                self.rows,
+                self.columns,
+                run.config.difficulty.config(),
+            );
+            Ok(Game {
+                tunnel,
+                builder,
+                score: 0,
+                exit,
+                base_tick,
+                seed: run.seed,
+                started: self.clock.elapsed(),
+                replay: None,
+                moves: Vec::new(),
+                rewind: None,
+                ghost: None,
+                coins: 0,
+                coin_at: None,
+            })
+        };
+        let mut games = [game()?, game()?];
+        let mut lag = [Duration::ZERO; 2];
+        let mut last = Instant::now();
+        let message = loop {
+            if self.fit_terminal() {
+                for game in &mut games {
+                    game.tunnel
+                        .resize(&mut game.builder, self.rows, self.columns)
+                        .map_err(io::Error::other)?;
+                }
+            }
+            let now = Instant::now();
+            let mut crashed = [false; 2];
+            for ((game, lag), crashed) in
+                games.iter_mut().zip(&mut lag).zip(&mut crashed)
+            {
+                *lag += now - last;
+                while !*crashed && *lag >= self.tick(game) {
+                    *lag -= self.tick(game);
+                    *crashed = self.step(game)?;
+                }
+            }
+            last = now;
+            self.draw_versus(&games, false)?;
+            match crashed {
+                [true, true] => break "Draw!",
+                [true, false] => break "Player two wins!",
+                [false, true] => break "Player one wins!",
+                [false, false] => {}
+            }
+
+            match versus_step(&mut self.controls, &keys, FRAME) {
+                VersusInput::Moves([one, two]) => {
+                    self.apply(&mut games[0], one)?;
+                    self.apply(&mut games[1], two)?;
+                }
+                VersusInput::Pause => {
+                    let paused_at = Instant::now();
+                    self.draw_versus(&games, true)?;
+                    self.renderer.draw_banner(" PAUSED ")?;
+                    // any move resumes, as for one player, without moving
+                    let quit = loop {
+                        match versus_step(&mut self.controls, &keys, FRAME) {
+                            VersusInput::Quit => break true,
+                            VersusInput::Pause => break false,
+                            VersusInput::Moves(moves)
+                                if moves.iter().any(|m| !m.is_empty()) =>
+                            {
+                                break false;
+                            }
+                            _ => {}
+                        }
+                    };
+                    self.clock.skip(paused_at);
+                    if quit {
+                        break QUITTING;
+                    }
+                    last = Instant::now();
+                }
+                VersusInput::Quit => break QUITTING,
+                VersusInput::Restart => {}
+            }
+        };
+        let [one, two] = games;
+        Ok(Match {
+            message,
+            scores: [one.score, two.score],
+            run,
+            tunnels: [one.tunnel, two.tunnel],
+        })
+    }
+
+    // both games side by side, VERSUS_GAP apart, over one status bar
+    fn draw_versus(&mut self, games: &[Game; 2], dim: bool) -> io::Result<()> {
+        self.renderer.status = Status {
+            elapsed: self.clock.elapsed().saturating_sub(games[0].started),
+            versus: Some([games[0].score, games[1].score]),
+            seed: games[0].seed,
+            ..Status::default()
+        };
+        if dim {
+            self.renderer.dim = true;
+            self.renderer.diff.clear();
+        }
+        let lefts = [0, self.columns + VERSUS_GAP];
+        for (game, left) in games.iter().zip(lefts) {
+            let mut side = Beside {
+                inner: &mut self.renderer,
+                left,
+            };
+            draw_frame(&mut side, &game.tunnel, self.fog, game.score)?;
+        }
+        self.renderer.dim = false;
+        self.renderer.draw_hud(0)?;
+        self.renderer.end_frame()
+    }
+
+    // the crash behind who won, until r plays again (true) or q quits
+    fn versus_end(&mut self, round: &Match) -> io::Result<bool> {
+        let keys = self.versus.clone().unwrap_or_else(keymap::versus);
+        let since = Instant::now();
+        self.renderer.dim = true;
+        self.renderer.diff.clear();
+        let lefts = [0, self.columns + VERSUS_GAP];
+        for ((tunnel, score), left) in
+            round.tunnels.iter().zip(round.scores).zip(lefts)
+        {
+            let mut side = Beside {
+                inner: &mut self.renderer,
+                left,
+            };
+            draw_frame(&mut side, tunnel, self.fog, score)?;
+        }
+        self.renderer.dim = false;
+        self.renderer.end_frame()?;
+        self.renderer.draw_banner(&format!(
+            " {} r plays again, q quits ",
+            round.message
+        ))?;
+        let again = loop {
+            match versus_step(&mut self.controls, &keys, FRAME) {
+                VersusInput::Restart => break true,
+                VersusInput::Quit => break false,
+                VersusInput::Pause | VersusInput::Moves(_) => {}
+            }
+        };
+        self.clock.skip(since);
+        Ok(again)
+    }
+
+    // --connect: a lane in the match, beside a miniature of another
+    // player's, until one of them crashes. The match steps both; moves go
+    // out as they're pressed, and the screen shows what comes back
+    #[cfg(feature = "net")]
+    fn online(&mut self) -> io::Result<Duel> {
+        let mut client = match (self.client.take(), &self.connect) {
+            (Some(client), _) => client,
+            (None, Some(addr)) => Client::connect(addr)?,
+            (None, None) => return Err(io::Error::other("no match to join")),
+        };
+        // the match's size, whatever the terminal's
+        let width = client.cols + VERSUS_GAP + client.cols.div_ceil(MINIATURE);
+        self.controls.resized = Some(terminal::size()?);
+        self.controls.held = None;
+        let started = self.clock.elapsed();
+        let mut opponent = None;
+        let mut mine;
+        let mut theirs: Option<LaneView> = None;
+        let message = loop {
+            if let Some(screen) = self.controls.resized.take() {
+                self.renderer.layout(client.rows, width, screen);
+            }
+            client.poll()?;
+            // the first other lane still going
+            if opponent.is_none() {
+                opponent = client
+                    .lanes()
+                    .iter()
+                    .find(|&(&lane, view)| lane != client.lane && !view.crashed)
+                    .map(|(&lane, _)| lane);
+                if opponent.is_some() {
+                    self.renderer.diff.clear();
+                }
+            }
+            let gone = opponent
+                .is_some_and(|lane| !client.lanes().contains_key(&lane));
+            if !gone {
+                theirs = opponent
+                    .and_then(|lane| client.lanes().get(&lane).cloned());
+            }
+            mine = client.view().cloned().unwrap_or_default();
+            let elapsed = self.clock.elapsed().saturating_sub(started);
+            self.draw_online(
+                &mine,
+                theirs.as_ref(),
+                client.seed,
+                elapsed,
+                false,
+            )?;
+            match (mine.crashed, theirs.as_ref().map(|view| view.crashed)) {
+                (true, Some(true)) => break "Draw!",
+                (true, Some(false)) => break "You lose!",
+                (true, None) => break "Game over!",
+                // gone, crashed or quit, maybe before its crash came in:
+                // out either way
+                (false, Some(crashed)) if crashed || gone => break "You win!",
+                (false, _) => {}
+            }
+
+            match keyboard_step(&mut self.controls, FRAME) {
+                PlayerInput::Moves(moves) => {
+                    for action in moves {
+                        client.send(action)?;
+                    }
+                }
+                PlayerInput::Quit => break QUITTING,
+                // the match doesn't wait for anyone
+                _ => {}
+            }
+        };
+        Ok(Duel {
+            message,
+            mine,
+            theirs,
+            seed: client.seed,
+            elapsed: self.clock.elapsed().saturating_sub(started),
+            client: Some(client),
+        })
+    }
+
+    // your lane, and the opponent's in miniature VERSUS_GAP to its right
+    #[cfg(feature = "net")]
+    fn draw_online(
+        &mut self,
+        mine: &LaneView,
+        theirs: Option<&LaneView>,
+        seed: u64,
+        elapsed: Duration,
+        dim: bool,
+    ) -> io::Result<()> {
+        self.renderer.status = Status {
+            elapsed,
+            online: Some((mine.score, theirs.map(|view| view.score))),
+            seed,
+            ..Status::default()
+        };
+        if dim {
+            self.renderer.dim = true;
+            self.renderer.diff.clear();
+        }
+        mine.draw(
+            &mut Beside {
+                inner: &mut self.renderer,
+                left: 0,
+            },
+            1,
+        )?;
+        if let Some(theirs) = theirs {
+            let mut side = Beside {
+                inner: &mut self.renderer,
+                left: mine.cols + VERSUS_GAP,
+            };
+            theirs.draw(&mut side, MINIATURE)?;
+        }
+        self.renderer.dim = false;
+        self.renderer.draw_hud(0)?;
+        self.renderer.end_frame()
+    }
+
+    // --spectate: every lane in the match side by side, as many times
+    // smaller as it takes to fit, each over its score, until q quits
+    #[cfg(feature = "net")]
+    fn spectate(&mut self) -> io::Result<()> {
+        let Some(mut client) = self.client.take() else {
+            return Err(io::Error::other("no match to watch"));
+        };
+        self.controls.resized = Some(terminal::size()?);
+        self.controls.held = None;
+        let started = self.clock.elapsed();
+        let mut screen = (0, 0);
+        let mut shown = None;
+        let (mut scale, mut width) = (1, client.cols);
+        loop {
+            client.poll()?;
+            let players = client.lanes().len();
+            // laid out again as players come and go
+            let resized = self.controls.resized.take();
+            if resized.is_some() || shown != Some(players) {
+                screen = resized.unwrap_or(screen);
+                shown = Some(players);
+                let count = Idx::try_from(players).unwrap_or(Idx::MAX).max(1);
+                scale = fit_lanes(client.cols, count, screen.0);
+                width = client.cols.div_ceil(scale);
+                self.renderer.layout(
+                    client.rows.div_ceil(scale),
+                    count * width + (count - 1) * VERSUS_GAP,
+                    screen,
+                );
+            }
+            self.renderer.status = Status {
+                elapsed: self.clock.elapsed().saturating_sub(started),
+                watching: Some(players),
+                seed: client.seed,
+                ..Status::default()
+            };
+            let lefts = (0..).step_by(usize::from(width + VERSUS_GAP));
+            for (view, left) in client.lanes().values().zip(lefts.clone()) {
+                let mut side = Beside {
+                    inner: &mut self.renderer,
+                    left,
+                };
+                view.draw(&mut side, scale)?;
+            }
+            // scores along the far end, clear of the players
+            for ((&lane, view), left) in client.lanes().iter().zip(lefts) {
+                let out = if view.crashed { " out" } else { "" };
+                let label = format!(" P{} {}{out} ", lane + 1, view.score);
+                let label: String =
+                    label.chars().take(usize::from(width)).collect();
+                let row = view.rows().div_ceil(scale).saturating_sub(1);
+                self.renderer.queue_label(row, left, &label)?;
+            }
+            if players == 0 {
+                self.renderer.queue_banner(" waiting for players ")?;
+            }
+            self.renderer.draw_hud(0)?;
+            self.renderer.end_frame()?;
+            if let PlayerInput::Quit = keyboard_step(&mut self.controls, FRAME)
+            {
+                break;
+            }
+        }
+        client.close()
+    }
+
+    // the crash behind who won, until r joins again (true) or q quits
+    #[cfg(feature = "net")]
+    fn online_end(&mut self, duel: &Duel) -> io::Result<bool> {
+        let since = Instant::now();
+        self.draw_online(
+            &duel.mine,
+            duel.theirs.as_ref(),
+            duel.seed,
+            duel.elapsed,
+            true,
+        )?;
+        self.renderer.draw_banner(&format!(
+            " {} r plays again, q quits ",
+            duel.message
+        ))?;
+        let again = play_again(&mut self.controls);
+        self.clock.skip(since);
+        Ok(again)
+    }
+
+    // practice: takes the game back REWIND_STEPS rows, or as far as it
+    // can; outside practice there's nothing to go back to
+    fn rewind(&mut self, game: &mut Game) {
+        let Some(rewind) = &mut game.rewind else {
+            return;
+        };
+        if let Some(snapshot) = rewind.rewind(REWIND_STEPS) {
+            game.tunnel = snapshot.tunnel;
+            game.builder = snapshot.builder;
+            game.score = snapshot.score;
+            game.moves.clear();
+        }
+    }
+
+    // practice: the crash dimmed under a banner until it's rewound (true)
+    // or the player gives up
+    fn crashed(&mut self, game: &mut Game) -> io::Result<bool> {
+        let crashed_at = Instant::now();
+        self.renderer
+            .draw_dimmed(&game.tunnel, self.fog, game.score)?;
+        self.renderer
+            .draw_banner(" CRASHED  u rewinds, q gives up ")?;
+        self.controls.held = None;
+        let rewound = loop {
+            match self.controls.next(None) {
+                Ok(Some(KeyCommand::Rewind)) => break true,
+                Ok(Some(KeyCommand::Quit)) | Err(_) => break false,
+                Ok(_) => {}
+            }
+        };
+        self.clock.skip(crashed_at);
+        if rewound {
+            self.rewind(game);
+        }
+        Ok(rewound)
+    }
+
+    // the game dimmed under PAUSED until it resumes, or quits (true)
+    fn pause(&mut self, game: &Game) -> io::Result<bool> {
+        let paused_at = Instant::now();
+        self.renderer
+            .draw_dimmed(&game.tunnel, self.fog, game.score)?;
+        self.renderer.draw_banner(" PAUSED ")?;
+        let input = paused(&mut self.controls);
+        self.clock.skip(paused_at);
+        Ok(matches!(input, PlayerInput::Quit))
+    }
+
+    // the round's tunnel source, and for a campaign level the exit; a
+    // level sets the size it's played at
+    #[cfg(feature = "levels")]
+    fn builder(
+        &mut self,
+        run: &RunCode,
+    ) -> io::Result<(Builder, Option<(u64, &'static str)>)> {
+        let Some(campaign) = &self.campaign else {
+            return Ok((self.seeded(run), None));
+        };
+        let levels = &campaign.campaign.levels;
+        let level = &levels[campaign.level];
+        // levels were checked as they loaded
+        let builder = level.builder().map_err(io::Error::other)?;
+        let row = level.exit_row().map_err(io::Error::other)?;
+        let message = match campaign.level + 1 == levels.len() {
+            true => CAMPAIGN_COMPLETE,
+            false => LEVEL_CLEARED,
+        };
+        self.size = (Some(level.rows), Some(level.cols));
+        match self.output {
+            Output::Terminal => {
+                self.controls.resized = Some(terminal::size()?);
+                self.fit_terminal();
+            }
+            Output::Json => {
+                (self.rows, self.columns) = (level.rows, level.cols)
+            }
+        }
+        Ok((Builder::Level(builder), Some((row, message))))
+    }
+
+    #[cfg(not(feature = "levels"))]
+    fn builder(
+        &mut self,
+        run: &RunCode,
+    ) -> io::Result<(Builder, Option<(u64, &'static str)>)> {
+        Ok((self.seeded(run), None))
+    }
+
+    fn seeded(&self, run: &RunCode) -> Builder {
+        let b =
+            CoinDrops::new(SimpleBuilder::new(run.seed), self.coins, run.seed);
+        match self.hazards {
+            // a stream apart from the coins', or the two would be in step
+            Some(percent) => {
+                Builder::Pillars(Box::new(Pillars::new(b, percent, !run.seed)))
+            }
+            None => Builder::Seeded(Box::new(b)),
+        }
+    }
+
+    // moves the campaign on past the level just cleared, and keeps the
+    // progress; a file that can't be written only means it doesn't last
+    #[cfg(feature = "levels")]
+    fn level_cleared(&mut self) {
+        let Some(campaign) = &mut self.campaign else {
+            return;
+        };
+        campaign.level += 1;
+        let name = &campaign.campaign.name;
+        if let Some(path) = &campaign.path {
+            campaign.progress = load_progress(path);
+        }
+        campaign.progress.clear(name, campaign.level);
+        if let Some(path) = &campaign.path {
+            let _ = save_text(path, &campaign.progress.to_text());
+        }
+        // after the last, the campaign starts over
+        campaign.level %= campaign.campaign.levels.len();
+    }
+
+    // how a round ends other than by a crash or a quit: at a campaign
+    // level's exit, or out of time
+    fn finished(&self, game: &Game) -> Option<&'static str> {
+        match game.exit {
+            Some((row, message)) if game.score >= row => Some(message),
+            _ => self
+                .time_left(game)
+                .is_some_and(|left| left.is_zero())
+                .then_some(TIME_UP),
+        }
+    }
+
+    // what's left of a timed round, leaving out pauses
+    fn time_left(&self, game: &Game) -> Option<Duration> {
+        let played = self.clock.elapsed().saturating_sub(game.started);
+        Some(self.mode.time_limit()?.saturating_sub(played))
+    }
+
+    // speed zones scroll faster by shortening the tick
+    fn tick(&self, game: &Game) -> Duration {
+        self.progression.tick(game.base_tick, game.score)
+            / u32::try_from(game.tunnel.speed_multiplier()).unwrap_or(1)
+    }
+
+    // a move into a wall ends the moves until the next step, so two quick
+    // presses can't hop through a pillar
+    fn apply(
+        &mut self,
+        game: &mut Game,
+        actions: Vec<Action>,
+    ) -> io::Result<()> {
+        for action in actions {
+            if game.tunnel.is_collision() {
+                break;
+            }
+            self.log(Some(GameEvent::Input(action)), game.score)?;
+            action.apply(&mut game.tunnel);
+            if action != Action::Stay {
+                game.moves.push(action);
+            }
+        }
+        game.bank_coins(self.clock.elapsed());
+        Ok(())
+    }
+
+    // scrolls a row, scoring it as the mode does unless the player crashed
+    // (true)
+    fn step(&mut self, game: &mut Game) -> io::Result<bool> {
+        if let Some(rewind) = &mut game.rewind {
+            rewind.push(Snapshot {
+                tunnel: game.tunnel.fork(),
+                builder: game.builder.clone(),
+                score: game.score,
+            });
+        }
+        game.tunnel.step(&mut game.builder);
+        let moves = std::mem::take(&mut game.moves);
+        if let Some(replay) = &mut game.replay {
+            replay.steps.push(moves);
+        }
+        self.log(GameEvent::newest_row(&game.tunnel), game.score)?;
+        if game.tunnel.is_collision() {
+            let player = u64::from(game.tunnel.player());
+            self.log(Some(GameEvent::Collision { player }), game.score)?;
+            return Ok(true);
+        }
+        game.score += self.mode.row_points(&game.tunnel);
+        game.bank_coins(self.clock.elapsed());
+        self.log(GameEvent::milestone(game.score), game.score)?;
+        Ok(false)
+    }
+
+    fn record(&mut self, game: &Game) -> io::Result<()> {
+        if let Some((file, recorder)) = &mut self.cast {
+            let at = self.clock.elapsed();
+            let frame = recorder.frame(at, &game.tunnel, self.fog, game.score);
+            file.write_all(frame.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // where the best run of the replay's tunnel and mode is kept, beside
+    // the high scores; only for tunnels that come round again, from
+    // --seed, --code or --daily
+    fn ghost_path(&self, replay: &Replay) -> Option<PathBuf> {
+        if self.seed.is_none() && !self.daily {
+            return None;
+        }
+        let name = match replay.mode {
+            Mode::Endless => format!("{}.replay", replay.run),
+            mode => format!("{}-{mode}.replay", replay.run),
+        };
+        let dir = self.scores_path.as_ref()?.with_file_name("ghosts");
+        Some(dir.join(name))
+    }
+
+    // the best run kept for the replay's tunnel and mode; one that's
+    // missing, unreadable or for another tunnel is none
+    fn best_run(&self, replay: &Replay) -> Option<Replay> {
+        let text = fs::read_to_string(self.ghost_path(replay)?).ok()?;
+        let best = Replay::parse(&text).ok()?;
+        (best.run == replay.run && best.mode == replay.mode).then_some(best)
+    }
+
+    // keeps the round's run if it beats the best so far
+    fn save_ghost(&self, round: &Round) -> io::Result<()> {
+        let Some(replay) = &round.replay else {
+            return Ok(());
+        };
+        let Some(path) = self.ghost_path(replay) else {
+            return Ok(());
+        };
+        let best = self.best_run(replay).map_or(0, |best| {
+            best.score(&mut SimpleBuilder::new(best.run.seed))
+        });
+        match round.score > best {
+            true => save_text(&path, &replay.to_text()),
+            false => Ok(()),
+        }
+    }
+
+    // keeps a score that makes its table in the high-score file, under
+    // the player's initials, and says where it placed; a file that can't
+    // be written only means the score doesn't last. With --leaderboard the
+    // score goes online too, if the round can be played back
+    fn submit_score(&mut self, round: &Round) -> io::Result<Option<usize>> {
+        // another game may have saved scores since this one began
+        if let Some(path) = &self.scores_path {
+            self.scores = load_scores(path);
+        }
+        let Some(place) = self.scores.place(round.board(), round.score) else {
+            return Ok(None);
+        };
+        let title = match place {
+            0 => "NEW HIGH SCORE!",
+            _ => "TOP 10!",
+        };
+        let since = Instant::now();
+        let initials = self.initials(title)?;
+        self.clock.skip(since);
+        // the leaderboard being down costs only the online entry
+        #[cfg(feature = "online")]
+        if let (Some(leaderboard), Some(replay)) =
+            (&self.leaderboard, &round.replay)
+        {
+            let _ = leaderboard.submit(
+                replay,
+                round.score,
+                &initials,
+                round.daily.as_deref(),
+            );
+        }
+        // a daily score is dated by its challenge, even after midnight
+        let place = self.scores.insert(Entry {
+            config: round.run.config,
+            score: round.score,
+            initials,
+            date: match &round.daily {
+                Some(day) => day.clone(),
+                None => scores::date(unix_secs()),
+            },
+            seed: Some(round.run.seed),
+            daily: round.daily.is_some(),
+            mode: round.mode,
+        });
+        if let Some(path) = &self.scores_path {
+            let _ = save_text(path, &self.scores.to_text());
+        }
+        Ok(place)
+    }
+
+    // up to three letters or digits, typed under `title`; Backspace takes
+    // one back and Enter keeps them, ??? if there are none
+    fn initials(&mut self, title: &str) -> io::Result<String> {
+        let mut initials = String::new();
+        self.controls.held = None;
+        loop {
+            self.fit_terminal();
+            let lines = [
+                "Your initials".to_owned(),
+                format!("{initials:_<3}"),
+                String::new(),
+                "Enter to save".to_owned(),
+            ];
+            self.renderer.draw_menu(title, &lines, Some(1))?;
+            match event::read()? {
+                event::Event::Resize(cols, rows) => {
+                    self.controls.resized = Some((cols, rows));
+                }
+                event::Event::Key(KeyEvent {
+                    code,
+                    modifiers,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) => match code {
+                    KeyCode::Char(c)
+                        if c.is_ascii_alphanumeric()
+                            && !modifiers.contains(KeyModifiers::CONTROL)
+                            && initials.len() < 3 =>
+                    {
+                        initials.push(c.to_ascii_uppercase());
+                    }
+                    KeyCode::Backspace => {
+                        initials.pop();
+                    }
+                    KeyCode::Enter => break,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        if initials.is_empty() {
+            initials.push_str("???");
+        }
+        Ok(initials)
+    }
+
+    // the campaign's levels, if the round finished it
+    #[cfg(feature = "levels")]
+    fn won(&self, round: &Round) -> Option<Vec<String>> {
+        let campaign = self.campaign.as_ref()?;
+        let mut lines = vec![CAMPAIGN_COMPLETE.to_owned(), String::new()];
+        lines.extend(
+            campaign.campaign.levels.iter().zip(1..).map(|(level, n)| {
+                format!("{n:>2}. {:<24} cleared", level.name)
+            }),
+        );
+        (round.message == CAMPAIGN_COMPLETE).then_some(lines)
+    }
+
+    #[cfg(not(feature = "levels"))]
+    fn won(&self, _round: &Round) -> Option<Vec<String>> {
+        None
+    }
+
+    // the round's last frame, faded, until restart (true) or quit; the
+    // recordings skip the wait like a pause. A round that made its table
+    // shows the table instead, with the round's place highlighted
+    fn end_screen(
+        &mut self,
+        round: &Round,
+        place: Option<usize>,
+    ) -> io::Result<bool> {
+        let since = Instant::now();
+        let quit = match self.menu {
+            true => "q for the menu",
+            false => "q quits",
+        };
+        if let Some(mut lines) = self.won(round) {
+            lines.push(String::new());
+            lines.push(format!("r plays it again, {quit}"));
+            self.renderer.draw_menu("YOU WIN!", &lines, None)?;
+            let again = play_again(&mut self.controls);
+            self.clock.skip(since);
+            return Ok(again);
+        }
+        match place {
+            Some(place) => {
+                let mut lines = table(&self.scores, round.board());
+                lines.push(String::new());
+                lines.push(format!("r restarts, {quit}"));
+                let title = match place {
+                    0 => format!("{} NEW HIGH SCORE!", round.message),
+                    _ => round.message.to_owned(),
+                };
+                self.renderer.draw_menu(&title, &lines, Some(place))?;
+            }
+            None => {
+                self.renderer.draw_dimmed(
+                    &round.tunnel,
+                    self.fog,
+                    round.score,
+                )?;
+                self.renderer.draw_banner(&format!(
+                    " {} r restarts, {quit} ",
+                    round.message
+                ))?;
+            }
+        }
+        let again = play_again(&mut self.controls);
+        self.clock.skip(since);
+        Ok(again)
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    if let Some(path) = &args.verify {
+        let replay = load_replay(path)?;
+        let score = replay.score(&mut SimpleBuilder::new(replay.run.seed));
+        println!("{{\"score\":{score},\"hash\":\"{:016x}\"}}", replay.hash());
+        return Ok(());
+    }
+    let replay = args.replay.as_deref().map(load_replay).transpose()?;
+    // a replay brings its own run and mode, as a run code would
+    let code = args.code.or(replay.as_ref().map(|replay| replay.run));
+    let mode = args.mode.or(replay.as_ref().map(|replay| replay.mode));
+    let player_type = if let Some(replay) = replay {
+        PlayerType::Replay(Playback {
+            replay,
+            next: 0,
+            speed: args.replay_speed,
+        })
+    } else if let Some(cmd) = &args.bot_cmd {
+        PlayerType::Bot(Bot::spawn(cmd)?)
+    } else if args.demo {
+        PlayerType::SelfDemo
+    } else {
+        PlayerType::Keyboard
+    };
+
+    let output = args.output;
+    #[cfg(feature = "net")]
+    let online = args.connect.is_some() || args.spectate.is_some();
+    #[cfg(not(feature = "net"))]
+    let online = false;
+    let mut keys = Keymap::preset(&args.keys).unwrap_or_default();
+    let config = match args.config {
+        Some(path) => Some(fs::read_to_string(path)?),
+        None => default_config().and_then(|path| fs::read_to_string(path).ok()),
+    };
+    if let Some(config) = config {
+        keys.apply(&config).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("config: {e}"))
+        })?;
+    }
+    // no gamepad support on this system just means the keyboard alone
+    let controls = Controls {
+        keys,
+        releases: false,
+        repeat: Duration::from_millis(args.repeat_ms),
+        held: None,
+        #[cfg(feature = "gamepad")]
+        pads: Gamepads::new().ok(),
+        resized: None,
+    };
+    // JSON frames go to a pipe, not a screen, so any size fits
+    let screen = match output {
+        Output::Terminal => Some(terminal::size()?),
+        Output::Json => None,
+    };
+    // on a screen, the status bar takes the bottom line
+    let (terminal_columns, terminal_rows) = match screen {
+        Some((cols, rows)) => (cols, rows.saturating_sub(1)),
+        None => (80, 24),
+    };
+    let config = match code {
+        Some(code) => code.config,
+        None if args.daily => runcode::DAILY,
+        None => RunConfig {
+            rows: args.rows.unwrap_or(terminal_rows),
+            cols: args.cols.unwrap_or(terminal_columns),
+            difficulty: args.difficulty.unwrap_or_default(),
+        },
+    };
+    // only show this many rows ahead of the player, for a harder game
+    let fog = args.fog.unwrap_or(usize::MAX);
+    // a game bigger than the terminal shrinks to fit it
+    let (rows, columns) = match screen {
+        Some(_) => (
+            cmp::min(config.rows, terminal_rows),
+            cmp::min(config.cols, terminal_columns),
+        ),
+        None => (config.rows, config.cols),
+    };
+    // shrunk, the tunnel wouldn't be the one the moves were made in
+    if matches!(player_type, PlayerType::Replay(_))
+        && (rows, columns) != (config.rows, config.cols)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "the replay needs a {}x{} game, and the terminal only fits \
+                 {rows}x{columns}",
+                config.rows, config.cols
+            ),
+        ));
+    }
+
+    // --export-gif alone still keeps the recording, beside the GIF
+    let gif = args.export_gif;
+    let cast_path = args
+        .export_cast
+        .or_else(|| gif.as_ref().map(|gif| gif.with_extension("cast")));
+    let cast = match &cast_path {
+        Some(path) => {
+            let mut file = BufWriter::new(File::create(path)?);
+            file.write_all(cast::header(columns, rows).as_bytes())?;
+            Some((file, Recorder::new(rows)))
+        }
+        None => None,
+    };
+    let tty = match args.record_tty {
+        Some(_) if output == Output::Json => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--record-tty records the terminal, not --output json",
+            ));
+        }
+        Some(path) => Some((BufWriter::new(File::create(path)?), Vec::new())),
+        None => None,
+    };
+    let events = match args.event_log {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+
+    let mut renderer = TerminalRenderer {
+        stdout: Screen {
+            stdout: io::stdout(),
+            tty,
+        },
+        status_row: rows,
+        columns,
+        origin: (0, 0),
+        dim: false,
+        theme: Theme::Color,
+        status: Status::default(),
+        diff: FrameDiff::default(),
+        caption: None,
+    };
+
+    if let Some(screen) = screen {
+        renderer.layout(rows, columns, screen);
+    }
+
+    let mut session = Session {
+        output,
+        renderer,
+        controls,
+        lines: io::stdin().lines(),
+        cast,
+        events,
+        clock: Clock {
+            started: Instant::now(),
+            paused: Cell::new(Duration::ZERO),
+        },
+        fog,
+        tick_ms: args.tick_ms,
+        progression: Progression {
+            every: args.level_every,
+            percent: args.level_speedup,
+            floor: Duration::from_millis(args.min_tick_ms),
+        },
+        rows,
+        columns,
+        size: match code {
+            Some(code) => (Some(code.config.rows), Some(code.config.cols)),
+            None if args.daily => (Some(config.rows), Some(config.cols)),
+            None => (args.rows, args.cols),
+        },
+        config,
+        seed: code.map(|code| code.seed).or(args.seed),
+        daily: args.daily,
+        practice: args.practice,
+        mode: mode.unwrap_or_default(),
+        // bots, and the demo asked for by name, go straight to the game
+        menu: output == Output::Terminal
+            && matches!(player_type, PlayerType::Keyboard)
+            && !args.versus
+            && !online,
+        selected: 0,
+        attract: (args.attract > 0).then(|| Duration::from_secs(args.attract)),
+        versus: args.versus.then(keymap::versus),
+        coins: args.coins,
+        hazards: args.hazards,
+        #[cfg(feature = "net")]
+        connect: args.connect,
+        #[cfg(feature = "net")]
+        client: None,
+        scores_path: args.scores.or_else(default_scores),
+        scores: HighScores::default(),
+        #[cfg(feature = "online")]
+        leaderboard: args.leaderboard.as_deref().map(Leaderboard::new),
+        #[cfg(feature = "levels")]
+        campaign: None,
+    };
+    // a campaign picks up at its first uncleared level, or starts over
+    #[cfg(feature = "levels")]
+    if let Some(dir) = &args.campaign {
+        let campaign = Campaign::load(dir).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("campaign: {e}"))
+        })?;
+        let path = default_progress();
+        let progress = path.as_deref().map(load_progress).unwrap_or_default();
+        let level = progress.cleared(&campaign.name) % campaign.levels.len();
+        session.campaign = Some(CampaignRun {
+            campaign,
+            level,
+            progress,
+            path,
+        });
+    }
+    if let Some(path) = &session.scores_path {
+        session.scores = load_scores(path);
+    }
+
+    // a match that can't be joined is better said before the screen goes
+    #[cfg(feature = "net")]
+    if let Some(addr) = &session.connect {
+        session.client = Some(Client::connect(addr)?);
+    }
+    #[cfg(feature = "net")]
+    if let Some(addr) = &args.spectate {
+        session.client = Some(Client::spectate(addr)?);
+    }
+
+    if output == Output::Terminal {
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(session.renderer.stdout, EnterAlternateScreen)?;
+        // Windows always reports releases; elsewhere the terminal has to
+        // be asked, if it can
+        session.controls.releases = cfg!(windows)
+            || matches!(terminal::supports_keyboard_enhancement(), Ok(true));
+        if session.controls.releases && !cfg!(windows) {
+            crossterm::execute!(
+                session.renderer.stdout,
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                )
+            )?;
+        }
+    }
+
+    let mut last_round = None;
+    let mut last_match = None;
+    #[cfg(feature = "net")]
+    let mut last_duel = None;
+    let mut state = match (session.menu, args.versus) {
+        (true, _) => State::Menu,
+        (false, true) => State::Versus,
+        (false, false) => State::Play(player_type),
+    };
+    #[cfg(feature = "net")]
+    if session.connect.is_some() {
+        state = State::Online;
+    } else if args.spectate.is_some() {
+        state = State::Spectate;
+    }
+    loop {
+        state = match state {
+            State::Menu => match session.menu()? {
+                Some(state) => state,
+                None => break,
+            },
+            State::HighScores => {
+                session.high_scores()?;
+                State::Menu
+            }
+            State::Versus => {
+                let round = session.versus()?;
+                let again =
+                    round.message != QUITTING && session.versus_end(&round)?;
+                last_match = Some(round);
+                match again {
+                    true => State::Versus,
+                    false => break,
+                }
+            }
+            #[cfg(feature = "net")]
+            State::Online => {
+                let mut duel = session.online()?;
+                let again =
+                    duel.message != QUITTING && session.online_end(&duel)?;
+                if let Some(client) = duel.client.take() {
+                    client.close()?;
+                }
+                last_duel = Some(duel);
+                match again {
+                    true => State::Online,
+                    false => break,
+                }
+            }
+            #[cfg(feature = "net")]
+            State::Spectate => {
+                session.spectate()?;
+                break;
+            }
+            // back to the menu however it ends, with nothing kept
+            State::Attract => {
+                session.renderer.caption = Some(ATTRACT);
+                let round = session.play(&mut PlayerType::Attract);
+                session.renderer.caption = None;
+                round?;
+                State::Menu
+            }
+            State::Play(mut player_type) => {
+                let round = session.play(&mut player_type)?;
+                if let (Some(path), Some(replay)) =
+                    (&args.record, &round.replay)
+                {
+                    fs::write(path, replay.to_text())?;
+                }
+                #[cfg(feature = "levels")]
+                if matches!(round.message, LEVEL_CLEARED | CAMPAIGN_COMPLETE) {
+                    session.level_cleared();
+                }
+                // only the player's own endless or timed rounds on screen,
+                // played to the end
+                let place = match output == Output::Terminal
+                    && matches!(player_type, PlayerType::Keyboard)
+                    && round.message != QUITTING
+                    && round.replay.is_some()
+                {
+                    true => {
+                        session.save_ghost(&round)?;
+                        session.submit_score(&round)?
+                    }
+                    false => None,
+                };
+                let again = output == Output::Terminal
+                    && round.message != QUITTING
+                    && session.end_screen(&round, place)?;
+                last_round = Some(round);
+                match (again, session.menu) {
+                    (true, _) => State::Play(player_type),
+                    (false, true) => State::Menu,
+                    (false, false) => break,
+                }
+            }
+        };
+    }
+    if let Some(file) = &mut session.events {
+        file.flush()?;
+    }
+    if let Some((file, _)) = &mut session.cast {
+        file.flush()?;
+    }
+
+    if output == Output::Json {
+        if let Some(Round {
+            message,
+            score,
+            run,
+            ..
+        }) = last_round
+        {
+            println!(
+                "{{\"game_over\":\"{message}\",\"score\":{score},\
+                 \"code\":\"{run}\"}}"
+            );
+        }
+        if let (Some(cast), Some(gif)) = (&cast_path, &gif) {
+            encode_gif(cast, gif)?;
+        }
+        return Ok(());
+    }
+
+    if session.controls.releases && !cfg!(windows) {
+        crossterm::execute!(
+            session.renderer.stdout,
+            PopKeyboardEnhancementFlags
+        )?;
+    }
+    crossterm::execute!(session.renderer.stdout, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    if let Some(round) = last_round {
+        println!("{} Final score: {}", round.message, round.score);
+        println!("Share this tunnel with --code {}", round.run);
+    }
+    if let Some(round) = last_match {
+        let [one, two] = round.scores;
+        println!("{} Final scores: {one} to {two}", round.message);
+        println!("Share this tunnel with --code {}", round.run);
+    }
+    #[cfg(feature = "net")]
+    if let Some(duel) = last_duel {
+        match duel.theirs {
+            Some(theirs) => println!(
+                "{} Final scores: {} to {}",
+                duel.message, duel.mine.score, theirs.score
+            ),
+            None => {
+                println!("{} Final score: {}", duel.message, duel.mine.score)
+            }
+        }
+    }
+    if let (Some(cast), Some(gif)) = (&cast_path, &gif) {
+        encode_gif(cast, gif)?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_line_shows_what_the_round_has() {
+        let status = Status {
+            level: Some(3),
+            tick: Duration::from_millis(100),
+            fuel: Some(5),
+            elapsed: Duration::from_secs(75),
+            seed: 7,
+            ..Status::default()
+        };
+        assert_eq!(
+            status.line(42),
+            " 42  level 3  10.0 rows/s  fuel 5  1:15  seed 7 "
+        );
+        let timed = Status {
+            left: Some(Duration::from_millis(1500)),
+            coins: Some(2),
+            coin_tick: true,
+            ..Status::default()
+        };
+        assert_eq!(timed.line(0), " 0  * 2 +10  0:02 left  seed 0 ");
+        let versus = Status {
+            versus: Some([4, 9]),
+            ..Status::default()
+        };
+        assert_eq!(versus.line(0), " P1 4  P2 9  0:00  seed 0 ");
+        let online = Status {
+            online: Some((3, None)),
+            ..Status::default()
+        };
+        assert!(online.line(0).starts_with(" you 3  no opponent yet  "));
+        let watching = Status {
+            watching: Some(1),
+            ..Status::default()
+        };
+        assert!(watching.line(0).starts_with(" watching 1 player  "));
+    }
+
+    #[test]
+    fn turn_wraps_both_ways() {
+        assert!(turn(&Theme::ALL, Theme::Color, false) == Theme::Mono);
+        assert!(turn(&Theme::ALL, Theme::Mono, true) == Theme::Color);
+        assert_eq!(turn(&[1, 2, 3], 1, true), 3);
+        assert_eq!(turn(&[1, 2, 3], 3, false), 1);
+        // anything not in the list counts as the first
+        assert_eq!(turn(&[1, 2, 3], 7, false), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn lanes_shrink_only_as_far_as_they_must() {
+        assert_eq!(fit_lanes(20, 2, 80), 1);
+        assert_eq!(fit_lanes(20, 2, 42), 1);
+        assert_eq!(fit_lanes(20, 2, 41), 2);
+        assert_eq!(fit_lanes(20, 4, 40), 3);
+        // past one column a lane, there's nothing more to shrink
+        assert_eq!(fit_lanes(20, 100, 10), 20);
+        assert_eq!(fit_lanes(0, 1, 10), 1);
+    }
+}
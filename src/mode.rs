@@ -0,0 +1,120 @@
+// Game modes, and how each scores a row, so every frontend and the
+// leaderboard's checks agree. Endless is the classic game: a point per row
+// until the crash. Time attack gives the player TIME_ATTACK to score what
+// they can, with bonuses for rows survived brushing a wall or inside a
+// speed zone, and ends when the time runs out as well as on a crash.
+
+use crate::{Tunnel, TunnelIndex};
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+pub const TIME_ATTACK: Duration = Duration::from_secs(120);
+// points on top of the row's own, in time attack
+pub const NEAR_MISS_BONUS: u64 = 2;
+pub const SPEED_ZONE_BONUS: u64 = 1;
+// points for each coin picked up, in any mode
+pub const COIN_POINTS: u64 = 10;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Mode {
+    #[default]
+    Endless,
+    TimeAttack,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 2] = [Mode::Endless, Mode::TimeAttack];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Endless => "endless",
+            Mode::TimeAttack => "time-attack",
+        }
+    }
+
+    // how long a round lasts, if it's timed; the clock stops while paused
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            Mode::Endless => None,
+            Mode::TimeAttack => Some(TIME_ATTACK),
+        }
+    }
+
+    // the points for surviving the step `t` just took, which didn't crash
+    pub fn row_points<T: TunnelIndex, const N: usize>(
+        self,
+        t: &Tunnel<T, N>,
+    ) -> u64 {
+        match self {
+            Mode::Endless => 1,
+            Mode::TimeAttack => {
+                let near_miss = t.clearance().is_some_and(|c| c.is_zero());
+                let speed_zone = t.speed_multiplier() > 1;
+                1 + u64::from(near_miss) * NEAR_MISS_BONUS
+                    + u64::from(speed_zone) * SPEED_ZONE_BONUS
+            }
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownMode(pub String);
+
+impl fmt::Display for UnknownMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown mode {:?}", self.0)
+    }
+}
+
+impl core::error::Error for UnknownMode {}
+
+impl FromStr for Mode {
+    type Err = UnknownMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::ALL
+            .into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownMode(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::seeded::Seeded;
+
+    #[test]
+    fn names_round_trip() {
+        for mode in Mode::ALL {
+            assert_eq!(mode.name().parse(), Ok(mode));
+        }
+        assert_eq!("Time-Attack".parse(), Ok(Mode::TimeAttack));
+        assert_eq!(
+            "sprint".parse::<Mode>(),
+            Err(UnknownMode("sprint".to_string()))
+        );
+        assert_eq!(Mode::Endless.time_limit(), None);
+        assert_eq!(Mode::TimeAttack.time_limit(), Some(TIME_ATTACK));
+    }
+
+    #[test]
+    fn time_attack_pays_for_brushing_walls() {
+        let mut b = Seeded::new(3);
+        let mut t: Tunnel<u16> = Tunnel::new(&mut b, 10, 12);
+        while t.clearance().is_some_and(|c| c > 0) {
+            t.move_player_left();
+        }
+        assert_eq!(Mode::Endless.row_points(&t), 1);
+        assert_eq!(Mode::TimeAttack.row_points(&t), 1 + NEAR_MISS_BONUS);
+    }
+}
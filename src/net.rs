@@ -0,0 +1,987 @@
+// Multiplayer over WebSocket: one authoritative Match, one lane per player.
+// Every lane is a Tunnel grown from the match seed, so players who join
+// together race the same walls; a late joiner's lane starts from the top.
+// A match thread steps every lane once per tick and broadcasts what
+// changed; inputs carry sequence numbers, and each lane reports the last
+// one it applied so clients can reconcile their prediction.
+//
+// Binary messages, fields big-endian as in server:
+//
+//   input    0x01 seq:u32 action:u8 (sim::Action::code())
+//
+//   welcome  0x81 lane:u32 rows:u16 cols:u16 seed:u64 difficulty:u8
+//   keyframe 0x82 tick:u64 count:u16, count times:
+//                 lane:u32 acked:u32 score:u64 crashed:u8 player:u16
+//                 rows:u16 cols:u16 cells:[u8; rows * cols]
+//   delta    0x83 tick:u64 count:u16, count times:
+//                 lane:u32 acked:u32 score:u64 crashed:u8 player:u16
+//                 cols:u16 row:[u8; cols]
+//   left     0x84 lane:u32
+//   error    0xff server::ProtocolError::code():u8
+//
+// Cells are TunnelCellType::code()s, row-major, front row first. A delta
+// scrolls each lane: drop the front row, append `row` at the back, put the
+// player at `player` in the new front row. Crashed lanes stop scrolling.
+// Every KEYFRAME_EVERY ticks a keyframe replaces the deltas, so missed or
+// misapplied ones do not last.
+//
+// A client that connects to /spectate watches without a lane: its welcome's
+// lane is SPECTATOR, it gets every broadcast, and its inputs are dropped.
+//
+// Client is the other end: it joins, sends inputs and keeps a LaneView of
+// every lane from what the match broadcasts.
+
+use crate::difficulty::Difficulty;
+use crate::env::screen_codes;
+use crate::render::Renderer;
+use crate::seeded::Seeded;
+use crate::server::{Fields, ProtocolError};
+use crate::sim::Action;
+use crate::{RowInfo, Tunnel, TunnelCellType};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::string::String;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+use std::vec::Vec;
+use tungstenite::handshake::server::Request;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Error as WsError, Message, WebSocket};
+
+pub const KEYFRAME_EVERY: u64 = 64;
+
+// the lane in a spectator's welcome, which no player gets
+pub const SPECTATOR: u32 = u32::MAX;
+
+// how often a connection looks for broadcasts while waiting on its client
+const POLL: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    Input { seq: u32, action: Action },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LaneState {
+    pub lane: u32,
+    pub acked: u32,
+    pub score: u64,
+    pub crashed: bool,
+    pub player: u16,
+    pub cols: u16,
+    // every row for a keyframe, the new back row for a delta
+    pub cells: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    Welcome {
+        lane: u32,
+        rows: u16,
+        cols: u16,
+        seed: u64,
+        difficulty: u8,
+    },
+    Keyframe {
+        tick: u64,
+        lanes: Vec<LaneState>,
+    },
+    Delta {
+        tick: u64,
+        lanes: Vec<LaneState>,
+    },
+    Left {
+        lane: u32,
+    },
+    Error(ProtocolError),
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let ClientMessage::Input { seq, action } = *self;
+        let mut out = vec![0x01];
+        out.extend_from_slice(&seq.to_be_bytes());
+        out.push(action.code());
+        out
+    }
+
+    pub fn decode(message: &[u8]) -> Result<ClientMessage, ProtocolError> {
+        let mut f = Fields(message);
+        let message = match f.u8()? {
+            0x01 => ClientMessage::Input {
+                seq: f.u32()?,
+                action: Action::from_code(f.u8()?)
+                    .ok_or(ProtocolError::BadArgument)?,
+            },
+            _ => return Err(ProtocolError::Malformed),
+        };
+        f.end()?;
+        Ok(message)
+    }
+}
+
+fn encode_lanes(out: &mut Vec<u8>, lanes: &[LaneState], keyframe: bool) {
+    let count = u16::try_from(lanes.len()).unwrap_or(u16::MAX);
+    out.extend_from_slice(&count.to_be_bytes());
+    for l in lanes.iter().take(usize::from(count)) {
+        out.extend_from_slice(&l.lane.to_be_bytes());
+        out.extend_from_slice(&l.acked.to_be_bytes());
+        out.extend_from_slice(&l.score.to_be_bytes());
+        out.push(u8::from(l.crashed));
+        out.extend_from_slice(&l.player.to_be_bytes());
+        if keyframe {
+            let rows = l.cells.len() / usize::from(l.cols).max(1);
+            let rows = u16::try_from(rows).unwrap_or(u16::MAX);
+            out.extend_from_slice(&rows.to_be_bytes());
+        }
+        out.extend_from_slice(&l.cols.to_be_bytes());
+        out.extend_from_slice(&l.cells);
+    }
+}
+
+fn decode_lanes(
+    f: &mut Fields<'_>,
+    keyframe: bool,
+) -> Result<Vec<LaneState>, ProtocolError> {
+    let count = f.u16()?;
+    let mut lanes = Vec::new();
+    for _ in 0..count {
+        let lane = f.u32()?;
+        let acked = f.u32()?;
+        let score = f.u64()?;
+        let crashed = f.u8()? != 0;
+        let player = f.u16()?;
+        let rows = if keyframe { f.u16()? } else { 1 };
+        let cols = f.u16()?;
+        let cells = f.bytes(usize::from(rows) * usize::from(cols))?.to_vec();
+        lanes.push(LaneState {
+            lane,
+            acked,
+            score,
+            crashed,
+            player,
+            cols,
+            cells,
+        });
+    }
+    Ok(lanes)
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            ServerMessage::Welcome {
+                lane,
+                rows,
+                cols,
+                seed,
+                difficulty,
+            } => {
+                out.push(0x81);
+                out.extend_from_slice(&lane.to_be_bytes());
+                out.extend_from_slice(&rows.to_be_bytes());
+                out.extend_from_slice(&cols.to_be_bytes());
+                out.extend_from_slice(&seed.to_be_bytes());
+                out.push(*difficulty);
+            }
+            ServerMessage::Keyframe { tick, lanes } => {
+                out.push(0x82);
+                out.extend_from_slice(&tick.to_be_bytes());
+                encode_lanes(&mut out, lanes, true);
+            }
+            ServerMessage::Delta { tick, lanes } => {
+                out.push(0x83);
+                out.extend_from_slice(&tick.to_be_bytes());
+                encode_lanes(&mut out, lanes, false);
+            }
+            ServerMessage::Left { lane } => {
+                out.push(0x84);
+                out.extend_from_slice(&lane.to_be_bytes());
+            }
+            ServerMessage::Error(e) => {
+                out.push(0xff);
+                out.push(e.code());
+            }
+        }
+        out
+    }
+
+    pub fn decode(message: &[u8]) -> Result<ServerMessage, ProtocolError> {
+        let mut f = Fields(message);
+        let message = match f.u8()? {
+            0x81 => ServerMessage::Welcome {
+                lane: f.u32()?,
+                rows: f.u16()?,
+                cols: f.u16()?,
+                seed: f.u64()?,
+                difficulty: f.u8()?,
+            },
+            0x82 => ServerMessage::Keyframe {
+                tick: f.u64()?,
+                lanes: decode_lanes(&mut f, true)?,
+            },
+            0x83 => ServerMessage::Delta {
+                tick: f.u64()?,
+                lanes: decode_lanes(&mut f, false)?,
+            },
+            0x84 => ServerMessage::Left { lane: f.u32()? },
+            0xff => ServerMessage::Error(
+                ProtocolError::from_code(f.u8()?)
+                    .ok_or(ProtocolError::Malformed)?,
+            ),
+            _ => return Err(ProtocolError::Malformed),
+        };
+        f.end()?;
+        Ok(message)
+    }
+}
+
+struct Lane {
+    tunnel: Tunnel<u16>,
+    builder: Seeded,
+    score: u64,
+    acked: u32,
+}
+
+impl Lane {
+    fn state(&self, id: u32, keyframe: bool) -> LaneState {
+        let cells = screen_codes(&self.tunnel);
+        let cols = self.tunnel.screen_width;
+        let back = cells.len().saturating_sub(usize::from(cols));
+        LaneState {
+            lane: id,
+            acked: self.acked,
+            score: self.score,
+            crashed: self.tunnel.is_collision(),
+            player: self.tunnel.player(),
+            cols,
+            cells: match keyframe {
+                true => cells,
+                false => cells[back..].to_vec(),
+            },
+        }
+    }
+}
+
+// the authoritative game state; net::serve runs one
+pub struct Match {
+    rows: u16,
+    cols: u16,
+    seed: u64,
+    difficulty: Difficulty,
+    lanes: BTreeMap<u32, Lane>,
+    next_lane: u32,
+    tick: u64,
+}
+
+impl Match {
+    pub fn new(
+        rows: u16,
+        cols: u16,
+        seed: u64,
+        difficulty: Difficulty,
+    ) -> Match {
+        Match {
+            rows,
+            cols,
+            seed,
+            difficulty,
+            lanes: BTreeMap::new(),
+            next_lane: 0,
+            tick: 0,
+        }
+    }
+
+    pub fn join(&mut self) -> (u32, ServerMessage) {
+        let id = self.next_lane;
+        self.next_lane = match self.next_lane.wrapping_add(1) {
+            SPECTATOR => 0,
+            next => next,
+        };
+        let mut builder = Seeded::new(self.seed);
+        let tunnel = Tunnel::with_config(
+            &mut builder,
+            self.rows,
+            self.cols,
+            self.difficulty.config(),
+        );
+        let lane = Lane {
+            tunnel,
+            builder,
+            score: 0,
+            acked: 0,
+        };
+        self.lanes.insert(id, lane);
+        (id, self.welcome(id))
+    }
+
+    // a spectator's welcome; it joins no lane
+    pub fn watch(&self) -> ServerMessage {
+        self.welcome(SPECTATOR)
+    }
+
+    fn welcome(&self, lane: u32) -> ServerMessage {
+        let difficulty =
+            Difficulty::ALL.iter().position(|&d| d == self.difficulty);
+        ServerMessage::Welcome {
+            lane,
+            rows: self.rows,
+            cols: self.cols,
+            seed: self.seed,
+            difficulty: difficulty.map_or(0, |d| d as u8),
+        }
+    }
+
+    pub fn leave(&mut self, lane: u32) -> Option<ServerMessage> {
+        self.lanes.remove(&lane)?;
+        Some(ServerMessage::Left { lane })
+    }
+
+    // moves at once; inputs that arrive out of order, repeated or for a
+    // crashed lane are dropped
+    pub fn input(&mut self, lane: u32, seq: u32, action: Action) -> bool {
+        let Some(lane) = self.lanes.get_mut(&lane) else {
+            return false;
+        };
+        if seq <= lane.acked || lane.tunnel.is_collision() {
+            return false;
+        }
+        lane.acked = seq;
+        action.apply(&mut lane.tunnel);
+        true
+    }
+
+    pub fn keyframe(&self) -> ServerMessage {
+        ServerMessage::Keyframe {
+            tick: self.tick,
+            lanes: self
+                .lanes
+                .iter()
+                .map(|(&id, l)| l.state(id, true))
+                .collect(),
+        }
+    }
+
+    pub fn tick(&mut self) -> ServerMessage {
+        self.tick += 1;
+        for lane in self.lanes.values_mut() {
+            if !lane.tunnel.is_collision() {
+                lane.tunnel.step(&mut lane.builder);
+                if !lane.tunnel.is_collision() {
+                    lane.score += 1;
+                }
+            }
+        }
+        if self.tick.is_multiple_of(KEYFRAME_EVERY) {
+            return self.keyframe();
+        }
+        ServerMessage::Delta {
+            tick: self.tick,
+            lanes: self
+                .lanes
+                .iter()
+                .map(|(&id, l)| l.state(id, false))
+                .collect(),
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.lanes.len()
+    }
+}
+
+// the match and where to send each lane's broadcasts, and the spectators'
+pub struct Hub {
+    game: Match,
+    outboxes: HashMap<u32, Sender<Vec<u8>>>,
+    spectators: Vec<Sender<Vec<u8>>>,
+}
+
+impl Hub {
+    pub fn new(game: Match) -> Hub {
+        Hub {
+            game,
+            outboxes: HashMap::new(),
+            spectators: Vec::new(),
+        }
+    }
+
+    pub fn game(&self) -> &Match {
+        &self.game
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let message = message.encode();
+        self.outboxes
+            .retain(|_, out| out.send(message.clone()).is_ok());
+        self.spectators
+            .retain(|out| out.send(message.clone()).is_ok());
+    }
+
+    // steps the match and tells everyone
+    pub fn tick(&mut self) {
+        let update = self.game.tick();
+        self.broadcast(&update);
+    }
+}
+
+fn lock(hub: &Mutex<Hub>) -> MutexGuard<'_, Hub> {
+    hub.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn ws_error(e: WsError) -> io::Error {
+    match e {
+        WsError::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+// Upgrades `stream`, joins a lane and plays it until the client leaves:
+// broadcasts go out as they come, inputs go to the match. A spectator gets
+// the broadcasts alone.
+pub fn handle_connection(
+    stream: TcpStream,
+    hub: &Mutex<Hub>,
+) -> io::Result<()> {
+    let mut spectating = false;
+    // the error type is tungstenite's to pick
+    #[allow(clippy::result_large_err)]
+    let role = |request: &Request, response| {
+        spectating = request.uri().path() == "/spectate";
+        Ok(response)
+    };
+    let mut ws =
+        tungstenite::accept_hdr(stream, role).map_err(|e| match e {
+            tungstenite::HandshakeError::Failure(e) => ws_error(e),
+            tungstenite::HandshakeError::Interrupted(_) => {
+                io::Error::from(io::ErrorKind::WouldBlock)
+            }
+        })?;
+    ws.get_ref().set_read_timeout(Some(POLL))?;
+    let (tx, rx) = mpsc::channel();
+    let (lane, welcome, keyframe) = {
+        let mut hub = lock(hub);
+        let (lane, welcome) = match spectating {
+            true => {
+                hub.spectators.push(tx);
+                (SPECTATOR, hub.game.watch())
+            }
+            false => {
+                let (lane, welcome) = hub.game.join();
+                hub.outboxes.insert(lane, tx);
+                (lane, welcome)
+            }
+        };
+        (lane, welcome, hub.game.keyframe())
+    };
+    let result = play(&mut ws, lane, &rx, hub, [welcome, keyframe]);
+    let mut hub = lock(hub);
+    hub.outboxes.remove(&lane);
+    if let Some(left) = hub.game.leave(lane) {
+        hub.broadcast(&left);
+    }
+    result
+}
+
+fn play(
+    ws: &mut tungstenite::WebSocket<TcpStream>,
+    lane: u32,
+    rx: &mpsc::Receiver<Vec<u8>>,
+    hub: &Mutex<Hub>,
+    first: [ServerMessage; 2],
+) -> io::Result<()> {
+    for message in first {
+        ws.send(Message::binary(message.encode()))
+            .map_err(ws_error)?;
+    }
+    loop {
+        while let Ok(message) = rx.try_recv() {
+            ws.send(Message::binary(message)).map_err(ws_error)?;
+        }
+        let reply = match ws.read() {
+            Ok(Message::Binary(message)) => {
+                match ClientMessage::decode(&message) {
+                    Ok(ClientMessage::Input { seq, action }) => {
+                        lock(hub).game.input(lane, seq, action);
+                        None
+                    }
+                    Err(e) => Some(ServerMessage::Error(e)),
+                }
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => None,
+            Err(WsError::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                None
+            }
+            Err(WsError::ConnectionClosed | WsError::AlreadyClosed) => {
+                return Ok(());
+            }
+            Err(e) => return Err(ws_error(e)),
+        };
+        if let Some(reply) = reply {
+            ws.send(Message::binary(reply.encode())).map_err(ws_error)?;
+        }
+    }
+}
+
+// A thread steps the match every `tick`; each connection gets a thread.
+pub fn serve(listener: TcpListener, game: Match, tick: Duration) {
+    let hub = Arc::new(Mutex::new(Hub::new(game)));
+    let ticker = Arc::clone(&hub);
+    thread::spawn(move || {
+        loop {
+            thread::sleep(tick);
+            lock(&ticker).tick();
+        }
+    });
+    for stream in listener.incoming().flatten() {
+        let hub = Arc::clone(&hub);
+        thread::spawn(move || handle_connection(stream, &hub));
+    }
+}
+
+// A lane as a client sees it, rebuilt from keyframes and deltas
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LaneView {
+    pub acked: u32,
+    pub score: u64,
+    pub crashed: bool,
+    pub player: u16,
+    pub cols: u16,
+    // TunnelCellType::code()s, row-major, front row first
+    pub cells: Vec<u8>,
+}
+
+impl LaneView {
+    fn apply(&mut self, state: LaneState, keyframe: bool) {
+        let scrolls = !keyframe && !self.crashed;
+        self.acked = state.acked;
+        self.score = state.score;
+        self.crashed = state.crashed;
+        self.player = state.player;
+        self.cols = state.cols;
+        if keyframe {
+            self.cells = state.cells;
+            return;
+        }
+        let cols = usize::from(state.cols);
+        // a delta for a view that missed its keyframe waits for the next
+        if !scrolls || cols == 0 || self.cells.len() < cols {
+            return;
+        }
+        let player = TunnelCellType::Player.code();
+        self.cells.drain(..cols);
+        self.cells.extend_from_slice(&state.cells);
+        for c in self.cells.iter_mut().take(cols) {
+            if *c == player {
+                *c = TunnelCellType::Floor.code();
+            }
+        }
+        if let Some(c) = self.cells.get_mut(usize::from(state.player)) {
+            *c = player;
+        }
+    }
+
+    pub fn rows(&self) -> u16 {
+        let rows = self.cells.len() / usize::from(self.cols.max(1));
+        u16::try_from(rows).unwrap_or(u16::MAX)
+    }
+
+    fn cell(&self, row: u16, col: u16) -> Option<TunnelCellType> {
+        if col >= self.cols {
+            return None;
+        }
+        let i = usize::from(row) * usize::from(self.cols) + usize::from(col);
+        TunnelCellType::from_code(*self.cells.get(i)?)
+    }
+
+    // like render::draw_frame, at 1/`scale` the size each way: a block of
+    // cells shows the player if it's there, a wall if it's all wall and
+    // floor otherwise, so a miniature keeps every way through
+    pub fn draw<R: Renderer<u16>>(
+        &self,
+        r: &mut R,
+        scale: u16,
+    ) -> Result<(), R::Error> {
+        let scale = scale.max(1);
+        r.begin_frame()?;
+        for row in 0..self.rows().div_ceil(scale) {
+            for col in 0..self.cols.div_ceil(scale) {
+                let block = (0..scale).flat_map(|dr| {
+                    (0..scale).filter_map(move |dc| {
+                        self.cell(row * scale + dr, col * scale + dc)
+                    })
+                });
+                let mut cell = TunnelCellType::Wall;
+                for c in block {
+                    match c {
+                        TunnelCellType::Player => {
+                            cell = c;
+                            break;
+                        }
+                        TunnelCellType::Wall => {}
+                        // too small to tell apart from the walls
+                        TunnelCellType::Pillar if scale > 1 => {}
+                        _ if scale > 1 => cell = TunnelCellType::Floor,
+                        _ => cell = c,
+                    }
+                }
+                r.draw_cell(row, col, cell, RowInfo::default())?;
+            }
+        }
+        r.draw_hud(self.score)?;
+        r.end_frame()
+    }
+}
+
+// One player's connection to a match: the lane it joined, and every lane
+// as of the last broadcast read
+pub struct Client {
+    ws: WebSocket<MaybeTlsStream<TcpStream>>,
+    pub lane: u32,
+    pub rows: u16,
+    pub cols: u16,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    seq: u32,
+    lanes: BTreeMap<u32, LaneView>,
+}
+
+fn protocol_error(e: ProtocolError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("server: {e}"))
+}
+
+impl Client {
+    // joins the match at `addr` (host:port), waiting for its welcome
+    pub fn connect(addr: &str) -> io::Result<Client> {
+        Client::open(format!("ws://{addr}"))
+    }
+
+    // watches the match at `addr`, with no lane of its own
+    pub fn spectate(addr: &str) -> io::Result<Client> {
+        Client::open(format!("ws://{addr}/spectate"))
+    }
+
+    fn open(url: String) -> io::Result<Client> {
+        let (mut ws, _) = tungstenite::connect(url).map_err(ws_error)?;
+        loop {
+            let Message::Binary(message) = ws.read().map_err(ws_error)? else {
+                continue;
+            };
+            match ServerMessage::decode(&message).map_err(protocol_error)? {
+                ServerMessage::Welcome {
+                    lane,
+                    rows,
+                    cols,
+                    seed,
+                    difficulty,
+                } => {
+                    if let MaybeTlsStream::Plain(stream) = ws.get_ref() {
+                        stream.set_read_timeout(Some(POLL))?;
+                    }
+                    return Ok(Client {
+                        ws,
+                        lane,
+                        rows,
+                        cols,
+                        seed,
+                        difficulty: Difficulty::ALL
+                            .get(usize::from(difficulty))
+                            .copied()
+                            .unwrap_or_default(),
+                        seq: 0,
+                        lanes: BTreeMap::new(),
+                    });
+                }
+                ServerMessage::Error(e) => return Err(protocol_error(e)),
+                _ => {}
+            }
+        }
+    }
+
+    // the move goes to the match at once; the lanes show it once applied
+    pub fn send(&mut self, action: Action) -> io::Result<()> {
+        self.seq = self.seq.wrapping_add(1);
+        let input = ClientMessage::Input {
+            seq: self.seq,
+            action,
+        };
+        self.ws
+            .send(Message::binary(input.encode()))
+            .map_err(ws_error)
+    }
+
+    // applies every broadcast that has come in, waiting up to POLL for one
+    pub fn poll(&mut self) -> io::Result<()> {
+        loop {
+            let message = match self.ws.read() {
+                Ok(Message::Binary(message)) => message,
+                Ok(_) => continue,
+                Err(WsError::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(());
+                }
+                Err(e) => return Err(ws_error(e)),
+            };
+            let (lanes, keyframe) = match ServerMessage::decode(&message)
+                .map_err(protocol_error)?
+            {
+                ServerMessage::Keyframe { lanes, .. } => (lanes, true),
+                ServerMessage::Delta { lanes, .. } => (lanes, false),
+                ServerMessage::Left { lane } => {
+                    self.lanes.remove(&lane);
+                    continue;
+                }
+                ServerMessage::Error(e) => return Err(protocol_error(e)),
+                ServerMessage::Welcome { .. } => continue,
+            };
+            for state in lanes {
+                let view = self.lanes.entry(state.lane).or_default();
+                view.apply(state, keyframe);
+            }
+        }
+    }
+
+    pub fn lanes(&self) -> &BTreeMap<u32, LaneView> {
+        &self.lanes
+    }
+
+    // this client's own lane, once a keyframe has shown it; a spectator's
+    // is always None
+    pub fn view(&self) -> Option<&LaneView> {
+        self.lanes.get(&self.lane)
+    }
+
+    // leaves the match; its lane goes with it
+    pub fn close(mut self) -> io::Result<()> {
+        match self.ws.close(None) {
+            Ok(()) | Err(WsError::ConnectionClosed) => Ok(()),
+            Err(e) => Err(ws_error(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let mut m = Match::new(8, 6, 4, Difficulty::Hard);
+        let (_, welcome) = m.join();
+        m.join();
+        let delta = m.tick();
+        for message in [
+            welcome,
+            m.keyframe(),
+            delta,
+            ServerMessage::Left { lane: 1 },
+        ] {
+            assert_eq!(ServerMessage::decode(&message.encode()), Ok(message));
+        }
+        let input = ClientMessage::Input {
+            seq: 7,
+            action: Action::Right,
+        };
+        assert_eq!(ClientMessage::decode(&input.encode()), Ok(input));
+        assert_eq!(
+            ClientMessage::decode(&[0x01, 0, 0, 0, 1, 5]),
+            Err(ProtocolError::BadArgument)
+        );
+        assert!(
+            ServerMessage::decode(&[0x83, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn lanes_share_walls_and_drop_stale_inputs() {
+        let mut m = Match::new(10, 9, 2, Difficulty::Normal);
+        let (a, _) = m.join();
+        let (b, _) = m.join();
+        assert!(m.input(a, 2, Action::Left));
+        assert!(!m.input(a, 1, Action::Left), "older than the last");
+        assert!(!m.input(a, 2, Action::Left), "repeated");
+        assert!(!m.input(9, 1, Action::Left), "no such lane");
+        let ServerMessage::Delta { tick: 1, lanes } = m.tick() else {
+            panic!("expected a delta");
+        };
+        assert_eq!((lanes[0].acked, lanes[1].acked), (2, 0));
+        assert_eq!(lanes[0].player + 1, lanes[1].player);
+        // same seed, same walls: the rows that scrolled in agree
+        assert_eq!(lanes[0].cells, lanes[1].cells);
+        assert_eq!(lanes[0].cells.len(), 9);
+        for _ in 1..KEYFRAME_EVERY - 1 {
+            m.tick();
+        }
+        assert!(matches!(m.tick(), ServerMessage::Keyframe { .. }));
+        assert_eq!(m.leave(b), Some(ServerMessage::Left { lane: b }));
+        assert_eq!(m.players(), 1);
+    }
+
+    #[test]
+    fn deltas_rebuild_the_screen() {
+        let mut m = Match::new(10, 9, 5, Difficulty::Normal);
+        let (a, _) = m.join();
+        let ServerMessage::Keyframe { lanes, .. } = m.keyframe() else {
+            unreachable!()
+        };
+        let mut cells = lanes[0].cells.clone();
+        let player_code = crate::TunnelCellType::Player.code();
+        let floor_code = crate::TunnelCellType::Floor.code();
+        for seq in 1..10 {
+            m.input(a, seq, Action::Right);
+            let ServerMessage::Delta { lanes, .. } = m.tick() else {
+                unreachable!()
+            };
+            let l = &lanes[0];
+            if l.crashed {
+                break;
+            }
+            cells.drain(..usize::from(l.cols));
+            cells.extend_from_slice(&l.cells);
+            for c in cells.iter_mut().take(usize::from(l.cols)) {
+                if *c == player_code {
+                    *c = floor_code;
+                }
+            }
+            cells[usize::from(l.player)] = player_code;
+            let ServerMessage::Keyframe { lanes, .. } = m.keyframe() else {
+                unreachable!()
+            };
+            assert_eq!(cells, lanes[0].cells);
+        }
+    }
+
+    #[test]
+    fn websocket_clients_join_play_and_leave() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let game = Match::new(10, 9, 1, Difficulty::Normal);
+        thread::spawn(move || serve(listener, game, Duration::from_millis(5)));
+
+        let url = format!("ws://{addr}");
+        let (mut a, _) = tungstenite::connect(&url).unwrap();
+        let next = |ws: &mut tungstenite::WebSocket<_>| loop {
+            if let Message::Binary(m) = ws.read().unwrap() {
+                return ServerMessage::decode(&m).unwrap();
+            }
+        };
+        let ServerMessage::Welcome { lane, seed: 1, .. } = next(&mut a) else {
+            panic!("expected a welcome");
+        };
+        assert!(matches!(next(&mut a), ServerMessage::Keyframe { .. }));
+        let (mut b, _) = tungstenite::connect(&url).unwrap();
+        let input = ClientMessage::Input {
+            seq: 1,
+            action: Action::Left,
+        };
+        a.send(Message::binary(input.encode())).unwrap();
+        // wait for the match to report the input applied
+        loop {
+            if let ServerMessage::Delta { lanes, .. } = next(&mut a)
+                && lanes.iter().any(|l| l.lane == lane && l.acked == 1)
+            {
+                break;
+            }
+        }
+        a.send(Message::binary(vec![0x09])).unwrap();
+        loop {
+            if let ServerMessage::Error(e) = next(&mut a) {
+                assert_eq!(e, ProtocolError::Malformed);
+                break;
+            }
+        }
+        a.close(None).unwrap();
+        loop {
+            if next(&mut b) == (ServerMessage::Left { lane }) {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn views_follow_the_match() {
+        let mut m = Match::new(10, 9, 5, Difficulty::Normal);
+        let (a, _) = m.join();
+        let ServerMessage::Keyframe { lanes, .. } = m.keyframe() else {
+            unreachable!()
+        };
+        let mut view = LaneView::default();
+        view.apply(lanes[0].clone(), true);
+        assert_eq!(view.cols, 9);
+        for seq in 1..40 {
+            m.input(a, seq, Action::Left);
+            let ServerMessage::Delta { lanes, .. } = m.tick() else {
+                unreachable!()
+            };
+            view.apply(lanes[0].clone(), false);
+            let ServerMessage::Keyframe { lanes, .. } = m.keyframe() else {
+                unreachable!()
+            };
+            assert_eq!(view.cells, lanes[0].cells);
+            assert_eq!(view.acked, seq.min(lanes[0].acked));
+        }
+        assert!(view.crashed, "steering into the left wall crashes");
+    }
+
+    #[test]
+    fn clients_see_each_other() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let game = Match::new(10, 9, 3, Difficulty::Normal);
+        thread::spawn(move || serve(listener, game, Duration::from_millis(5)));
+
+        let mut a = Client::connect(&addr).unwrap();
+        let mut b = Client::connect(&addr).unwrap();
+        assert_eq!((a.rows, a.cols, a.seed), (10, 9, 3));
+        assert_ne!(a.lane, b.lane);
+        a.send(Action::Left).unwrap();
+        while a.view().is_none_or(|v| v.acked < 1) {
+            a.poll().unwrap();
+        }
+        while !b.lanes().contains_key(&a.lane) {
+            b.poll().unwrap();
+        }
+        assert_eq!(b.lanes()[&a.lane].cols, 9);
+        let gone = a.lane;
+        a.close().unwrap();
+        while b.lanes().contains_key(&gone) {
+            b.poll().unwrap();
+        }
+    }
+
+    #[test]
+    fn spectators_watch_without_a_lane() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let game = Match::new(10, 9, 3, Difficulty::Normal);
+        thread::spawn(move || serve(listener, game, Duration::from_millis(5)));
+
+        let mut s = Client::spectate(&addr).unwrap();
+        assert_eq!((s.lane, s.rows, s.seed), (SPECTATOR, 10, 3));
+        let a = Client::connect(&addr).unwrap();
+        while !s.lanes().contains_key(&a.lane) {
+            s.poll().unwrap();
+        }
+        // its inputs go nowhere, and nothing breaks
+        s.send(Action::Left).unwrap();
+        s.poll().unwrap();
+        assert_eq!(s.lanes().len(), 1);
+        assert!(s.view().is_none());
+    }
+}
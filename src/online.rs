@@ -0,0 +1,195 @@
+// Online leaderboards, for players who opt in. A score goes up as JSON
+// along with its replay, so the server can play it back and check it (see
+// replay::Replay::score, or `tunnel --verify`), and a table comes back in
+// the high-score file's format (see scores). Under the leaderboard's URL:
+//
+//   POST scores  {"code":..,"seed":..,"score":..,"initials":..,
+//                 "replay_hash":..,"replay":..}
+//   GET  scores?difficulty=normal&rows=23&cols=80
+//
+// with "daily":"2026-10-16" in the POST, and &daily=2026-10-16 on the GET,
+// for the daily challenge's table, and likewise "mode":"time-attack" and
+// &mode=time-attack for a mode other than endless.
+//
+// Requests give up after TIMEOUT, so a slow server can't hold up a game.
+
+use crate::mode::Mode;
+use crate::replay::Replay;
+use crate::scores::{Board, HighScores};
+use std::string::String;
+use std::time::Duration;
+use ureq::Agent;
+
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Leaderboard {
+    url: String,
+    agent: Agent,
+}
+
+// `s` as a JSON string
+fn quoted(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if u32::from(c) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", u32::from(c)));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn submission(
+    replay: &Replay,
+    score: u64,
+    initials: &str,
+    daily: Option<&str>,
+) -> String {
+    let mut extra = String::new();
+    if let Some(day) = daily {
+        extra.push_str(&format!(",\"daily\":{}", quoted(day)));
+    }
+    if replay.mode != Mode::Endless {
+        extra.push_str(&format!(",\"mode\":{}", quoted(replay.mode.name())));
+    }
+    format!(
+        "{{\"code\":{},\"seed\":{},\"score\":{score},\"initials\":{},\
+         \"replay_hash\":\"{:016x}\",\"replay\":{}{extra}}}",
+        quoted(&replay.run.to_string()),
+        replay.run.seed,
+        quoted(initials),
+        replay.hash(),
+        quoted(&replay.to_text())
+    )
+}
+
+impl Leaderboard {
+    // `url` is the leaderboard's root, with or without a trailing slash
+    pub fn new(url: &str) -> Leaderboard {
+        let agent = Agent::config_builder()
+            .timeout_global(Some(TIMEOUT))
+            .build()
+            .into();
+        Leaderboard {
+            url: format!("{}/", url.trim_end_matches('/')),
+            agent,
+        }
+    }
+
+    pub fn scores_url(&self, board: Board<'_>) -> String {
+        let config = board.config;
+        let mut url = format!(
+            "{}scores?difficulty={}&rows={}&cols={}",
+            self.url, config.difficulty, config.rows, config.cols
+        );
+        if let Some(day) = board.daily {
+            url.push_str(&format!("&daily={day}"));
+        }
+        if board.mode != Mode::Endless {
+            url.push_str(&format!("&mode={}", board.mode));
+        }
+        url
+    }
+
+    pub fn submit(
+        &self,
+        replay: &Replay,
+        score: u64,
+        initials: &str,
+        daily: Option<&str>,
+    ) -> Result<(), ureq::Error> {
+        self.agent
+            .post(format!("{}scores", self.url))
+            .header("Content-Type", "application/json")
+            .send(submission(replay, score, initials, daily))?;
+        Ok(())
+    }
+
+    // the server's `board`; lines it gets wrong are left out
+    pub fn scores(&self, board: Board<'_>) -> Result<HighScores, ureq::Error> {
+        let text = self
+            .agent
+            .get(self.scores_url(board))
+            .call()?
+            .body_mut()
+            .read_to_string()?;
+        Ok(HighScores::parse(&text))
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::runcode::{RunCode, RunConfig};
+    use crate::sim::Action;
+
+    #[test]
+    fn submission_is_json_with_the_replay() {
+        let run = RunCode {
+            seed: 5,
+            config: RunConfig {
+                rows: 23,
+                cols: 80,
+                difficulty: Difficulty::Hard,
+            },
+        };
+        let mut replay = Replay::new(run, Mode::Endless);
+        replay.steps.push(vec![Action::Left]);
+        let json = submission(&replay, 1, "A\"B", None);
+        assert!(json.starts_with(&format!("{{\"code\":\"{run}\",\"seed\":5,")));
+        assert!(json.contains("\"score\":1,\"initials\":\"A\\\"B\","));
+        assert!(
+            json.contains(&format!(
+                "\"replay_hash\":\"{:016x}\"",
+                replay.hash()
+            ))
+        );
+        assert!(json.ends_with(&format!("\"replay\":\"{run}\\n1\\n\"}}")));
+        let daily = submission(&replay, 1, "AB", Some("2026-10-16"));
+        assert!(daily.ends_with("\\n\",\"daily\":\"2026-10-16\"}"));
+        replay.mode = Mode::TimeAttack;
+        let timed = submission(&replay, 1, "AB", None);
+        assert!(timed.ends_with("\\n\",\"mode\":\"time-attack\"}"));
+    }
+
+    #[test]
+    fn urls_sit_under_the_root() {
+        let board = Leaderboard::new("https://example.com/tunnel/");
+        let config = RunConfig {
+            rows: 23,
+            cols: 80,
+            difficulty: Difficulty::Normal,
+        };
+        assert_eq!(
+            board.scores_url(config.into()),
+            "https://example.com/tunnel/scores?difficulty=normal&rows=23&cols=80"
+        );
+        let daily = Board {
+            config,
+            daily: Some("2026-10-16"),
+            mode: Mode::Endless,
+        };
+        assert!(
+            board
+                .scores_url(daily)
+                .ends_with("&cols=80&daily=2026-10-16")
+        );
+        let timed = Board {
+            mode: Mode::TimeAttack,
+            ..config.into()
+        };
+        assert!(
+            board
+                .scores_url(timed)
+                .ends_with("&cols=80&mode=time-attack")
+        );
+    }
+}
@@ -0,0 +1,276 @@
+// Compact text notation for hand-authored tunnel sections. A pattern is a
+// sequence of run tokens (`L5`, `R3`, `K10`, `S2`, `M`: move left wall, move
+// right wall, keep walls, split or merge the corridor around a pillar, with
+// an optional repeat count) and per-row arrows (`>` pushes the corridor
+// right, `<` pulls it left, `=` keeps it in place, `{` and `}` steer the
+// pillar between two branches left and right). Whitespace is ignored, so
+// `L5 R3 K10` and `>>>>>  <<< ==========` are equivalent.
+
+use crate::{TunnelBuilder, TunnelBuilderChoice, TunnelIndex, two};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+use core::str::FromStr;
+
+#[derive(Debug, PartialEq)]
+pub enum PatternError {
+    Empty,
+    UnexpectedChar { offset: usize, found: char },
+    InvalidCount { offset: usize },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Empty => write!(f, "pattern contains no steps"),
+            PatternError::UnexpectedChar { offset, found } => {
+                write!(f, "unexpected {found:?} at offset {offset}")
+            }
+            PatternError::InvalidCount { offset } => {
+                write!(f, "invalid repeat count at offset {offset}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for PatternError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternBuilder {
+    runs: Vec<(TunnelBuilderChoice, usize)>,
+    run: usize,
+    taken: usize,
+}
+
+impl PatternBuilder {
+    pub fn parse(pattern: &str) -> Result<PatternBuilder, PatternError> {
+        let mut runs = Vec::new();
+        let mut chars = pattern.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            let choice = match c {
+                c if c.is_whitespace() => continue,
+                '>' => {
+                    runs.push((TunnelBuilderChoice::MoveLeftWall, 1));
+                    continue;
+                }
+                '<' => {
+                    runs.push((TunnelBuilderChoice::MoveRightWall, 1));
+                    continue;
+                }
+                '=' => {
+                    runs.push((TunnelBuilderChoice::KeepWalls, 1));
+                    continue;
+                }
+                '{' => {
+                    runs.push((TunnelBuilderChoice::SteerPillarLeft, 1));
+                    continue;
+                }
+                '}' => {
+                    runs.push((TunnelBuilderChoice::SteerPillarRight, 1));
+                    continue;
+                }
+                'L' | 'l' => TunnelBuilderChoice::MoveLeftWall,
+                'R' | 'r' => TunnelBuilderChoice::MoveRightWall,
+                'K' | 'k' => TunnelBuilderChoice::KeepWalls,
+                'S' | 's' => TunnelBuilderChoice::SplitCorridor,
+                'M' | 'm' => TunnelBuilderChoice::MergeCorridor,
+                found => {
+                    return Err(PatternError::UnexpectedChar { offset, found });
+                }
+            };
+            let mut digits = String::new();
+            while let Some((_, d)) = chars.next_if(|(_, d)| d.is_ascii_digit())
+            {
+                digits.push(d);
+            }
+            let count = if digits.is_empty() {
+                1
+            } else {
+                match digits.parse() {
+                    Ok(0) | Err(_) => {
+                        return Err(PatternError::InvalidCount {
+                            offset: offset + c.len_utf8(),
+                        });
+                    }
+                    Ok(n) => n,
+                }
+            };
+            runs.push((choice, count));
+        }
+        if runs.is_empty() {
+            return Err(PatternError::Empty);
+        }
+        Ok(PatternBuilder {
+            runs,
+            run: 0,
+            taken: 0,
+        })
+    }
+
+    pub fn from_choices(
+        choices: impl IntoIterator<Item = TunnelBuilderChoice>,
+    ) -> Result<PatternBuilder, PatternError> {
+        let mut runs: Vec<(TunnelBuilderChoice, usize)> = Vec::new();
+        for choice in choices {
+            match runs.last_mut() {
+                Some((last, count)) if *last == choice => *count += 1,
+                _ => runs.push((choice, 1)),
+            }
+        }
+        if runs.is_empty() {
+            return Err(PatternError::Empty);
+        }
+        Ok(PatternBuilder {
+            runs,
+            run: 0,
+            taken: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.runs.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+}
+
+impl FromStr for PatternBuilder {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PatternBuilder::parse(s)
+    }
+}
+
+impl TunnelBuilder for PatternBuilder {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        max / two()
+    }
+
+    // loop back to the start once the pattern runs out, so a short section
+    // can drive a tunnel of any length
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        let (choice, count) = self.runs[self.run];
+        self.taken += 1;
+        if self.taken >= count {
+            self.taken = 0;
+            self.run = (self.run + 1) % self.runs.len();
+        }
+        choice
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use TunnelBuilderChoice::{
+        KeepWalls, MergeCorridor, MoveLeftWall, MoveRightWall, SplitCorridor,
+        SteerPillarLeft, SteerPillarRight,
+    };
+
+    fn take(b: &mut PatternBuilder, n: usize) -> Vec<TunnelBuilderChoice> {
+        (0..n).map(|_| b.choose_step()).collect()
+    }
+
+    #[test]
+    fn run_tokens_expand_to_repeated_choices() {
+        let mut b = PatternBuilder::parse("L2 R1 K3").unwrap();
+        assert_eq!(b.len(), 6);
+        let expected = vec![
+            MoveLeftWall,
+            MoveLeftWall,
+            MoveRightWall,
+            KeepWalls,
+            KeepWalls,
+            KeepWalls,
+        ];
+        assert_eq!(expected, take(&mut b, 6));
+    }
+
+    #[test]
+    fn arrows_and_runs_are_equivalent() {
+        let mut runs: PatternBuilder = "L5 R3 K10".parse().unwrap();
+        let mut arrows: PatternBuilder =
+            ">>>>>  <<< ==========".parse().unwrap();
+        assert_eq!(take(&mut runs, 40), take(&mut arrows, 40));
+    }
+
+    #[test]
+    fn split_steer_and_merge_tokens() {
+        let mut b = PatternBuilder::parse("S2 {} m").unwrap();
+        let expected = vec![
+            SplitCorridor,
+            SplitCorridor,
+            SteerPillarLeft,
+            SteerPillarRight,
+            MergeCorridor,
+        ];
+        assert_eq!(expected, take(&mut b, 5));
+    }
+
+    #[test]
+    fn lowercase_and_missing_counts_are_accepted() {
+        let mut b = PatternBuilder::parse("l r2k").unwrap();
+        let expected = vec![MoveLeftWall, MoveRightWall, MoveRightWall];
+        assert_eq!(expected, take(&mut b, 3));
+        assert_eq!(KeepWalls, b.choose_step());
+    }
+
+    #[test]
+    fn pattern_loops_when_exhausted() {
+        let mut b = PatternBuilder::parse("><").unwrap();
+        let expected = vec![MoveLeftWall, MoveRightWall, MoveLeftWall];
+        assert_eq!(expected, take(&mut b, 3));
+    }
+
+    #[test]
+    fn from_choices_merges_adjacent_runs() {
+        let choices = [MoveLeftWall, MoveLeftWall, KeepWalls];
+        let b = PatternBuilder::from_choices(choices).unwrap();
+        assert_eq!(b, PatternBuilder::parse("L2 K").unwrap());
+        assert_eq!(PatternBuilder::from_choices([]), Err(PatternError::Empty));
+    }
+
+    #[test]
+    fn player_starts_in_the_middle() {
+        let mut b = PatternBuilder::parse("=").unwrap();
+        assert_eq!(b.choose_player_start::<u8>(9), 4);
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert_eq!(PatternBuilder::parse(""), Err(PatternError::Empty));
+        assert_eq!(PatternBuilder::parse(" \n\t"), Err(PatternError::Empty));
+        assert_eq!(
+            PatternBuilder::parse("L5 X2"),
+            Err(PatternError::UnexpectedChar {
+                offset: 3,
+                found: 'X'
+            })
+        );
+        assert_eq!(
+            PatternBuilder::parse("K0"),
+            Err(PatternError::InvalidCount { offset: 1 })
+        );
+        assert_eq!(
+            PatternBuilder::parse("R99999999999999999999999"),
+            Err(PatternError::InvalidCount { offset: 1 })
+        );
+        assert_eq!(
+            PatternBuilder::parse("<3").unwrap_err().to_string(),
+            "unexpected '3' at offset 1"
+        );
+    }
+
+    #[test]
+    fn keep_walls_leaves_left_wall_in_place() {
+        let mut b = PatternBuilder::parse("K").unwrap();
+        let mut t = crate::Tunnel::<u8>::new(&mut b, 5, 5);
+        for _ in 0..8 {
+            t.step(&mut b);
+            assert!(t.walls.iter().all(|w| w.left_wall == 0));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+// Bevy glue: TunnelPlugin steps a TunnelState resource on a fixed timer and
+// mirrors the tunnel's cells onto TunnelCell entities, one per screen cell,
+// so a game only has to attach sprites (or whatever) to those entities.
+// The game inserts TunnelState itself and steers through TunnelInput.
+
+use crate::{Direction, Tunnel, TunnelBuilder, TunnelCellType};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_time::{Time, Timer, TimerMode};
+use core::marker::PhantomData;
+use core::time::Duration;
+use std::vec::Vec;
+
+#[derive(Resource)]
+pub struct TunnelState<B> {
+    pub tunnel: Tunnel<u16>,
+    pub builder: B,
+    pub crashed: bool,
+    // one entity per cell, row-major, spawned on the first sync
+    cells: Vec<Entity>,
+    synced: Option<u64>,
+}
+
+impl<B: TunnelBuilder> TunnelState<B> {
+    pub fn new(tunnel: Tunnel<u16>, builder: B) -> TunnelState<B> {
+        TunnelState {
+            tunnel,
+            builder,
+            crashed: false,
+            cells: Vec::new(),
+            synced: None,
+        }
+    }
+}
+
+// the move to make on the next step; taken (and cleared) by the step
+#[derive(Resource, Default)]
+pub struct TunnelInput(pub Option<Direction>);
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct TunnelCell {
+    pub row: u16,
+    pub col: u16,
+    pub cell_type: TunnelCellType,
+}
+
+#[derive(Resource)]
+struct StepTimer(Timer);
+
+pub struct TunnelPlugin<B> {
+    pub step: Duration,
+    builder: PhantomData<fn() -> B>,
+}
+
+impl<B> TunnelPlugin<B> {
+    pub fn new(step: Duration) -> TunnelPlugin<B> {
+        TunnelPlugin {
+            step,
+            builder: PhantomData,
+        }
+    }
+}
+
+impl<B: TunnelBuilder + Send + Sync + 'static> Plugin for TunnelPlugin<B> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(StepTimer(Timer::new(
+            self.step,
+            TimerMode::Repeating,
+        )))
+        .init_resource::<TunnelInput>()
+        .add_systems(
+            Update,
+            (step_tunnel::<B>, sync_cells::<B>)
+                .chain()
+                .run_if(resource_exists::<TunnelState<B>>),
+        );
+    }
+}
+
+fn step_tunnel<B: TunnelBuilder + Send + Sync + 'static>(
+    time: Res<Time>,
+    mut timer: ResMut<StepTimer>,
+    mut input: ResMut<TunnelInput>,
+    mut state: ResMut<TunnelState<B>>,
+) {
+    timer.0.tick(time.delta());
+    for _ in 0..timer.0.times_finished_this_tick() {
+        if state.crashed {
+            return;
+        }
+        let state = &mut *state;
+        match input.0.take() {
+            Some(Direction::Left) => state.tunnel.move_player_left(),
+            Some(Direction::Right) => state.tunnel.move_player_right(),
+            None => {}
+        }
+        state.tunnel.step(&mut state.builder);
+        state.crashed = state.tunnel.is_collision();
+    }
+}
+
+fn sync_cells<B: TunnelBuilder + Send + Sync + 'static>(
+    mut commands: Commands,
+    mut state: ResMut<TunnelState<B>>,
+    mut cells: Query<&mut TunnelCell>,
+) {
+    let generation = state.tunnel.generation();
+    if state.synced == Some(generation) {
+        return;
+    }
+    let state = &mut *state;
+    for (i, (row, col, cell_type)) in state.tunnel.iter().enumerate() {
+        let cell = TunnelCell {
+            row,
+            col,
+            cell_type,
+        };
+        match state.cells.get(i) {
+            Some(&entity) => {
+                if let Ok(mut c) = cells.get_mut(entity) {
+                    // only touch changed cells, so Changed<TunnelCell> is
+                    // a useful filter for whatever draws them
+                    c.set_if_neq(cell);
+                }
+            }
+            None => state.cells.push(commands.spawn(cell).id()),
+        }
+    }
+    state.synced = Some(generation);
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+    use bevy_time::{TimePlugin, TimeUpdateStrategy};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            TimePlugin,
+            TunnelPlugin::<PatternBuilder>::new(Duration::from_millis(100)),
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Duration::from_millis(100),
+        ));
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::new(&mut b, 6, 7);
+        app.insert_resource(TunnelState::new(t, b));
+        app
+    }
+
+    #[test]
+    fn plugin_steps_on_the_timer_and_mirrors_cells() {
+        let mut app = app();
+        app.update();
+        app.update();
+        app.update();
+
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let mut expected = Tunnel::<u16>::new(&mut b, 6, 7);
+        let world = app.world_mut();
+        let state = world.resource::<TunnelState<PatternBuilder>>();
+        // the first update only starts the clock
+        for _ in 0..2 {
+            expected.step(&mut b);
+        }
+        assert_eq!(state.tunnel.rows_generated, expected.rows_generated);
+        assert!(state.tunnel.iter().eq(expected.iter()));
+
+        let mut cells: Vec<_> =
+            world.query::<&TunnelCell>().iter(world).copied().collect();
+        cells.sort_by_key(|c| (c.row, c.col));
+        let cells = cells.into_iter().map(|c| (c.row, c.col, c.cell_type));
+        assert!(cells.eq(expected.iter()));
+    }
+
+    #[test]
+    fn input_moves_the_player_before_the_step() {
+        let mut app = app();
+        let start = app
+            .world()
+            .resource::<TunnelState<PatternBuilder>>()
+            .tunnel
+            .player();
+        app.insert_resource(TunnelInput(Some(Direction::Left)));
+        app.update();
+        app.update();
+        let world = app.world();
+        assert!(world.resource::<TunnelInput>().0.is_none());
+        let state = world.resource::<TunnelState<PatternBuilder>>();
+        assert_eq!(state.tunnel.player(), start - 1);
+    }
+}
@@ -0,0 +1,133 @@
+// termion frontend pieces, for POSIX systems where crossterm will not
+// build: a Renderer that writes frames to any terminal, and the keys that
+// steer the player. src/bin/tunnel-termion.rs puts them together into the
+// terminal game.
+
+use crate::ansi::Key;
+use crate::render::Renderer;
+use crate::{Direction, Floor, Hazard, RowInfo, TunnelCellType};
+use std::io::{self, Write};
+use termion::color::{self, Color};
+use termion::{clear, cursor, event};
+
+// the glyphs and colours of the crossterm frontend
+pub fn glyph(
+    cell_type: TunnelCellType,
+    info: RowInfo,
+) -> (char, &'static dyn Color) {
+    match cell_type {
+        TunnelCellType::Player => ('v', &color::LightGreen),
+        TunnelCellType::Floor => match (info.floor, info.wind) {
+            _ if info.hazard == Some(Hazard::Warning) => {
+                ('!', &color::LightRed)
+            }
+            (Floor::Ice, _) => ('-', &color::LightCyan),
+            (Floor::Sticky, _) => ('~', &color::Yellow),
+            (Floor::Normal, Some(wind)) => match wind.direction {
+                Direction::Left => ('<', &color::LightBlack),
+                Direction::Right => ('>', &color::LightBlack),
+            },
+            (Floor::Normal, None) if info.speed_zone => {
+                ('^', &color::LightBlack)
+            }
+            (Floor::Normal, None) => (' ', &color::Reset),
+        },
+        TunnelCellType::Wall => ('O', &color::Reset),
+        TunnelCellType::Fuel => ('+', &color::LightYellow),
+        TunnelCellType::Key => ('k', &color::LightYellow),
+        TunnelCellType::Door => ('#', &color::LightMagenta),
+        TunnelCellType::Hidden => ('.', &color::LightBlack),
+        TunnelCellType::Coin => ('*', &color::LightYellow),
+        TunnelCellType::Pillar => ('X', &color::Red),
+    }
+}
+
+pub struct TermionRenderer<W> {
+    pub out: W,
+    pub score_row: u16,
+}
+
+// termion counts rows and columns from one
+fn goto(row: u16, col: u16) -> cursor::Goto {
+    cursor::Goto(col.saturating_add(1), row.saturating_add(1))
+}
+
+impl<W: Write> Renderer<u16> for TermionRenderer<W> {
+    type Error = io::Error;
+
+    fn begin_frame(&mut self) -> io::Result<()> {
+        write!(self.out, "{}", clear::All)
+    }
+
+    fn draw_cell(
+        &mut self,
+        row: u16,
+        col: u16,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> io::Result<()> {
+        let (symbol, fg) = glyph(cell_type, info);
+        write!(self.out, "{}{}{symbol}", goto(row, col), color::Fg(fg))
+    }
+
+    fn draw_hud(&mut self, score: u64) -> io::Result<()> {
+        write!(
+            self.out,
+            "{}{}{score}{}",
+            goto(self.score_row, 0),
+            color::Fg(color::LightGreen),
+            color::Fg(color::Reset)
+        )
+    }
+
+    fn end_frame(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+// the keys of ansi::KeyParser, as termion reads them
+pub fn read_key(key: event::Key) -> Option<Key> {
+    match key {
+        event::Key::Left | event::Key::Char('a' | 'A' | 'h') => Some(Key::Left),
+        event::Key::Right | event::Key::Char('d' | 'D' | 'l') => {
+            Some(Key::Right)
+        }
+        event::Key::Char('q' | 'Q') | event::Key::Ctrl('c' | 'd') => {
+            Some(Key::Quit)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::Tunnel;
+    use crate::pattern::PatternBuilder;
+    use crate::render::draw_frame;
+    use std::vec::Vec;
+
+    #[test]
+    fn renderer_places_every_cell_and_the_score() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u16>::new(&mut b, 5, 4);
+        let mut r = TermionRenderer {
+            out: Vec::new(),
+            score_row: 4,
+        };
+        draw_frame(&mut r, &t, usize::MAX, 9).unwrap();
+        let out = std::string::String::from_utf8(r.out).unwrap();
+        assert!(out.starts_with("\x1b[2J\x1b[1;1H"));
+        assert_eq!(out.matches('H').count(), t.iter().count() + 1);
+        assert!(out.ends_with("\x1b[5;1H\x1b[38;5;10m9\x1b[39m"));
+    }
+
+    #[test]
+    fn keys_match_the_remote_terminal_game() {
+        assert_eq!(read_key(event::Key::Left), Some(Key::Left));
+        assert_eq!(read_key(event::Key::Char('l')), Some(Key::Right));
+        assert_eq!(read_key(event::Key::Ctrl('c')), Some(Key::Quit));
+        assert_eq!(read_key(event::Key::Up), None);
+    }
+}
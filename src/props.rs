@@ -0,0 +1,208 @@
+// proptest support: Arbitrary configs, builder choices and player inputs,
+// and check_game, which plays one arbitrary game and checks after every
+// step that the tunnel is still one a frontend can draw and play: walls
+// on screen, gaps no narrower than configured, the player inside.
+
+use crate::pattern::PatternBuilder;
+use crate::sim::Action;
+use crate::{
+    Attractor, FuelTank, Gravity, MaxSlope, RowStorage, Tunnel,
+    TunnelBuilderChoice, TunnelConfig, TunnelIndex,
+};
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use std::vec::Vec;
+
+impl Arbitrary for TunnelBuilderChoice {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<TunnelBuilderChoice>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        prop_oneof![
+            Just(TunnelBuilderChoice::MoveLeftWall),
+            Just(TunnelBuilderChoice::MoveRightWall),
+            Just(TunnelBuilderChoice::KeepWalls),
+            Just(TunnelBuilderChoice::SplitCorridor),
+            Just(TunnelBuilderChoice::MergeCorridor),
+            Just(TunnelBuilderChoice::SteerPillarLeft),
+            Just(TunnelBuilderChoice::SteerPillarRight),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Action {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Action>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        prop_oneof![
+            Just(Action::Stay),
+            Just(Action::Left),
+            Just(Action::Right),
+            Just(Action::DashLeft),
+            Just(Action::DashRight),
+        ]
+        .boxed()
+    }
+}
+
+// every knob but max_rows, which only turns a tall screen into an error
+impl Arbitrary for TunnelConfig {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<TunnelConfig>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        let max_slope = (0..4_usize, 0..8_usize)
+            .prop_map(|(shift, rows)| MaxSlope { shift, rows });
+        let toward =
+            prop_oneof![Just(Attractor::Center), Just(Attractor::LastWall)];
+        let gravity = (toward, 0..6_usize)
+            .prop_map(|(toward, every)| Gravity { toward, every });
+        let fuel = (0..20_usize, 0..10_usize)
+            .prop_map(|(capacity, pickup)| FuelTank { capacity, pickup });
+        (
+            option::of(max_slope),
+            0..20_usize,
+            0..12_usize,
+            0..20_usize,
+            option::of(gravity),
+            option::of(fuel),
+            proptest::sample::select(RowStorage::ALL.as_slice()),
+        )
+            .prop_map(
+                |(
+                    max_slope,
+                    grace_rows,
+                    min_gap,
+                    narrow_every,
+                    gravity,
+                    fuel,
+                    storage,
+                )| {
+                    TunnelConfig {
+                        max_slope,
+                        grace_rows,
+                        min_gap,
+                        narrow_every,
+                        gravity,
+                        fuel,
+                        storage,
+                        max_rows: None,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+// builder choices that cycle forever, as a builder
+pub fn choice_sequence() -> impl Strategy<Value = PatternBuilder> {
+    vec(any::<TunnelBuilderChoice>(), 1..64).prop_map(|choices| {
+        PatternBuilder::from_choices(choices)
+            .expect("at least one choice makes a pattern")
+    })
+}
+
+// one input per step
+pub fn input_script(max_steps: usize) -> impl Strategy<Value = Vec<Action>> {
+    vec(any::<Action>(), 0..max_steps)
+}
+
+#[derive(Clone, Debug)]
+pub struct Game {
+    pub rows: u16,
+    pub cols: u16,
+    pub config: TunnelConfig,
+    pub builder: PatternBuilder,
+    // the game ends when these run out or the player crashes
+    pub inputs: Vec<Action>,
+}
+
+impl Arbitrary for Game {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Game>;
+
+    // from the smallest screen with a floor column between two walls
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (
+            3..40_u16,
+            3..64_u16,
+            any::<TunnelConfig>(),
+            choice_sequence(),
+            input_script(400),
+        )
+            .prop_map(|(rows, cols, config, builder, inputs)| Game {
+                rows,
+                cols,
+                config,
+                builder,
+                inputs,
+            })
+            .boxed()
+    }
+}
+
+// the invariants check_game holds every step to, for other harnesses
+pub fn check_tunnel<T: TunnelIndex, const N: usize>(
+    t: &Tunnel<T, N>,
+) -> Result<(), TestCaseError> {
+    let cols = t.screen_width.to_usize().unwrap_or(0);
+    let player = t.player().to_usize().unwrap_or(usize::MAX);
+    prop_assert!(player < cols, "player {player} off a {cols}-wide screen");
+    prop_assert_eq!(t.iter().count(), t.buffered_rows() * cols);
+    // narrowing stops at min_gap, or never starts on a narrower screen
+    let min_gap = t.config.min_gap.max(1).min(cols.saturating_sub(2));
+    for (row, walls) in t.walls.iter().enumerate() {
+        let left = walls.left_wall.to_usize().unwrap_or(usize::MAX);
+        let gap = walls.gap_to_right_wall.to_usize().unwrap_or(usize::MAX);
+        prop_assert!(
+            left + gap + 1 < cols,
+            "row {row}: walls at {left} and {} on a {cols}-wide screen",
+            left + gap + 1
+        );
+        prop_assert!(gap >= min_gap, "row {row}: gap {gap} under {min_gap}");
+        if let Some((start, width)) = walls.pillar {
+            let start = start.to_usize().unwrap_or(usize::MAX);
+            let end = start + width.to_usize().unwrap_or(usize::MAX);
+            // a floor column on either side of it
+            prop_assert!(
+                left + 1 < start && end <= left + gap,
+                "row {row}: pillar {start}..{end} outside the floor"
+            );
+        }
+    }
+    Ok(())
+}
+
+// plays `game` to its end, checking the tunnel after every step
+pub fn check_game(game: &Game) -> Result<(), TestCaseError> {
+    let mut b = game.builder.clone();
+    let mut t =
+        Tunnel::with_config(&mut b, game.rows, game.cols, game.config.clone());
+    check_tunnel(&t)?;
+    for action in &game.inputs {
+        if t.is_collision() {
+            break;
+        }
+        action.apply(&mut t);
+        t.step(&mut b);
+        check_tunnel(&t)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn arbitrary_games_keep_their_invariants(game in any::<Game>()) {
+            check_game(&game)?;
+        }
+    }
+}
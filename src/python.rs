@@ -0,0 +1,215 @@
+// PyO3 bindings, importable as the `tunnel` module once built as an
+// extension: `cargo rustc --lib --no-default-features --features
+// python,pyo3/extension-module --crate-type cdylib`, then put
+// libtunnel.so on the Python path as tunnel.so. The loop mirrors
+// sim::Policy: pick an Action from an observation, act(), repeat until
+// crashed. Observations are bytes of TunnelCellType::code()s, row-major.
+
+use crate::difficulty::Difficulty;
+use crate::pattern::PatternBuilder;
+use crate::seeded::Seeded;
+use crate::sim::Action;
+use crate::{
+    Event, RowInfo, Tunnel, TunnelBuilder, TunnelBuilderChoice, TunnelIndex,
+};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::string::ToString;
+use std::vec::Vec;
+
+enum Builder {
+    Seeded(Seeded),
+    Pattern(PatternBuilder),
+}
+
+impl TunnelBuilder for Builder {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        match self {
+            Builder::Seeded(b) => b.choose_player_start(max),
+            Builder::Pattern(b) => b.choose_player_start(max),
+        }
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        match self {
+            Builder::Seeded(b) => b.choose_step(),
+            Builder::Pattern(b) => b.choose_step(),
+        }
+    }
+
+    fn choose_step_for<T: TunnelIndex, const N: usize>(
+        &mut self,
+        t: &Tunnel<T, N>,
+    ) -> TunnelBuilderChoice {
+        match self {
+            Builder::Seeded(b) => b.choose_step_for(t),
+            Builder::Pattern(b) => b.choose_step_for(t),
+        }
+    }
+
+    fn row_info(&mut self) -> RowInfo {
+        match self {
+            Builder::Seeded(b) => b.row_info(),
+            Builder::Pattern(b) => b.row_info(),
+        }
+    }
+
+    fn event(&mut self) -> Option<Event> {
+        match self {
+            Builder::Seeded(b) => b.event(),
+            Builder::Pattern(b) => b.event(),
+        }
+    }
+}
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pyclass(name = "Tunnel")]
+pub struct PyTunnel {
+    tunnel: Tunnel<u16>,
+    builder: Builder,
+}
+
+#[pymethods]
+impl PyTunnel {
+    // `pattern` (see PatternBuilder::parse) replaces the seeded builder
+    #[new]
+    #[pyo3(signature = (rows, cols, seed=0, difficulty="normal", pattern=None))]
+    pub fn new(
+        rows: u16,
+        cols: u16,
+        seed: u64,
+        difficulty: &str,
+        pattern: Option<&str>,
+    ) -> PyResult<PyTunnel> {
+        let difficulty: Difficulty = difficulty.parse().map_err(value_error)?;
+        let mut builder = match pattern {
+            Some(p) => Builder::Pattern(p.parse().map_err(value_error)?),
+            None => Builder::Seeded(Seeded::new(seed)),
+        };
+        let tunnel = Tunnel::try_with_config(
+            &mut builder,
+            rows,
+            cols,
+            difficulty.config(),
+        )
+        .map_err(value_error)?;
+        Ok(PyTunnel { tunnel, builder })
+    }
+
+    pub fn step(&mut self) {
+        self.tunnel.step(&mut self.builder);
+    }
+
+    pub fn move_left(&mut self) {
+        self.tunnel.move_player_left();
+    }
+
+    pub fn move_right(&mut self) {
+        self.tunnel.move_player_right();
+    }
+
+    // 0 stays, 1 moves left, 2 moves right; then steps and returns the new
+    // observation and whether the player crashed
+    pub fn act<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: u8,
+    ) -> PyResult<(Bound<'py, PyBytes>, bool)> {
+        self.apply(action)?;
+        Ok((self.observe(py, None), self.tunnel.is_collision()))
+    }
+
+    // rows more than `radius` ahead of the player come out Hidden
+    #[pyo3(signature = (radius=None))]
+    pub fn observe<'py>(
+        &self,
+        py: Python<'py>,
+        radius: Option<usize>,
+    ) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.cells(radius.unwrap_or(usize::MAX)))
+    }
+
+    #[getter]
+    pub fn rows(&self) -> usize {
+        self.tunnel.buffered_rows()
+    }
+
+    #[getter]
+    pub fn cols(&self) -> u16 {
+        self.tunnel.screen_width
+    }
+
+    #[getter]
+    pub fn player(&self) -> u16 {
+        self.tunnel.player()
+    }
+
+    #[getter]
+    pub fn crashed(&self) -> bool {
+        self.tunnel.is_collision()
+    }
+
+    #[getter]
+    pub fn generation(&self) -> u64 {
+        self.tunnel.generation()
+    }
+}
+
+impl PyTunnel {
+    fn apply(&mut self, action: u8) -> PyResult<()> {
+        Action::from_code(action)
+            .ok_or_else(|| value_error("action must be 0 to 4"))?
+            .apply(&mut self.tunnel);
+        self.step();
+        Ok(())
+    }
+
+    fn cells(&self, radius: usize) -> Vec<u8> {
+        self.tunnel
+            .iter_visible(radius)
+            .map(|(_, _, cell_type)| cell_type.code())
+            .collect()
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "tunnel")]
+fn tunnel_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTunnel>()
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_and_pattern_tunnels_step_and_observe() {
+        let mut t = PyTunnel::new(12, 9, 5, "hard", None).unwrap();
+        let mut b = Seeded::new(5);
+        let mut expected =
+            Tunnel::with_config(&mut b, 12, 9, Difficulty::Hard.config());
+        t.apply(1).unwrap();
+        expected.move_player_left();
+        expected.step(&mut b);
+        assert_eq!(t.player(), expected.player());
+        let codes = expected.iter().map(|(_, _, c)| c.code());
+        assert!(codes.eq(t.cells(usize::MAX)));
+        assert_eq!(t.cells(usize::MAX).len(), t.rows() * 9);
+
+        let p = PyTunnel::new(12, 9, 0, "normal", Some("L1 R1")).unwrap();
+        assert!(matches!(p.builder, Builder::Pattern(_)));
+    }
+
+    #[test]
+    fn bad_arguments_are_value_errors() {
+        assert!(PyTunnel::new(12, 9, 0, "easy", None).is_err());
+        assert!(PyTunnel::new(12, 9, 0, "normal", Some("Q")).is_err());
+        let mut t = PyTunnel::new(12, 9, 0, "normal", None).unwrap();
+        assert!(t.apply(5).is_err());
+    }
+}
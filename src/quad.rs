@@ -0,0 +1,223 @@
+// macroquad frontend pieces: a texture atlas with one square tile per kind
+// of cell (see Tile for the order), a Renderer that draws a frame with it,
+// and the keys that steer the player. examples/macroquad.rs puts them
+// together into a playable window.
+
+use crate::render::Renderer;
+use crate::{Direction, Floor, Hazard, RowInfo, TunnelCellType};
+use macroquad::prelude::*;
+
+// tiles left to right in an atlas image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tile {
+    Floor,
+    Wall,
+    Player,
+    Fuel,
+    Key,
+    Door,
+    Hidden,
+    Ice,
+    Sticky,
+    WindLeft,
+    WindRight,
+    SpeedZone,
+    Warning,
+    Coin,
+    Pillar,
+}
+
+impl Tile {
+    pub const COUNT: usize = 15;
+
+    pub fn for_cell(cell_type: TunnelCellType, info: RowInfo) -> Tile {
+        match cell_type {
+            TunnelCellType::Player => Tile::Player,
+            TunnelCellType::Wall => Tile::Wall,
+            TunnelCellType::Fuel => Tile::Fuel,
+            TunnelCellType::Key => Tile::Key,
+            TunnelCellType::Door => Tile::Door,
+            TunnelCellType::Hidden => Tile::Hidden,
+            TunnelCellType::Coin => Tile::Coin,
+            TunnelCellType::Pillar => Tile::Pillar,
+            TunnelCellType::Floor => match (info.floor, info.wind) {
+                _ if info.hazard == Some(Hazard::Warning) => Tile::Warning,
+                (Floor::Ice, _) => Tile::Ice,
+                (Floor::Sticky, _) => Tile::Sticky,
+                (Floor::Normal, Some(wind)) => match wind.direction {
+                    Direction::Left => Tile::WindLeft,
+                    Direction::Right => Tile::WindRight,
+                },
+                (Floor::Normal, None) if info.speed_zone => Tile::SpeedZone,
+                (Floor::Normal, None) => Tile::Floor,
+            },
+        }
+    }
+}
+
+pub struct Atlas {
+    pub texture: Texture2D,
+    // edge of one square tile, in texture pixels
+    pub tile: f32,
+}
+
+impl Atlas {
+    pub fn new(texture: Texture2D, tile: f32) -> Atlas {
+        texture.set_filter(FilterMode::Nearest);
+        Atlas { texture, tile }
+    }
+
+    // an atlas of flat colours, for prototyping before there is any art
+    pub fn from_colors(colors: [Color; Tile::COUNT]) -> Atlas {
+        Atlas::new(Texture2D::from_image(&color_image(colors)), 1.)
+    }
+
+    fn source(&self, tile: Tile) -> Rect {
+        Rect::new(tile as usize as f32 * self.tile, 0., self.tile, self.tile)
+    }
+}
+
+fn color_image(colors: [Color; Tile::COUNT]) -> Image {
+    let mut image = Image::gen_image_color(Tile::COUNT as u16, 1, BLANK);
+    for (x, color) in colors.into_iter().enumerate() {
+        image.set_pixel(x as u32, 0, color);
+    }
+    image
+}
+
+pub const DEFAULT_COLORS: [Color; Tile::COUNT] = [
+    BLACK, GRAY, GREEN, YELLOW, GOLD, MAGENTA, DARKGRAY, SKYBLUE, BROWN,
+    DARKBLUE, DARKBLUE, DARKPURPLE, RED, ORANGE, MAROON,
+];
+
+pub struct QuadRenderer {
+    pub atlas: Atlas,
+    // edge of one cell on screen, in pixels
+    pub cell: f32,
+    pub background: Color,
+    // see Renderer::scroll; the player waits for end_frame, so the rows
+    // sliding up pass under it
+    offset: f32,
+    player: Option<(u16, RowInfo)>,
+}
+
+impl QuadRenderer {
+    pub fn new(atlas: Atlas, cell: f32, background: Color) -> QuadRenderer {
+        QuadRenderer {
+            atlas,
+            cell,
+            background,
+            offset: 0.,
+            player: None,
+        }
+    }
+
+    fn draw_tile(&self, x: f32, y: f32, tile: Tile) {
+        let params = DrawTextureParams {
+            dest_size: Some(vec2(self.cell, self.cell)),
+            source: Some(self.atlas.source(tile)),
+            ..DrawTextureParams::default()
+        };
+        draw_texture_ex(&self.atlas.texture, x, y, WHITE, params);
+    }
+}
+
+impl Renderer<u16> for QuadRenderer {
+    type Error = core::convert::Infallible;
+
+    fn begin_frame(&mut self) -> Result<(), Self::Error> {
+        clear_background(self.background);
+        Ok(())
+    }
+
+    fn draw_cell(
+        &mut self,
+        row: u16,
+        col: u16,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> Result<(), Self::Error> {
+        if cell_type == TunnelCellType::Player {
+            self.player = Some((col, info));
+            return Ok(());
+        }
+        let x = f32::from(col) * self.cell;
+        let y = (f32::from(row) - self.offset) * self.cell;
+        self.draw_tile(x, y, Tile::for_cell(cell_type, info));
+        Ok(())
+    }
+
+    fn draw_hud(&mut self, score: u64) -> Result<(), Self::Error> {
+        draw_text(&format!("{score}"), 4., screen_height() - 4., 24., GREEN);
+        Ok(())
+    }
+
+    // the caller still awaits next_frame(), which presents the frame
+    fn end_frame(&mut self) -> Result<(), Self::Error> {
+        if let Some((col, info)) = self.player.take() {
+            let x = f32::from(col) * self.cell;
+            self.draw_tile(x, 0., Tile::for_cell(TunnelCellType::Player, info));
+        }
+        Ok(())
+    }
+
+    fn scroll(&mut self, offset: f32) -> Result<(), Self::Error> {
+        self.offset = offset;
+        Ok(())
+    }
+}
+
+// arrow keys or A/D
+pub fn direction_for(key: KeyCode) -> Option<Direction> {
+    match key {
+        KeyCode::Left | KeyCode::A => Some(Direction::Left),
+        KeyCode::Right | KeyCode::D => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+// the direction pressed since the last frame, if any
+pub fn read_direction() -> Option<Direction> {
+    get_keys_pressed().into_iter().find_map(direction_for)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::Wind;
+
+    #[test]
+    fn floor_tiles_follow_row_info() {
+        let floor = |info| Tile::for_cell(TunnelCellType::Floor, info);
+        assert_eq!(floor(RowInfo::default()), Tile::Floor);
+        let wind = Some(Wind {
+            direction: Direction::Right,
+            strength: 1,
+        });
+        assert_eq!(
+            floor(RowInfo {
+                wind,
+                ..RowInfo::default()
+            }),
+            Tile::WindRight
+        );
+        let ice = RowInfo {
+            floor: Floor::Ice,
+            hazard: Some(Hazard::Warning),
+            ..RowInfo::default()
+        };
+        assert_eq!(floor(ice), Tile::Warning);
+        assert_eq!(Tile::for_cell(TunnelCellType::Wall, ice), Tile::Wall);
+    }
+
+    #[test]
+    fn color_atlas_has_one_pixel_per_tile() {
+        let image = color_image(DEFAULT_COLORS);
+        assert_eq!(image.width(), Tile::COUNT);
+        let green: [u8; 4] = GREEN.into();
+        assert_eq!(image.get_image_data()[Tile::Player as usize], green);
+        assert_eq!(direction_for(KeyCode::A), Some(Direction::Left));
+        assert_eq!(direction_for(KeyCode::Space), None);
+    }
+}
@@ -0,0 +1,413 @@
+// A frontend draws a frame as begin_frame, one draw_cell per visible cell,
+// draw_hud, then end_frame; draw_frame drives that sequence so a frontend
+// only has to say how each piece looks on its surface.
+
+use crate::ansi::glyph;
+use crate::{RowInfo, Tunnel, TunnelCellType, TunnelIndex};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+
+pub trait Renderer<T> {
+    type Error;
+
+    fn begin_frame(&mut self) -> Result<(), Self::Error>;
+    // `info` is the row's RowInfo, for styling floor cells by zone
+    fn draw_cell(
+        &mut self,
+        row: T,
+        col: T,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> Result<(), Self::Error>;
+    fn draw_hud(&mut self, score: u64) -> Result<(), Self::Error>;
+    fn end_frame(&mut self) -> Result<(), Self::Error>;
+
+    // how far (0.0 to 1.0) the rows have slid up towards the next step, for
+    // the cells that follow; the player stays put. Renderers on a grid of
+    // characters can't draw between rows and keep this default
+    fn scroll(&mut self, _offset: f32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    // another player's glyph, on the player's row (0) over the cell drawn
+    // there, such as an earlier run being raced; renderers that have no
+    // way to show one keep this default
+    fn draw_ghost(&mut self, _row: T, _col: T) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// draw cells up to `fog` rows ahead of the player (see iter_visible)
+pub fn draw_frame<T: TunnelIndex, R: Renderer<T>>(
+    r: &mut R,
+    t: &Tunnel<T>,
+    fog: usize,
+    score: u64,
+) -> Result<(), R::Error> {
+    draw_frame_with_ghost(r, t, fog, score, None)
+}
+
+// draw_frame with a ghost in column `ghost`, unless the player is there
+pub fn draw_frame_with_ghost<T: TunnelIndex, R: Renderer<T>>(
+    r: &mut R,
+    t: &Tunnel<T>,
+    fog: usize,
+    score: u64,
+    ghost: Option<T>,
+) -> Result<(), R::Error> {
+    r.begin_frame()?;
+    for (row, col, cell_type) in t.iter_visible(fog) {
+        let info = row
+            .to_usize()
+            .and_then(|row| t.row_info(row))
+            .unwrap_or_default();
+        r.draw_cell(row, col, cell_type, info)?;
+    }
+    if let Some(col) = ghost.filter(|&col| col != t.player()) {
+        r.draw_ghost(T::zero(), col)?;
+    }
+    r.draw_hud(score)?;
+    r.end_frame()
+}
+
+// draw_frame partway to the next step, so the rows glide up between steps
+// rather than jumping a whole row at each one
+pub fn draw_frame_scrolled<T: TunnelIndex, R: Renderer<T>>(
+    r: &mut R,
+    t: &Tunnel<T>,
+    fog: usize,
+    score: u64,
+    offset: f32,
+) -> Result<(), R::Error> {
+    r.scroll(offset.clamp(0., 1.))?;
+    let frame = draw_frame(r, t, fog, score);
+    r.scroll(0.)?;
+    frame
+}
+
+// Another renderer's surface from column `left` on, for games side by
+// side on one screen, as in a split-screen versus. Each game's frame goes
+// through its own Beside; the score and the end of the frame are left to
+// whoever draws the whole screen.
+pub struct Beside<'a, T, R> {
+    pub inner: &'a mut R,
+    pub left: T,
+}
+
+impl<T: TunnelIndex, R: Renderer<T>> Renderer<T> for Beside<'_, T, R> {
+    type Error = R::Error;
+
+    fn begin_frame(&mut self) -> Result<(), R::Error> {
+        self.inner.begin_frame()
+    }
+
+    fn draw_cell(
+        &mut self,
+        row: T,
+        col: T,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> Result<(), R::Error> {
+        self.inner.draw_cell(row, self.left + col, cell_type, info)
+    }
+
+    fn draw_hud(&mut self, _: u64) -> Result<(), R::Error> {
+        Ok(())
+    }
+
+    fn end_frame(&mut self) -> Result<(), R::Error> {
+        Ok(())
+    }
+
+    fn scroll(&mut self, offset: f32) -> Result<(), R::Error> {
+        self.inner.scroll(offset)
+    }
+
+    fn draw_ghost(&mut self, row: T, col: T) -> Result<(), R::Error> {
+        self.inner.draw_ghost(row, self.left + col)
+    }
+}
+
+// The cells of the last frame, for renderers that draw only the cells that
+// changed instead of clearing the screen for every frame, which flickers
+// over ssh and on slow terminals. It starts empty, and a renderer clears
+// it whenever something else draws over the screen; while it is empty the
+// next frame has to be drawn from scratch.
+#[derive(Clone, Debug, Default)]
+pub struct FrameDiff {
+    rows: Vec<Vec<Option<(TunnelCellType, RowInfo)>>>,
+}
+
+impl FrameDiff {
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // notes the cell for the next frame; true if the last one had
+    // something else there
+    pub fn changed(
+        &mut self,
+        row: usize,
+        col: usize,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> bool {
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Vec::new());
+        }
+        let cells = &mut self.rows[row];
+        if cells.len() <= col {
+            cells.resize(col + 1, None);
+        }
+        let cell = Some((cell_type, info));
+        let changed = cells[col] != cell;
+        cells[col] = cell;
+        changed
+    }
+
+    // has the next frame draw the cell again, whatever it is, for a cell
+    // something else was drawn over
+    pub fn forget(&mut self, row: usize, col: usize) {
+        if let Some(cell) = self.rows.get_mut(row).and_then(|r| r.get_mut(col))
+        {
+            *cell = None;
+        }
+    }
+}
+
+// one line per row in the terminal glyphs, without colour or score
+struct Text {
+    out: String,
+    row: Option<usize>,
+}
+
+impl<T: TunnelIndex> Renderer<T> for Text {
+    type Error = Infallible;
+
+    fn begin_frame(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn draw_cell(
+        &mut self,
+        row: T,
+        _: T,
+        cell_type: TunnelCellType,
+        info: RowInfo,
+    ) -> Result<(), Infallible> {
+        let row = row.to_usize();
+        if self.row.is_some() && self.row != row {
+            self.out.push('\n');
+        }
+        self.row = row;
+        self.out.push(char::from(glyph(cell_type, info).0));
+        Ok(())
+    }
+
+    fn draw_hud(&mut self, _: u64) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn end_frame(&mut self) -> Result<(), Infallible> {
+        if self.row.is_some() {
+            self.out.push('\n');
+        }
+        Ok(())
+    }
+}
+
+// the frame as text, for tests, logs and bug reports
+pub fn render_to_string<T: TunnelIndex>(t: &Tunnel<T>, fog: usize) -> String {
+    let mut text = Text {
+        out: String::new(),
+        row: None,
+    };
+    let Ok(()) = draw_frame(&mut text, t, fog, 0);
+    text.out
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::TunnelBuilder;
+    use crate::builders::{Mirror, Solvable};
+    use crate::difficulty::Difficulty;
+    use crate::pattern::PatternBuilder;
+    use crate::seeded::Seeded;
+    use alloc::vec::Vec;
+    use expect_test::expect_file;
+
+    #[derive(Debug, PartialEq)]
+    enum Call {
+        Begin,
+        Cell(u8, u8, TunnelCellType),
+        Hud(u64),
+        End,
+        Scroll(f32),
+        Ghost(u8),
+    }
+
+    impl Renderer<u8> for Vec<Call> {
+        type Error = ();
+        fn begin_frame(&mut self) -> Result<(), ()> {
+            self.push(Call::Begin);
+            Ok(())
+        }
+        fn draw_cell(
+            &mut self,
+            row: u8,
+            col: u8,
+            cell_type: TunnelCellType,
+            _: RowInfo,
+        ) -> Result<(), ()> {
+            self.push(Call::Cell(row, col, cell_type));
+            Ok(())
+        }
+        fn draw_hud(&mut self, score: u64) -> Result<(), ()> {
+            self.push(Call::Hud(score));
+            Ok(())
+        }
+        fn end_frame(&mut self) -> Result<(), ()> {
+            self.push(Call::End);
+            Ok(())
+        }
+        fn scroll(&mut self, offset: f32) -> Result<(), ()> {
+            self.push(Call::Scroll(offset));
+            Ok(())
+        }
+        fn draw_ghost(&mut self, row: u8, col: u8) -> Result<(), ()> {
+            assert_eq!(row, 0);
+            self.push(Call::Ghost(col));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn frame_draws_every_cell_between_begin_and_hud() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u8>::new(&mut b, 6, 7);
+        let mut calls = Vec::new();
+        draw_frame(&mut calls, &t, usize::MAX, 42).unwrap();
+
+        assert_eq!(calls.first(), Some(&Call::Begin));
+        assert_eq!(calls[calls.len() - 2..], [Call::Hud(42), Call::End]);
+        let cells = t.iter().map(|(r, c, ty)| Call::Cell(r, c, ty));
+        assert!(cells.eq(calls.drain(1..calls.len() - 2)));
+    }
+
+    #[test]
+    fn scrolled_frame_is_offset_then_reset() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u8>::new(&mut b, 6, 7);
+        let mut calls = Vec::new();
+        draw_frame_scrolled(&mut calls, &t, usize::MAX, 3, 0.25).unwrap();
+        assert_eq!(calls[..2], [Call::Scroll(0.25), Call::Begin]);
+        assert_eq!(calls[calls.len() - 2..], [Call::End, Call::Scroll(0.)]);
+        calls.clear();
+        draw_frame_scrolled(&mut calls, &t, usize::MAX, 3, 4.).unwrap();
+        assert_eq!(calls.first(), Some(&Call::Scroll(1.)));
+    }
+
+    #[test]
+    fn ghost_goes_over_the_cells_unless_the_player_is_there() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u8>::new(&mut b, 6, 7);
+        let mut calls = Vec::new();
+        let ghost = t.player() + 1;
+        draw_frame_with_ghost(&mut calls, &t, usize::MAX, 3, Some(ghost))
+            .unwrap();
+        assert_eq!(
+            calls[calls.len() - 3..],
+            [Call::Ghost(ghost), Call::Hud(3), Call::End]
+        );
+        calls.clear();
+        draw_frame_with_ghost(&mut calls, &t, usize::MAX, 3, Some(t.player()))
+            .unwrap();
+        assert!(!calls.iter().any(|c| matches!(c, Call::Ghost(_))));
+    }
+
+    #[test]
+    fn beside_shifts_the_cells_and_leaves_the_hud() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u8>::new(&mut b, 6, 7);
+        let mut calls = Vec::new();
+        let mut right = Beside {
+            inner: &mut calls,
+            left: 9,
+        };
+        draw_frame(&mut right, &t, usize::MAX, 5).unwrap();
+        assert_eq!(calls.first(), Some(&Call::Begin));
+        let cells = t.iter().map(|(r, c, ty)| Call::Cell(r, c + 9, ty));
+        assert!(cells.eq(calls.drain(1..)));
+    }
+
+    fn played(
+        b: &mut impl TunnelBuilder,
+        rows: u16,
+        cols: u16,
+        steps: usize,
+    ) -> Tunnel<u16> {
+        let mut t =
+            Tunnel::with_config(b, rows, cols, Difficulty::Normal.config());
+        for _ in 0..steps {
+            t.step(b);
+        }
+        t
+    }
+
+    #[test]
+    fn text_has_one_line_per_row() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u8>::new(&mut b, 6, 7);
+        let text = render_to_string(&t, usize::MAX);
+        assert_eq!(text.lines().count(), t.buffered_rows());
+        assert!(text.lines().all(|line| line.len() == 7));
+        assert!(render_to_string(&t, 1).contains('.'));
+    }
+
+    #[test]
+    fn diff_passes_only_changed_cells() {
+        let mut diff = FrameDiff::default();
+        assert!(diff.is_empty());
+        let info = RowInfo::default();
+        assert!(diff.changed(2, 3, TunnelCellType::Wall, info));
+        assert!(!diff.is_empty());
+        assert!(!diff.changed(2, 3, TunnelCellType::Wall, info));
+        assert!(diff.changed(2, 3, TunnelCellType::Floor, info));
+        assert!(diff.changed(0, 0, TunnelCellType::Floor, info));
+        diff.forget(0, 0);
+        diff.forget(9, 9);
+        assert!(diff.changed(0, 0, TunnelCellType::Floor, info));
+        diff.clear();
+        assert!(diff.is_empty());
+        assert!(diff.changed(2, 3, TunnelCellType::Floor, info));
+    }
+
+    // UPDATE_EXPECT=1 cargo test rewrites these after an intended change
+    #[test]
+    fn stock_builders_match_golden_frames() {
+        let frame = |t: Tunnel<u16>| render_to_string(&t, usize::MAX);
+        expect_file!["../tests/frames/seeded-8x12.txt"]
+            .assert_eq(&frame(played(&mut Seeded::new(1), 8, 12, 20)));
+        expect_file!["../tests/frames/seeded-16x24.txt"]
+            .assert_eq(&frame(played(&mut Seeded::new(2), 16, 24, 40)));
+        expect_file!["../tests/frames/seeded-24x48.txt"]
+            .assert_eq(&frame(played(&mut Seeded::new(3), 24, 48, 80)));
+        let mut mirror = Mirror::new(Seeded::new(2));
+        expect_file!["../tests/frames/mirror-16x24.txt"]
+            .assert_eq(&frame(played(&mut mirror, 16, 24, 40)));
+        let mut solvable = Solvable::new(Seeded::new(4));
+        expect_file!["../tests/frames/solvable-20x16.txt"]
+            .assert_eq(&frame(played(&mut solvable, 20, 16, 60)));
+        let mut pattern =
+            PatternBuilder::parse("L3 R3 S2 K4 { { } } M2").unwrap();
+        expect_file!["../tests/frames/pattern-12x20.txt"]
+            .assert_eq(&frame(played(&mut pattern, 12, 20, 30)));
+    }
+}
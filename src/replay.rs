@@ -0,0 +1,225 @@
+// Deterministic replays: a run code (seed, size and difficulty), the game mode,
+// and the moves made before each step. The tunnel comes from the seed alone, so
+// the same moves with the same builder score the same on every machine, which
+// is how a leaderboard server checks a submitted score. As text, the code on
+// the first line, followed by the mode unless it's endless, then a line per
+// step of Action::code() digits, empty for a step without moves:
+//
+//     N0TK-YM2E-7GQ4-C8AR-W1BX-6F3D time-attack
+//     1
+//
+//     22
+//
+// A crash ends the replay; the step it happened on is the last line. So
+// does the end of a timed round, which the replay keeps no clock for.
+
+use crate::mode::Mode;
+use crate::runcode::RunCode;
+use crate::sim::Action;
+use crate::{Tunnel, TunnelBuilder};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub run: RunCode,
+    pub mode: Mode,
+    pub steps: Vec<Vec<Action>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReplayError {
+    MissingCode,
+    BadCode(crate::runcode::RunCodeError),
+    BadMove { line: usize },
+}
+
+impl core::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ReplayError::MissingCode => write!(f, "no run code"),
+            ReplayError::BadCode(e) => write!(f, "run code: {e}"),
+            ReplayError::BadMove { line } => write!(f, "line {line}: bad move"),
+        }
+    }
+}
+
+impl core::error::Error for ReplayError {}
+
+impl Replay {
+    pub fn new(run: RunCode, mode: Mode) -> Replay {
+        Replay {
+            run,
+            mode,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut lines = text.lines();
+        let code = lines.next().ok_or(ReplayError::MissingCode)?.trim();
+        // codes may be spaced out in groups, so only a mode name is a mode
+        let (code, mode) =
+            match code.rsplit_once(' ').map(|(c, m)| (c, m.parse())) {
+                Some((code, Ok(mode))) => (code, mode),
+                _ => (code, Mode::Endless),
+            };
+        let run = RunCode::decode(code).map_err(ReplayError::BadCode)?;
+        let steps = lines
+            .enumerate()
+            .map(|(i, line)| {
+                line.trim()
+                    .bytes()
+                    .map(|b| {
+                        b.checked_sub(b'0')
+                            .and_then(Action::from_code)
+                            .ok_or(ReplayError::BadMove { line: i + 2 })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Replay { run, mode, steps })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = self.run.to_string();
+        if self.mode != Mode::Endless {
+            text.push(' ');
+            text.push_str(self.mode.name());
+        }
+        text.push('\n');
+        for step in &self.steps {
+            text.extend(step.iter().map(|a| char::from(b'0' + a.code())));
+            text.push('\n');
+        }
+        text
+    }
+
+    // FNV-1a over the text, to name a replay without sending it whole
+    pub fn hash(&self) -> u64 {
+        self.to_text()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+                (hash ^ u64::from(b)).wrapping_mul(0x100_0000_01b3)
+            })
+    }
+
+    // plays the moves over the tunnel `b` builds from the run, and scores
+    // each step survived as the mode does; the builder has to be the one
+    // the game was played with, seeded from the run
+    pub fn score(&self, b: &mut impl TunnelBuilder) -> u64 {
+        let mut score = 0;
+        self.play(b, |t| {
+            if !t.is_collision() {
+                score += self.mode.row_points(t);
+            }
+        });
+        score
+    }
+
+    // the player's column at the start and after each step, up to the
+    // crash, for drawing the run back as a ghost; `b` as for score()
+    pub fn columns(&self, b: &mut impl TunnelBuilder) -> Vec<u16> {
+        let mut columns = Vec::with_capacity(self.steps.len() + 1);
+        let start = self.play(b, |t| columns.push(t.player()));
+        columns.insert(0, start);
+        columns
+    }
+
+    // `each` sees the tunnel after every step, the crash included; the
+    // player's starting column comes back
+    fn play(
+        &self,
+        b: &mut impl TunnelBuilder,
+        mut each: impl FnMut(&Tunnel<u16>),
+    ) -> u16 {
+        let config = self.run.config;
+        let mut t: Tunnel<u16> = Tunnel::with_config(
+            b,
+            config.rows,
+            config.cols,
+            config.difficulty.config(),
+        );
+        let start = t.player();
+        for step in &self.steps {
+            // as in play, a move into a wall ends the moves until the step
+            for action in step {
+                if t.is_collision() {
+                    break;
+                }
+                action.apply(&mut t);
+            }
+            t.step(b);
+            each(&t);
+            if t.is_collision() {
+                break;
+            }
+        }
+        start
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::runcode::RunConfig;
+    use crate::seeded::Seeded;
+    use alloc::{format, vec};
+
+    fn run() -> RunCode {
+        RunCode {
+            seed: 11,
+            config: RunConfig {
+                rows: 12,
+                cols: 20,
+                difficulty: Difficulty::Chill,
+            },
+        }
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let mut replay = Replay::new(run(), Mode::Endless);
+        replay.steps = vec![vec![Action::Left], vec![], vec![Action::Right; 2]];
+        let text = replay.to_text();
+        assert!(text.ends_with("\n1\n\n22\n"));
+        assert_eq!(Replay::parse(&text), Ok(replay.clone()));
+        assert_ne!(replay.hash(), Replay::new(run(), Mode::Endless).hash());
+        assert_eq!(Replay::parse(""), Err(ReplayError::MissingCode));
+        let spaced = text.replace('-', " ");
+        assert_eq!(Replay::parse(&spaced), Ok(replay.clone()));
+        replay.mode = Mode::TimeAttack;
+        let timed = replay.to_text();
+        assert!(timed.starts_with(&format!("{} time-attack\n", run())));
+        assert_eq!(Replay::parse(&timed), Ok(replay));
+        let bad = text.replace("22", "29");
+        assert_eq!(Replay::parse(&bad), Err(ReplayError::BadMove { line: 4 }));
+    }
+
+    #[test]
+    fn same_moves_same_score() {
+        let mut replay = Replay::new(run(), Mode::Endless);
+        replay.steps = vec![vec![]; 30];
+        let score = replay.score(&mut Seeded::new(run().seed));
+        assert_eq!(score, replay.score(&mut Seeded::new(run().seed)));
+        assert!(score <= 30);
+        // steps after the crash are never reached
+        if score < 30 {
+            replay.steps.push(vec![Action::Left]);
+            assert_eq!(replay.score(&mut Seeded::new(run().seed)), score);
+        }
+    }
+
+    #[test]
+    fn columns_follow_the_moves() {
+        let mut replay = Replay::new(run(), Mode::Endless);
+        replay.steps = vec![vec![Action::Left], vec![], vec![Action::Right; 2]];
+        let columns = replay.columns(&mut Seeded::new(run().seed));
+        assert!(columns.len() <= 4);
+        let start = columns[0];
+        let moved = [start - 1, start - 1, start + 1];
+        assert!(columns[1..].iter().eq(&moved[..columns.len() - 1]));
+    }
+}
@@ -0,0 +1,89 @@
+// Taking a game back a few steps, for practice modes. A frontend pushes a
+// snapshot of whatever its game is (a Tunnel::fork(), the builder, the
+// score) before each step, and rewind() hands back the one from that many
+// steps ago. Only the newest `capacity` are kept.
+
+use alloc::collections::VecDeque;
+
+pub struct Rewind<S> {
+    snapshots: VecDeque<S>,
+    capacity: usize,
+}
+
+impl<S> Rewind<S> {
+    pub fn new(capacity: usize) -> Rewind<S> {
+        Rewind {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, snapshot: S) {
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    // the snapshot from `steps` pushes ago (1 is the latest), or the oldest
+    // there is, dropping it and every newer one, so rewinding again goes
+    // back further
+    pub fn rewind(&mut self, steps: usize) -> Option<S> {
+        let keep = self.snapshots.len().saturating_sub(steps);
+        self.snapshots.truncate(keep + 1);
+        self.snapshots.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::Tunnel;
+    use crate::pattern::PatternBuilder;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn rewinds_drop_what_came_after() {
+        let mut r = Rewind::new(4);
+        assert_eq!(r.rewind(1), None);
+        for score in 0..6 {
+            r.push(score);
+        }
+        assert_eq!(r.len(), 4);
+        assert_eq!(r.rewind(2), Some(4));
+        assert_eq!(r.rewind(1), Some(3));
+        assert_eq!(r.rewind(9), Some(2));
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn a_rewound_tunnel_plays_out_the_same() {
+        let mut b = PatternBuilder::parse("L3 K R2 S M").unwrap();
+        let mut t: Tunnel<u16> = Tunnel::new(&mut b, 8, 16);
+        let mut r = Rewind::new(8);
+        let mut rows = Vec::new();
+        for _ in 0..5 {
+            r.push((t.fork(), b.clone()));
+            t.step(&mut b);
+            rows.push(t.iter().collect::<Vec<_>>());
+        }
+        let (mut t, mut b) = r.rewind(3).unwrap();
+        for row in &rows[2..] {
+            t.step(&mut b);
+            assert_eq!(&t.iter().collect::<Vec<_>>(), row);
+        }
+    }
+}
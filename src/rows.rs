@@ -0,0 +1,845 @@
+// Storage for the buffered rows. Plain keeps one entry per row; Runs keeps
+// one entry per run of identical rows, so a tall look-ahead buffer costs
+// memory proportional to how often the walls change rather than to height;
+// Fixed is a ring of N rows held inline, for tunnels that must not allocate
+// (heapless::Deque with the `heapless` feature, ArrayRing otherwise);
+// Shared keeps rows in reference-counted chunks, so a clone shares every
+// chunk and copies one only when either side writes to it; Columns splits
+// rows into parallel buffers of left walls, gaps and everything else, so
+// scans over wall positions walk contiguous runs of T. A columnar row only
+// exists once reassembled, so rows are read through RowRef and written
+// through RowMut. Packed does the same with walls squeezed into a byte each
+// and the rest kept as runs, for tunnels at most 255 columns wide.
+
+use crate::{RowInfo, TunnelIndex, TunnelWalls};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "heapless")]
+type Ring<T, const N: usize> = heapless::Deque<TunnelWalls<T>, N>;
+#[cfg(not(feature = "heapless"))]
+type Ring<T, const N: usize> = ArrayRing<T, N>;
+
+// rows per Shared chunk: a write after fork() copies at most this many
+const CHUNK: usize = 32;
+
+// the subset of heapless::Deque that RowStore needs
+#[cfg(not(feature = "heapless"))]
+#[derive(Clone)]
+pub(crate) struct ArrayRing<T, const N: usize> {
+    rows: [Option<TunnelWalls<T>>; N],
+    head: usize,
+    len: usize,
+}
+
+#[cfg(not(feature = "heapless"))]
+impl<T: Copy, const N: usize> ArrayRing<T, N> {
+    fn new() -> Self {
+        ArrayRing {
+            rows: [None; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Option<&TunnelWalls<T>> {
+        if index >= self.len {
+            return None;
+        }
+        self.rows[(self.head + index) % N].as_ref()
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut TunnelWalls<T>> {
+        if index >= self.len {
+            return None;
+        }
+        self.rows[(self.head + index) % N].as_mut()
+    }
+
+    fn back(&self) -> Option<&TunnelWalls<T>> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    fn push_back(&mut self, row: TunnelWalls<T>) -> Result<(), TunnelWalls<T>> {
+        if self.len == N {
+            return Err(row);
+        }
+        self.rows[(self.head + self.len) % N] = Some(row);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop_front(&mut self) -> Option<TunnelWalls<T>> {
+        self.len = self.len.checked_sub(1)?;
+        let row = self.rows[self.head].take();
+        self.head = (self.head + 1) % N;
+        row
+    }
+
+    fn iter(&self) -> impl DoubleEndedIterator<Item = &TunnelWalls<T>> {
+        (0..self.len).filter_map(|i| self.rows[(self.head + i) % N].as_ref())
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Columns<T> {
+    left_walls: VecDeque<T>,
+    gaps: VecDeque<T>,
+    rest: VecDeque<(Option<(T, T)>, RowInfo)>,
+}
+
+impl<T: Copy> Columns<T> {
+    fn with_capacity(capacity: usize) -> Self {
+        Columns {
+            left_walls: VecDeque::with_capacity(capacity),
+            gaps: VecDeque::with_capacity(capacity),
+            rest: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.left_walls.len()
+    }
+
+    fn get(&self, index: usize) -> Option<TunnelWalls<T>> {
+        let (pillar, info) = *self.rest.get(index)?;
+        Some(TunnelWalls {
+            left_wall: self.left_walls[index],
+            gap_to_right_wall: self.gaps[index],
+            pillar,
+            info,
+        })
+    }
+
+    fn set(&mut self, index: usize, row: TunnelWalls<T>) {
+        self.left_walls[index] = row.left_wall;
+        self.gaps[index] = row.gap_to_right_wall;
+        self.rest[index] = (row.pillar, row.info);
+    }
+
+    fn push_back(&mut self, row: TunnelWalls<T>) {
+        self.left_walls.push_back(row.left_wall);
+        self.gaps.push_back(row.gap_to_right_wall);
+        self.rest.push_back((row.pillar, row.info));
+    }
+
+    fn pop_front(&mut self) -> Option<TunnelWalls<T>> {
+        let row = self.get(0)?;
+        self.left_walls.pop_front();
+        self.gaps.pop_front();
+        self.rest.pop_front();
+        Some(row)
+    }
+}
+
+type Extras = (Option<[u8; 2]>, RowInfo);
+
+#[derive(Clone)]
+pub(crate) struct Packed<T> {
+    walls: VecDeque<[u8; 2]>,
+    // pillars and row info seldom change from one row to the next
+    extras: VecDeque<(Extras, usize)>,
+    index: PhantomData<T>,
+}
+
+// columns past u8::MAX never reach here: Packed is only picked for
+// tunnels narrow enough
+fn pack<T: TunnelIndex>(column: T) -> u8 {
+    column.to_u8().unwrap_or(u8::MAX)
+}
+
+impl<T: TunnelIndex> Packed<T> {
+    fn with_capacity(capacity: usize) -> Self {
+        Packed {
+            walls: VecDeque::with_capacity(capacity),
+            extras: VecDeque::new(),
+            index: PhantomData,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.walls.len()
+    }
+
+    fn get(&self, index: usize) -> Option<TunnelWalls<T>> {
+        let walls = *self.walls.get(index)?;
+        Some(Packed::unpack(walls, *run_at(&self.extras, index)?))
+    }
+
+    fn unpack(walls: [u8; 2], extras: Extras) -> TunnelWalls<T> {
+        let ([left_wall, gap], (pillar, info)) = (walls, extras);
+        TunnelWalls {
+            left_wall: left_wall.into(),
+            gap_to_right_wall: gap.into(),
+            pillar: pillar.map(|[start, width]| (start.into(), width.into())),
+            info,
+        }
+    }
+
+    // walks walls and runs side by side instead of looking up each row's run
+    fn iter(&self) -> PackedIter<'_, T> {
+        PackedIter {
+            packed: self,
+            lo: 0,
+            hi: self.len(),
+            lo_run: 0,
+            lo_left: self.extras.front().map_or(0, |(_, count)| *count),
+            hi_run: self.extras.len().saturating_sub(1),
+            hi_left: self.extras.back().map_or(0, |(_, count)| *count),
+        }
+    }
+
+    fn extras(row: &TunnelWalls<T>) -> Extras {
+        let pillar =
+            row.pillar.map(|(start, width)| [pack(start), pack(width)]);
+        (pillar, row.info)
+    }
+
+    fn set(&mut self, index: usize, row: TunnelWalls<T>) {
+        self.walls[index] = [pack(row.left_wall), pack(row.gap_to_right_wall)];
+        let extras = Packed::extras(&row);
+        if run_at(&self.extras, index) != Some(&extras)
+            && let Some(run) = split_run(&mut self.extras, index)
+        {
+            *run = extras;
+        }
+    }
+
+    fn push_back(&mut self, row: TunnelWalls<T>) {
+        self.walls
+            .push_back([pack(row.left_wall), pack(row.gap_to_right_wall)]);
+        push_run(&mut self.extras, Packed::extras(&row));
+    }
+
+    fn pop_front(&mut self) -> Option<TunnelWalls<T>> {
+        let row = self.get(0)?;
+        self.walls.pop_front();
+        pop_run(&mut self.extras);
+        Some(row)
+    }
+}
+
+// rows lo..hi are left; lo_left rows of run lo_run remain from lo on, and
+// hi_left rows of run hi_run remain up to hi
+pub(crate) struct PackedIter<'a, T> {
+    packed: &'a Packed<T>,
+    lo: usize,
+    hi: usize,
+    lo_run: usize,
+    lo_left: usize,
+    hi_run: usize,
+    hi_left: usize,
+}
+
+impl<T: TunnelIndex> Iterator for PackedIter<'_, T> {
+    type Item = TunnelWalls<T>;
+
+    fn next(&mut self) -> Option<TunnelWalls<T>> {
+        if self.lo >= self.hi {
+            return None;
+        }
+        if self.lo_left == 0 {
+            self.lo_run += 1;
+            self.lo_left = self.packed.extras[self.lo_run].1;
+        }
+        let walls = self.packed.walls[self.lo];
+        let extras = self.packed.extras[self.lo_run].0;
+        self.lo += 1;
+        self.lo_left -= 1;
+        Some(Packed::unpack(walls, extras))
+    }
+}
+
+impl<T: TunnelIndex> DoubleEndedIterator for PackedIter<'_, T> {
+    fn next_back(&mut self) -> Option<TunnelWalls<T>> {
+        if self.lo >= self.hi {
+            return None;
+        }
+        if self.hi_left == 0 {
+            self.hi_run -= 1;
+            self.hi_left = self.packed.extras[self.hi_run].1;
+        }
+        self.hi -= 1;
+        self.hi_left -= 1;
+        let walls = self.packed.walls[self.hi];
+        Some(Packed::unpack(walls, self.packed.extras[self.hi_run].0))
+    }
+}
+
+fn run_at<V>(runs: &VecDeque<(V, usize)>, index: usize) -> Option<&V> {
+    let mut skipped = 0;
+    runs.iter().find_map(|(value, count)| {
+        skipped += count;
+        (index < skipped).then_some(value)
+    })
+}
+
+// splits the run holding `index` so a change touches that row alone
+fn split_run<V: Copy>(
+    runs: &mut VecDeque<(V, usize)>,
+    index: usize,
+) -> Option<&mut V> {
+    let mut start = 0;
+    let mut found = None;
+    for (i, (_, count)) in runs.iter().enumerate() {
+        if index < start + count {
+            found = Some(i);
+            break;
+        }
+        start += count;
+    }
+    let mut i = found?;
+    let (value, count) = runs[i];
+    let before = index - start;
+    let after = count - before - 1;
+    runs[i].1 = 1;
+    if before > 0 {
+        runs.insert(i, (value, before));
+        i += 1;
+    }
+    if after > 0 {
+        runs.insert(i + 1, (value, after));
+    }
+    runs.get_mut(i).map(|(value, _)| value)
+}
+
+fn push_run<V: PartialEq>(runs: &mut VecDeque<(V, usize)>, value: V) {
+    match runs.back_mut() {
+        Some((last, count)) if *last == value => *count += 1,
+        _ => runs.push_back((value, 1)),
+    }
+}
+
+fn pop_run<V: Copy>(runs: &mut VecDeque<(V, usize)>) -> Option<V> {
+    let (value, count) = runs.front_mut()?;
+    let value = *value;
+    *count -= 1;
+    if *count == 0 {
+        runs.pop_front();
+    }
+    Some(value)
+}
+
+// a row borrowed for reading; a columnar or packed row is reassembled into
+// a copy
+pub(crate) enum RowRef<'a, T> {
+    Borrowed(&'a TunnelWalls<T>),
+    Owned(TunnelWalls<T>),
+}
+
+impl<T> Deref for RowRef<'_, T> {
+    type Target = TunnelWalls<T>;
+
+    fn deref(&self) -> &TunnelWalls<T> {
+        match self {
+            RowRef::Borrowed(row) => row,
+            RowRef::Owned(row) => row,
+        }
+    }
+}
+
+// a row borrowed for writing; a columnar or packed row is a copy that is
+// written back when the borrow ends
+pub(crate) enum RowMut<'a, T: TunnelIndex> {
+    Direct(&'a mut TunnelWalls<T>),
+    Columns {
+        columns: &'a mut Columns<T>,
+        index: usize,
+        row: TunnelWalls<T>,
+    },
+    Packed {
+        packed: &'a mut Packed<T>,
+        index: usize,
+        row: TunnelWalls<T>,
+    },
+}
+
+impl<T: TunnelIndex> Deref for RowMut<'_, T> {
+    type Target = TunnelWalls<T>;
+
+    fn deref(&self) -> &TunnelWalls<T> {
+        match self {
+            RowMut::Direct(row) => row,
+            RowMut::Columns { row, .. } | RowMut::Packed { row, .. } => row,
+        }
+    }
+}
+
+impl<T: TunnelIndex> DerefMut for RowMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut TunnelWalls<T> {
+        match self {
+            RowMut::Direct(row) => row,
+            RowMut::Columns { row, .. } | RowMut::Packed { row, .. } => row,
+        }
+    }
+}
+
+impl<T: TunnelIndex> Drop for RowMut<'_, T> {
+    fn drop(&mut self) {
+        match self {
+            RowMut::Direct(_) => {}
+            RowMut::Columns {
+                columns,
+                index,
+                row,
+            } => columns.set(*index, *row),
+            RowMut::Packed { packed, index, row } => packed.set(*index, *row),
+        }
+    }
+}
+
+pub(crate) enum RowStore<T, const N: usize> {
+    Plain(VecDeque<TunnelWalls<T>>),
+    Runs {
+        runs: VecDeque<(TunnelWalls<T>, usize)>,
+        len: usize,
+    },
+    // carries its own clone(): see fixed()
+    Fixed(Ring<T, N>, fn(&Ring<T, N>) -> Ring<T, N>),
+    // every chunk but the last is full; `head` rows of the first are popped
+    Shared {
+        chunks: VecDeque<Arc<Vec<TunnelWalls<T>>>>,
+        head: usize,
+        len: usize,
+    },
+    Columns(Columns<T>),
+    Packed(Packed<T>),
+}
+
+impl<T: TunnelIndex, const N: usize> RowStore<T, N> {
+    pub(crate) fn with_capacity(compress: bool, capacity: usize) -> Self {
+        if compress {
+            RowStore::Runs {
+                runs: VecDeque::new(),
+                len: 0,
+            }
+        } else {
+            RowStore::Plain(VecDeque::with_capacity(capacity))
+        }
+    }
+
+    // kept apart from with_capacity(): heapless::Deque rejects N == 0 at
+    // compile time, so only TunnelFixed may mention it. That covers
+    // Deque::clone() too, hence the function pointer taken here rather
+    // than a call in RowStore::clone(), which every Tunnel instantiates.
+    pub(crate) fn fixed() -> Self {
+        RowStore::Fixed(Ring::new(), Ring::clone)
+    }
+
+    pub(crate) fn columns(capacity: usize) -> Self {
+        RowStore::Columns(Columns::with_capacity(capacity))
+    }
+
+    pub(crate) fn packed(capacity: usize) -> Self {
+        RowStore::Packed(Packed::with_capacity(capacity))
+    }
+
+    pub(crate) fn shared() -> Self {
+        RowStore::Shared {
+            chunks: VecDeque::new(),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            RowStore::Plain(rows) => rows.len(),
+            RowStore::Runs { len, .. } | RowStore::Shared { len, .. } => *len,
+            RowStore::Fixed(ring, _) => ring.len(),
+            RowStore::Columns(columns) => columns.len(),
+            RowStore::Packed(packed) => packed.len(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        match self {
+            RowStore::Plain(rows) => rows.capacity(),
+            RowStore::Runs { runs, .. } => runs.capacity(),
+            RowStore::Fixed(..) => N,
+            RowStore::Shared { chunks, .. } => chunks.len() * CHUNK,
+            RowStore::Columns(columns) => columns.left_walls.capacity(),
+            RowStore::Packed(packed) => packed.walls.capacity(),
+        }
+    }
+
+    // entries actually held: rows for Plain, runs for Runs, chunks for
+    // Shared, runs of extras for Packed
+    #[cfg(test)]
+    pub(crate) fn stored(&self) -> usize {
+        match self {
+            RowStore::Plain(rows) => rows.len(),
+            RowStore::Runs { runs, .. } => runs.len(),
+            RowStore::Fixed(ring, _) => ring.len(),
+            RowStore::Shared { chunks, .. } => chunks.len(),
+            RowStore::Columns(columns) => columns.len(),
+            RowStore::Packed(packed) => packed.extras.len(),
+        }
+    }
+
+    pub(crate) fn front(&self) -> Option<RowRef<'_, T>> {
+        let row = match self {
+            RowStore::Plain(rows) => rows.front(),
+            RowStore::Runs { runs, .. } => runs.front().map(|(row, _)| row),
+            RowStore::Fixed(ring, _) => ring.get(0),
+            RowStore::Shared { chunks, head, .. } => chunks.front()?.get(*head),
+            RowStore::Columns(columns) => {
+                return columns.get(0).map(RowRef::Owned);
+            }
+            RowStore::Packed(packed) => {
+                return packed.get(0).map(RowRef::Owned);
+            }
+        };
+        row.map(RowRef::Borrowed)
+    }
+
+    pub(crate) fn back(&self) -> Option<RowRef<'_, T>> {
+        let row = match self {
+            RowStore::Plain(rows) => rows.back(),
+            RowStore::Runs { runs, .. } => runs.back().map(|(row, _)| row),
+            RowStore::Fixed(ring, _) => ring.back(),
+            RowStore::Shared { chunks, .. } => chunks.back()?.last(),
+            RowStore::Columns(columns) => {
+                let last = columns.len().checked_sub(1)?;
+                return columns.get(last).map(RowRef::Owned);
+            }
+            RowStore::Packed(packed) => {
+                let last = packed.len().checked_sub(1)?;
+                return packed.get(last).map(RowRef::Owned);
+            }
+        };
+        row.map(RowRef::Borrowed)
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<RowRef<'_, T>> {
+        let row = match self {
+            RowStore::Plain(rows) => rows.get(index),
+            RowStore::Runs { runs, .. } => run_at(runs, index),
+            RowStore::Fixed(ring, _) => ring.get(index),
+            RowStore::Shared { chunks, head, len } => {
+                if index >= *len {
+                    return None;
+                }
+                let index = index + *head;
+                chunks.get(index / CHUNK)?.get(index % CHUNK)
+            }
+            RowStore::Columns(columns) => {
+                return columns.get(index).map(RowRef::Owned);
+            }
+            RowStore::Packed(packed) => {
+                return packed.get(index).map(RowRef::Owned);
+            }
+        };
+        row.map(RowRef::Borrowed)
+    }
+
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<RowMut<'_, T>> {
+        let row = match self {
+            RowStore::Plain(rows) => rows.get_mut(index),
+            RowStore::Runs { runs, .. } => split_run(runs, index),
+            RowStore::Fixed(ring, _) => ring.get_mut(index),
+            RowStore::Shared { chunks, head, len } => {
+                if index >= *len {
+                    return None;
+                }
+                let index = index + *head;
+                let chunk = chunks.get_mut(index / CHUNK)?;
+                Arc::make_mut(chunk).get_mut(index % CHUNK)
+            }
+            RowStore::Columns(columns) => {
+                let row = columns.get(index)?;
+                return Some(RowMut::Columns {
+                    columns,
+                    index,
+                    row,
+                });
+            }
+            RowStore::Packed(packed) => {
+                let row = packed.get(index)?;
+                return Some(RowMut::Packed { packed, index, row });
+            }
+        };
+        row.map(RowMut::Direct)
+    }
+
+    pub(crate) fn front_mut(&mut self) -> Option<RowMut<'_, T>> {
+        self.get_mut(0)
+    }
+
+    pub(crate) fn push_back(&mut self, row: TunnelWalls<T>) {
+        match self {
+            RowStore::Plain(rows) => rows.push_back(row),
+            RowStore::Runs { runs, len } => {
+                *len += 1;
+                push_run(runs, row);
+            }
+            // a full ring drops its oldest row to make room
+            RowStore::Fixed(ring, _) => {
+                if let Err(row) = ring.push_back(row) {
+                    ring.pop_front();
+                    // cannot fail: the ring just made room
+                    let _ = ring.push_back(row);
+                }
+            }
+            RowStore::Shared { chunks, len, .. } => {
+                *len += 1;
+                match chunks.back_mut() {
+                    Some(chunk) if chunk.len() < CHUNK => {
+                        Arc::make_mut(chunk).push(row);
+                    }
+                    _ => {
+                        let mut chunk = Vec::with_capacity(CHUNK);
+                        chunk.push(row);
+                        chunks.push_back(Arc::new(chunk));
+                    }
+                }
+            }
+            RowStore::Columns(columns) => columns.push_back(row),
+            RowStore::Packed(packed) => packed.push_back(row),
+        }
+    }
+
+    pub(crate) fn pop_front(&mut self) -> Option<TunnelWalls<T>> {
+        match self {
+            RowStore::Plain(rows) => rows.pop_front(),
+            RowStore::Runs { runs, len } => {
+                let row = pop_run(runs)?;
+                *len -= 1;
+                Some(row)
+            }
+            RowStore::Fixed(ring, _) => ring.pop_front(),
+            RowStore::Shared { chunks, head, len } => {
+                let chunk = chunks.front()?;
+                let row = chunk[*head];
+                *head += 1;
+                if *head == chunk.len() {
+                    chunks.pop_front();
+                    *head = 0;
+                }
+                *len -= 1;
+                Some(row)
+            }
+            RowStore::Columns(columns) => columns.pop_front(),
+            RowStore::Packed(packed) => packed.pop_front(),
+        }
+    }
+
+    pub(crate) fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = RowRef<'_, T>> {
+        let (plain, runs, fixed, packed, indexed) = match self {
+            RowStore::Plain(rows) => {
+                (Some(rows.iter()), None, None, None, None)
+            }
+            RowStore::Runs { runs, .. } => (
+                None,
+                Some(runs.iter().flat_map(|(row, count)| {
+                    core::iter::repeat_n(row, *count)
+                })),
+                None,
+                None,
+                None,
+            ),
+            RowStore::Fixed(ring, _) => {
+                (None, None, Some(ring.iter()), None, None)
+            }
+            RowStore::Packed(packed) => {
+                (None, None, None, Some(packed.iter()), None)
+            }
+            RowStore::Shared { .. } | RowStore::Columns(_) => (
+                None,
+                None,
+                None,
+                None,
+                Some((0..self.len()).filter_map(|i| self.get(i))),
+            ),
+        };
+        plain
+            .into_iter()
+            .flatten()
+            .chain(runs.into_iter().flatten())
+            .chain(fixed.into_iter().flatten())
+            .map(RowRef::Borrowed)
+            .chain(packed.into_iter().flatten().map(RowRef::Owned))
+            .chain(indexed.into_iter().flatten())
+    }
+}
+
+impl<T: Copy, const N: usize> Clone for RowStore<T, N> {
+    fn clone(&self) -> Self {
+        match self {
+            RowStore::Plain(rows) => RowStore::Plain(rows.clone()),
+            RowStore::Runs { runs, len } => RowStore::Runs {
+                runs: runs.clone(),
+                len: *len,
+            },
+            RowStore::Fixed(ring, clone) => {
+                RowStore::Fixed(clone(ring), *clone)
+            }
+            RowStore::Shared { chunks, head, len } => RowStore::Shared {
+                chunks: chunks.clone(),
+                head: *head,
+                len: *len,
+            },
+            RowStore::Columns(columns) => RowStore::Columns(columns.clone()),
+            RowStore::Packed(packed) => RowStore::Packed(packed.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::RowInfo;
+
+    fn row(left_wall: u8) -> TunnelWalls<u8> {
+        TunnelWalls {
+            left_wall,
+            gap_to_right_wall: 3,
+            pillar: None,
+            info: RowInfo::default(),
+        }
+    }
+
+    #[test]
+    fn runs_collapse_identical_rows() {
+        let mut store = RowStore::<u8, 0>::with_capacity(true, 0);
+        for left_wall in [1, 1, 1, 2, 2, 1] {
+            store.push_back(row(left_wall));
+        }
+        assert_eq!(store.len(), 6);
+        assert_eq!(store.stored(), 3);
+        let walls: Vec<_> = store.iter().map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![1, 1, 1, 2, 2, 1]);
+        assert_eq!(store.get(4).map(|w| w.left_wall), Some(2));
+        assert_eq!(store.back().map(|w| w.left_wall), Some(1));
+        assert!(store.get(6).is_none());
+
+        assert_eq!(store.pop_front().map(|w| w.left_wall), Some(1));
+        assert_eq!((store.len(), store.stored()), (5, 3));
+    }
+
+    #[test]
+    fn fixed_ring_wraps_and_drops_oldest_when_full() {
+        let mut store = RowStore::<u8, 3>::fixed();
+        assert_eq!(store.capacity(), 3);
+        for left_wall in 1..=4 {
+            store.push_back(row(left_wall));
+        }
+        let walls: Vec<_> = store.iter().map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![2, 3, 4]);
+        assert_eq!(store.back().map(|w| w.left_wall), Some(4));
+        assert_eq!(store.pop_front().map(|w| w.left_wall), Some(2));
+        store.get_mut(1).unwrap().left_wall = 7;
+        let walls: Vec<_> = store.iter().rev().map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![7, 3]);
+        assert!(store.get(2).is_none());
+        store.pop_front();
+        store.pop_front();
+        assert!(store.pop_front().is_none());
+        assert!(store.back().is_none());
+    }
+
+    #[test]
+    fn shared_clone_copies_only_the_written_chunk() {
+        let mut store = RowStore::<u8, 0>::shared();
+        for left_wall in 0..70 {
+            store.push_back(row(left_wall));
+        }
+        store.pop_front();
+        let mut fork = store.clone();
+        let RowStore::Shared { chunks, .. } = &store else {
+            unreachable!()
+        };
+        let before: Vec<_> = chunks.iter().map(Arc::as_ptr).collect();
+
+        fork.get_mut(40).unwrap().left_wall = 99;
+        fork.push_back(row(70));
+        let RowStore::Shared { chunks, .. } = &fork else {
+            unreachable!()
+        };
+        let after: Vec<_> = chunks.iter().map(Arc::as_ptr).collect();
+        assert_eq!(after[0], before[0]);
+        assert_ne!(after[1], before[1]);
+        assert_ne!(after[2], before[2]);
+
+        assert_eq!(store.get(40).map(|w| w.left_wall), Some(41));
+        assert_eq!(fork.get(40).map(|w| w.left_wall), Some(99));
+        assert_eq!((store.len(), fork.len()), (69, 70));
+        let walls: Vec<_> =
+            fork.iter().rev().take(2).map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![70, 69]);
+        while fork.pop_front().is_some() {}
+        assert_eq!((fork.len(), fork.stored()), (0, 0));
+        assert!(fork.back().is_none());
+    }
+
+    #[test]
+    fn columns_write_back_when_the_borrow_ends() {
+        let mut store = RowStore::<u8, 0>::columns(4);
+        for left_wall in 1..=4 {
+            store.push_back(row(left_wall));
+        }
+        {
+            let mut second = store.get_mut(1).unwrap();
+            second.left_wall = 9;
+            second.info.key = Some(3);
+        }
+        let walls: Vec<_> = store.iter().map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![1, 9, 3, 4]);
+        assert_eq!(store.get(1).and_then(|w| w.info.key), Some(3));
+        assert_eq!(store.pop_front().map(|w| w.left_wall), Some(1));
+        assert_eq!(store.back().map(|w| w.left_wall), Some(4));
+        assert!(store.get_mut(3).is_none());
+    }
+
+    #[test]
+    fn packed_rows_round_trip_and_share_extras() {
+        let mut store = RowStore::<u64, 0>::packed(4);
+        for left_wall in [1, 2, 3, 4] {
+            store.push_back(TunnelWalls {
+                left_wall,
+                gap_to_right_wall: 200,
+                pillar: Some((100, 3)),
+                info: RowInfo::default(),
+            });
+        }
+        assert_eq!(store.stored(), 1);
+        store.get_mut(2).unwrap().info.key = Some(5);
+        store.get_mut(3).unwrap().left_wall = 250;
+        assert_eq!(store.stored(), 3);
+        let walls: Vec<_> = store.iter().map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![1, 2, 3, 250]);
+        let keys: Vec<_> = store.iter().map(|w| w.info.key).collect();
+        assert_eq!(keys, vec![None, None, Some(5), None]);
+        assert_eq!(store.back().map(|w| w.pillar), Some(Some((100, 3))));
+        assert_eq!(store.pop_front().map(|w| w.gap_to_right_wall), Some(200));
+        store.pop_front();
+        assert_eq!(store.stored(), 2);
+    }
+
+    #[test]
+    fn get_mut_splits_only_the_touched_row() {
+        let mut store = RowStore::<u8, 0>::with_capacity(true, 0);
+        for _ in 0..5 {
+            store.push_back(row(1));
+        }
+        store.get_mut(2).unwrap().left_wall = 4;
+        let walls: Vec<_> = store.iter().map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![1, 1, 4, 1, 1]);
+        assert_eq!(store.stored(), 3);
+        store.front_mut().unwrap().left_wall = 0;
+        let walls: Vec<_> = store.iter().map(|w| w.left_wall).collect();
+        assert_eq!(walls, vec![0, 1, 4, 1, 1]);
+        assert!(store.get_mut(5).is_none());
+    }
+}
@@ -0,0 +1,283 @@
+// Short shareable codes for a run: seed, dimensions and difficulty packed
+// into bytes, guarded by a checksum, and spelled in Crockford base32 so a
+// code survives being read aloud or retyped (case, `I`/`L`/`O` confusion
+// and `-` separators are all forgiven by decode()).
+
+use crate::difficulty::Difficulty;
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+const VERSION: u8 = 1;
+const PAYLOAD_BYTES: usize = 15;
+const CODE_CHARS: usize = PAYLOAD_BYTES * 8 / 5;
+const GROUP_CHARS: usize = 4;
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunConfig {
+    pub rows: u16,
+    pub cols: u16,
+    pub difficulty: Difficulty,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunCode {
+    pub seed: u64,
+    pub config: RunConfig,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RunCodeError {
+    InvalidLength(usize),
+    InvalidChar(char),
+    BadChecksum,
+    UnsupportedVersion(u8),
+    UnknownDifficulty(u8),
+}
+
+impl fmt::Display for RunCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunCodeError::InvalidLength(n) => {
+                write!(f, "expected {CODE_CHARS} characters, found {n}")
+            }
+            RunCodeError::InvalidChar(c) => {
+                write!(f, "invalid character {c:?}")
+            }
+            RunCodeError::BadChecksum => write!(f, "checksum mismatch"),
+            RunCodeError::UnsupportedVersion(v) => {
+                write!(f, "unsupported code version {v}")
+            }
+            RunCodeError::UnknownDifficulty(d) => {
+                write!(f, "unknown difficulty {d}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for RunCodeError {}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0x5a, |acc: u8, b| {
+        acc.rotate_left(3).wrapping_add(*b) ^ 0xa5
+    })
+}
+
+fn decode_char(c: char) -> Result<u8, RunCodeError> {
+    let c = match c.to_ascii_uppercase() {
+        'O' => '0',
+        'I' | 'L' => '1',
+        c => c,
+    };
+    match ALPHABET.iter().position(|a| char::from(*a) == c) {
+        Some(i) => Ok(i as u8),
+        None => Err(RunCodeError::InvalidChar(c)),
+    }
+}
+
+fn encode_bytes(bytes: &[u8]) -> String {
+    let mut code = String::new();
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for b in bytes {
+        acc = (acc << 8) | u32::from(*b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            if !code.is_empty() && code.len() % (GROUP_CHARS + 1) == 4 {
+                code.push('-');
+            }
+            code.push(char::from(ALPHABET[((acc >> bits) & 31) as usize]));
+        }
+    }
+    code
+}
+
+// the size and difficulty every daily challenge is played at, whatever
+// the settings
+pub const DAILY: RunConfig = RunConfig {
+    rows: 23,
+    cols: 80,
+    difficulty: Difficulty::Normal,
+};
+
+impl RunCode {
+    // the daily challenge for the UTC day `secs` after the Unix epoch
+    // falls on: the same tunnel for everyone all day, and another tomorrow
+    pub fn daily(secs: u64) -> RunCode {
+        // splitmix64's finalizer, so neighbouring days look nothing alike
+        let mut z = (secs / 86_400).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        RunCode {
+            seed: z ^ (z >> 31),
+            config: DAILY,
+        }
+    }
+
+    pub fn encode(seed: u64, config: RunConfig) -> String {
+        let mut bytes = Vec::with_capacity(PAYLOAD_BYTES);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&seed.to_be_bytes());
+        bytes.extend_from_slice(&config.rows.to_be_bytes());
+        bytes.extend_from_slice(&config.cols.to_be_bytes());
+        bytes.push(config.difficulty.into());
+        bytes.push(checksum(&bytes));
+        encode_bytes(&bytes)
+    }
+
+    pub fn decode(code: &str) -> Result<RunCode, RunCodeError> {
+        let digits = code
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(decode_char)
+            .collect::<Result<Vec<u8>, RunCodeError>>()?;
+        if digits.len() != CODE_CHARS {
+            return Err(RunCodeError::InvalidLength(digits.len()));
+        }
+
+        let mut bytes = Vec::with_capacity(PAYLOAD_BYTES);
+        let mut acc: u32 = 0;
+        let mut bits = 0;
+        for d in digits {
+            acc = (acc << 5) | u32::from(d);
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((acc >> bits) as u8);
+            }
+        }
+
+        let (payload, sum) = bytes.split_at(PAYLOAD_BYTES - 1);
+        if checksum(payload) != sum[0] {
+            return Err(RunCodeError::BadChecksum);
+        }
+        if payload[0] != VERSION {
+            return Err(RunCodeError::UnsupportedVersion(payload[0]));
+        }
+        let u16_at =
+            |i: usize| u16::from_be_bytes([payload[i], payload[i + 1]]);
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&payload[1..9]);
+        let difficulty = Difficulty::try_from(payload[13])
+            .map_err(|_| RunCodeError::UnknownDifficulty(payload[13]))?;
+        Ok(RunCode {
+            seed: u64::from_be_bytes(seed),
+            config: RunConfig {
+                rows: u16_at(9),
+                cols: u16_at(11),
+                difficulty,
+            },
+        })
+    }
+}
+
+impl fmt::Display for RunCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&RunCode::encode(self.seed, self.config))
+    }
+}
+
+impl core::str::FromStr for RunCode {
+    type Err = RunCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RunCode::decode(s)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    const CONFIG: RunConfig = RunConfig {
+        rows: 40,
+        cols: 120,
+        difficulty: Difficulty::Hard,
+    };
+
+    #[test]
+    fn round_trip() {
+        for seed in [0, 1, 0xdead_beef, u64::MAX] {
+            let code = RunCode::encode(seed, CONFIG);
+            assert_eq!(code.len(), CODE_CHARS + CODE_CHARS / GROUP_CHARS - 1);
+            let decoded = RunCode::decode(&code).unwrap();
+            assert_eq!(
+                decoded,
+                RunCode {
+                    seed,
+                    config: CONFIG
+                }
+            );
+            assert_eq!(decoded.to_string(), code);
+        }
+    }
+
+    #[test]
+    fn daily_changes_at_utc_midnight() {
+        let day = 20_000 * 86_400;
+        assert_eq!(RunCode::daily(day), RunCode::daily(day + 86_399));
+        assert_ne!(RunCode::daily(day).seed, RunCode::daily(day - 1).seed);
+        assert_eq!(RunCode::daily(day).config, DAILY);
+    }
+
+    #[test]
+    fn decode_forgives_case_separators_and_lookalikes() {
+        let code = RunCode::encode(42, CONFIG);
+        let sloppy = code.replace('-', " ").replace('0', "o").to_lowercase();
+        assert_eq!(
+            sloppy.parse(),
+            Ok(RunCode {
+                seed: 42,
+                config: CONFIG
+            })
+        );
+    }
+
+    #[test]
+    fn decode_rejects_damaged_codes() {
+        let code = RunCode::encode(42, CONFIG);
+        assert_eq!(
+            RunCode::decode(&code[1..]),
+            Err(RunCodeError::InvalidLength(CODE_CHARS - 1))
+        );
+        assert_eq!(
+            RunCode::decode(&code.replacen(|c| c != '-', "U", 1)),
+            Err(RunCodeError::InvalidChar('U'))
+        );
+        let first = code.chars().next().unwrap();
+        let typo = if first == '2' { "3" } else { "2" };
+        assert_eq!(
+            RunCode::decode(&code.replacen(first, typo, 1)),
+            Err(RunCodeError::BadChecksum)
+        );
+        assert_eq!(
+            RunCode::decode("").unwrap_err().to_string(),
+            format!("expected {CODE_CHARS} characters, found 0")
+        );
+    }
+
+    #[test]
+    fn decode_rejects_unknown_difficulty() {
+        let mut bytes = vec![VERSION];
+        bytes.extend_from_slice(&[0; PAYLOAD_BYTES - 3]);
+        bytes.push(9);
+        bytes.push(checksum(&bytes));
+        assert_eq!(
+            RunCode::decode(&encode_bytes(&bytes)),
+            Err(RunCodeError::UnknownDifficulty(9))
+        );
+    }
+
+    #[test]
+    fn decode_rejects_unknown_versions() {
+        let mut bytes = vec![VERSION + 1];
+        bytes.extend_from_slice(&[0; PAYLOAD_BYTES - 2]);
+        bytes.push(checksum(&bytes));
+        assert_eq!(
+            RunCode::decode(&encode_bytes(&bytes)),
+            Err(RunCodeError::UnsupportedVersion(VERSION + 1))
+        );
+    }
+}
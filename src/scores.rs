@@ -0,0 +1,368 @@
+// High scores for frontends to keep between runs: the ten best for each
+// difficulty and grid size, one per line as the difficulty, rows x columns,
+// score, initials, date and seed:
+//
+//     normal 23x80 412 ABC 2026-10-16 8112309
+//     hard 23x80 97 ZZ 2026-10-15 5
+//
+// Daily challenges (see runcode::RunCode::daily) have a table for each
+// day, on lines that start with `daily` and have every field, the date
+// being the challenge's, and modes other than endless (see mode) have
+// tables of their own, on lines that start with the mode:
+//
+//     daily normal 23x80 188 ABC 2026-10-16 5316046455853262713
+//     time-attack normal 23x80 1021 ABC 2026-10-16 8112309
+//
+// Lines with only the first three fields have no initials, date or seed
+// (shown as ??? and -). Lines that don't parse are left out, so a damaged
+// file costs only the scores on its damaged lines, and an empty or missing
+// one is an empty table. Frontends decide where the file lives.
+
+use crate::mode::Mode;
+use crate::runcode::RunConfig;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+// places on each difficulty and size's table
+pub const PLACES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub config: RunConfig,
+    pub score: u64,
+    // up to three characters, without spaces
+    pub initials: String,
+    // YYYY-MM-DD (see date()), or - if unknown
+    pub date: String,
+    pub seed: Option<u64>,
+    // on the day's daily challenge table rather than the usual one
+    pub daily: bool,
+    pub mode: Mode,
+}
+
+impl Entry {
+    // the table it's on
+    pub fn board(&self) -> Board<'_> {
+        Board {
+            config: self.config,
+            daily: self.daily.then_some(self.date.as_str()),
+            mode: self.mode,
+        }
+    }
+}
+
+// a table: a difficulty, size and mode, and the day for a daily
+// challenge's
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Board<'a> {
+    pub config: RunConfig,
+    // YYYY-MM-DD
+    pub daily: Option<&'a str>,
+    pub mode: Mode,
+}
+
+impl From<RunConfig> for Board<'_> {
+    fn from(config: RunConfig) -> Self {
+        Board {
+            config,
+            daily: None,
+            mode: Mode::Endless,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HighScores {
+    // best first
+    entries: Vec<Entry>,
+}
+
+// the UTC date `secs` after the Unix epoch, as YYYY-MM-DD
+pub fn date(secs: u64) -> String {
+    // civil_from_days, from Howard Hinnant's date algorithms
+    let z = secs / 86_400 + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = era * 400 + yoe + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn parse_line(line: &str) -> Option<Entry> {
+    let (daily, line) = match line.trim_start().strip_prefix("daily ") {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (mode, line) = match line.split_once(' ') {
+        Some((mode, rest)) => match mode.parse::<Mode>() {
+            Ok(mode) => (mode, rest),
+            Err(_) => (Mode::Endless, line),
+        },
+        None => (Mode::Endless, line),
+    };
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (difficulty, size, score, rest) = match fields[..] {
+        [difficulty, size, score] => (difficulty, size, score, None),
+        [difficulty, size, score, initials, date, seed] => {
+            (difficulty, size, score, Some((initials, date, seed)))
+        }
+        _ => return None,
+    };
+    let (rows, cols) = size.split_once('x')?;
+    let config = RunConfig {
+        rows: rows.parse().ok()?,
+        cols: cols.parse().ok()?,
+        difficulty: difficulty.parse().ok()?,
+    };
+    let (initials, date, seed) = match rest {
+        Some((initials, _, _)) if initials.chars().count() > 3 => return None,
+        // a daily score belongs to a day
+        Some((_, "-", _)) if daily => return None,
+        Some((initials, date, "-")) => (initials, date, None),
+        Some((initials, date, seed)) => {
+            (initials, date, Some(seed.parse().ok()?))
+        }
+        None if daily => return None,
+        None => ("???", "-", None),
+    };
+    Some(Entry {
+        config,
+        score: score.parse().ok()?,
+        initials: initials.to_string(),
+        date: date.to_string(),
+        seed,
+        daily,
+        mode,
+    })
+}
+
+impl HighScores {
+    pub fn parse(text: &str) -> HighScores {
+        let mut scores = HighScores::default();
+        for entry in text.lines().filter_map(parse_line) {
+            scores.insert(entry);
+        }
+        scores
+    }
+
+    // a table (or, from a RunConfig, its usual table), best first
+    pub fn board<'a>(
+        &'a self,
+        board: impl Into<Board<'a>>,
+    ) -> impl Iterator<Item = &'a Entry> {
+        let board = board.into();
+        self.entries.iter().filter(move |e| e.board() == board)
+    }
+
+    pub fn best<'a>(&'a self, board: impl Into<Board<'a>>) -> Option<u64> {
+        self.board(board).next().map(|e| e.score)
+    }
+
+    // the place (0 is best) `score` would take on a table, if it makes the
+    // table at all; a score of 0 never does, and a tie goes under the
+    // scores already there
+    pub fn place<'a>(
+        &'a self,
+        board: impl Into<Board<'a>>,
+        score: u64,
+    ) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        let place = self.board(board).take_while(|e| e.score >= score).count();
+        (place < PLACES).then_some(place)
+    }
+
+    // puts `entry` on its table, dropping whatever it pushes off the end,
+    // and says where it went
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        let place = self.place(entry.board(), entry.score)?;
+        let at = self.entries.partition_point(|e| e.score >= entry.score);
+        self.entries.insert(at, entry);
+        let board = self.entries[at].board();
+        let off = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.board() == board)
+            .nth(PLACES)
+            .map(|(i, _)| i);
+        if let Some(i) = off {
+            self.entries.remove(i);
+        }
+        Some(place)
+    }
+
+    // every table's scores, best first
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for e in self.iter() {
+            let seed = match e.seed {
+                Some(seed) => seed.to_string(),
+                None => String::from("-"),
+            };
+            let daily = match e.daily {
+                true => "daily ",
+                false => "",
+            };
+            let mode = match e.mode {
+                Mode::Endless => String::new(),
+                mode => format!("{mode} "),
+            };
+            let _ = writeln!(
+                text,
+                "{daily}{mode}{} {}x{} {} {} {} {seed}",
+                e.config.difficulty,
+                e.config.rows,
+                e.config.cols,
+                e.score,
+                e.initials,
+                e.date
+            );
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    fn config(difficulty: Difficulty, rows: u16) -> RunConfig {
+        RunConfig {
+            rows,
+            cols: 80,
+            difficulty,
+        }
+    }
+
+    fn entry(config: RunConfig, score: u64) -> Entry {
+        Entry {
+            config,
+            score,
+            initials: String::from("ABC"),
+            date: String::from("2026-10-16"),
+            seed: Some(score),
+            daily: false,
+            mode: Mode::Endless,
+        }
+    }
+
+    #[test]
+    fn tables_keep_the_ten_best_apiece() {
+        let mut scores = HighScores::default();
+        let normal = config(Difficulty::Normal, 23);
+        assert_eq!(scores.place(normal, 0), None);
+        assert_eq!(scores.insert(entry(normal, 40)), Some(0));
+        assert_eq!(scores.insert(entry(normal, 40)), Some(1));
+        assert_eq!(
+            scores.insert(entry(config(Difficulty::Hard, 23), 70)),
+            Some(0)
+        );
+        for score in 50..58 {
+            assert_eq!(scores.insert(entry(normal, score)), Some(0));
+        }
+        assert_eq!(scores.place(normal, 40), None);
+        assert_eq!(scores.insert(entry(normal, 45)), Some(8));
+        assert_eq!(scores.best(normal), Some(57));
+        let table: Vec<u64> = scores.board(normal).map(|e| e.score).collect();
+        assert_eq!(table, [57, 56, 55, 54, 53, 52, 51, 50, 45, 40]);
+        assert_eq!(scores.iter().count(), PLACES + 1);
+    }
+
+    #[test]
+    fn text_round_trips_and_damage_stays_local() {
+        let mut scores = HighScores::default();
+        scores.insert(entry(config(Difficulty::Normal, 23), 412));
+        let mut hard = entry(config(Difficulty::Hard, 23), 97);
+        hard.seed = None;
+        scores.insert(hard);
+        let text = scores.to_text();
+        assert_eq!(
+            text,
+            "normal 23x80 412 ABC 2026-10-16 412\nhard 23x80 97 ABC 2026-10-16 -\n"
+        );
+        assert_eq!(HighScores::parse(&text), scores);
+
+        let damaged = "normal 23x80 412 ABC 2026-10-16 7\nhard 23x 97\n\
+                       \u{0}\u{1}garbage\nchill 10x10\nnormal 23x80 12 ABCD - -\n\
+                       normal 23x80 50\n";
+        let parsed = HighScores::parse(damaged);
+        let normal: Vec<_> =
+            parsed.board(config(Difficulty::Normal, 23)).collect();
+        assert_eq!(normal.len(), 2);
+        assert_eq!((normal[0].score, normal[0].seed), (412, Some(7)));
+        assert_eq!(
+            (normal[1].initials.as_str(), normal[1].seed),
+            ("???", None)
+        );
+        assert_eq!(HighScores::parse(""), HighScores::default());
+    }
+
+    #[test]
+    fn daily_tables_are_kept_apart_by_day() {
+        let normal = config(Difficulty::Normal, 23);
+        let mut scores = HighScores::default();
+        scores.insert(entry(normal, 10));
+        let mut today = entry(normal, 5);
+        today.daily = true;
+        let mut yesterday = today.clone();
+        yesterday.date = String::from("2026-10-15");
+        yesterday.score = 50;
+        assert_eq!(scores.insert(today.clone()), Some(0));
+        assert_eq!(scores.insert(yesterday), Some(0));
+        let day = Board {
+            config: normal,
+            daily: Some("2026-10-16"),
+            mode: Mode::Endless,
+        };
+        assert_eq!(scores.best(day), Some(5));
+        assert_eq!(scores.best(normal), Some(10));
+        assert_eq!(scores.place(day, 6), Some(0));
+
+        let text = scores.to_text();
+        assert!(text.contains("\ndaily normal 23x80 5 ABC 2026-10-16 5\n"));
+        assert_eq!(HighScores::parse(&text), scores);
+        // a daily line needs its day
+        let undated = "daily normal 23x80 5\ndaily normal 23x80 5 ABC - 5\n";
+        assert_eq!(HighScores::parse(undated), HighScores::default());
+    }
+
+    #[test]
+    fn modes_have_tables_of_their_own() {
+        let normal = config(Difficulty::Normal, 23);
+        let mut scores = HighScores::default();
+        scores.insert(entry(normal, 10));
+        let mut timed = entry(normal, 300);
+        timed.mode = Mode::TimeAttack;
+        assert_eq!(scores.insert(timed), Some(0));
+        assert_eq!(scores.best(normal), Some(10));
+        let board = Board {
+            mode: Mode::TimeAttack,
+            ..normal.into()
+        };
+        assert_eq!(scores.best(board), Some(300));
+        let text = scores.to_text();
+        assert!(text.starts_with("time-attack normal 23x80 300 ABC"));
+        assert_eq!(HighScores::parse(&text), scores);
+    }
+
+    #[test]
+    fn dates_are_utc_days() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_700_000_000), "2023-11-14");
+    }
+}
@@ -0,0 +1,63 @@
+// A self-contained random builder: the same seed gives the same tunnel on
+// every platform, with no dependency on `rand`, for bindings and headless
+// runs that need reproducible levels.
+
+use crate::{TunnelBuilder, TunnelBuilderChoice, TunnelIndex};
+
+#[derive(Clone)]
+pub struct Seeded {
+    state: u64,
+}
+
+impl Seeded {
+    pub fn new(seed: u64) -> Seeded {
+        Seeded { state: seed }
+    }
+
+    // splitmix64
+    pub(crate) fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl TunnelBuilder for Seeded {
+    fn choose_player_start<T: TunnelIndex>(&mut self, max: T) -> T {
+        max / 2.into()
+    }
+
+    fn choose_step(&mut self) -> TunnelBuilderChoice {
+        match self.next() % 20 {
+            0 => TunnelBuilderChoice::SplitCorridor,
+            1 => TunnelBuilderChoice::MergeCorridor,
+            2 => TunnelBuilderChoice::SteerPillarLeft,
+            3 => TunnelBuilderChoice::SteerPillarRight,
+            n if n % 2 == 0 => TunnelBuilderChoice::MoveLeftWall,
+            _ => TunnelBuilderChoice::MoveRightWall,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::Tunnel;
+
+    #[test]
+    fn same_seed_same_tunnel() {
+        let run = |seed| {
+            let mut b = Seeded::new(seed);
+            let mut t = Tunnel::<u16>::new(&mut b, 20, 30);
+            for _ in 0..50 {
+                t.step(&mut b);
+            }
+            t
+        };
+        assert!(run(7).iter().eq(run(7).iter()));
+        assert!(!run(7).iter().eq(run(8).iter()));
+    }
+}
@@ -0,0 +1,639 @@
+// Remote play over TCP. Every message is a big-endian u32 length and then
+// that many bytes: an opcode and its fields, all integers big-endian.
+//
+//   create   0x01 rows:u16 cols:u16 seed:u64 difficulty:u8 -> session
+//   input    0x02 id:u64 action:u8 (sim::Action::code()) -> frame
+//   frame    0x03 id:u64 -> frame
+//   close    0x04 id:u64 -> closed
+//
+//   session  0x81 id:u64
+//   frame    0x82 score:u64 crashed:u8 player:u16 rows:u16 cols:u16
+//                 cells:[u8; rows * cols] (TunnelCellType::code()s)
+//   closed   0x83
+//   error    0xff ProtocolError::code():u8
+//
+// Each session is an env::Env; input steps it once, after the move. A
+// session answers only to the connection that created it, and closes when
+// that connection does.
+
+use crate::difficulty::Difficulty;
+use crate::env::{Env, EnvConfig};
+use crate::sim::Action;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::vec::Vec;
+
+// larger tunnels are refused, to bound the memory one request can claim
+pub const MAX_SIDE: u16 = 1024;
+// the longest request, create; anything longer isn't one
+pub const MAX_REQUEST: u32 = 14;
+pub const MAX_RESPONSE: u32 = 4 + 17 + MAX_SIDE as u32 * MAX_SIDE as u32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    Create {
+        rows: u16,
+        cols: u16,
+        seed: u64,
+        difficulty: u8,
+    },
+    Input {
+        id: u64,
+        action: u8,
+    },
+    Frame {
+        id: u64,
+    },
+    Close {
+        id: u64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    Session {
+        id: u64,
+    },
+    Frame {
+        score: u64,
+        crashed: bool,
+        player: u16,
+        rows: u16,
+        cols: u16,
+        cells: Vec<u8>,
+    },
+    Closed,
+    Error(ProtocolError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProtocolError {
+    Malformed,
+    UnknownSession,
+    BadArgument,
+    TooManySessions,
+}
+
+impl ProtocolError {
+    pub fn code(self) -> u8 {
+        match self {
+            ProtocolError::Malformed => 1,
+            ProtocolError::UnknownSession => 2,
+            ProtocolError::BadArgument => 3,
+            ProtocolError::TooManySessions => 4,
+        }
+    }
+
+    pub(crate) fn from_code(code: u8) -> Option<ProtocolError> {
+        Some(match code {
+            1 => ProtocolError::Malformed,
+            2 => ProtocolError::UnknownSession,
+            3 => ProtocolError::BadArgument,
+            4 => ProtocolError::TooManySessions,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProtocolError::Malformed => "malformed message",
+            ProtocolError::UnknownSession => "unknown session",
+            ProtocolError::BadArgument => "argument out of range",
+            ProtocolError::TooManySessions => "too many sessions",
+        })
+    }
+}
+
+impl core::error::Error for ProtocolError {}
+
+// reads big-endian fields off the front of a message
+pub(crate) struct Fields<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Fields<'a> {
+    pub(crate) fn take<const N: usize>(
+        &mut self,
+    ) -> Result<[u8; N], ProtocolError> {
+        let (head, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(ProtocolError::Malformed)?;
+        self.0 = rest;
+        Ok(*head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, ProtocolError> {
+        Ok(u16::from_be_bytes(self.take()?))
+    }
+
+    #[cfg(feature = "net")]
+    pub(crate) fn u32(&mut self) -> Result<u32, ProtocolError> {
+        Ok(u32::from_be_bytes(self.take()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, ProtocolError> {
+        Ok(u64::from_be_bytes(self.take()?))
+    }
+
+    #[cfg(feature = "net")]
+    pub(crate) fn bytes(
+        &mut self,
+        n: usize,
+    ) -> Result<&'a [u8], ProtocolError> {
+        let (head, rest) =
+            self.0.split_at_checked(n).ok_or(ProtocolError::Malformed)?;
+        self.0 = rest;
+        Ok(head)
+    }
+
+    pub(crate) fn end(self) -> Result<(), ProtocolError> {
+        match self.0 {
+            [] => Ok(()),
+            _ => Err(ProtocolError::Malformed),
+        }
+    }
+}
+
+impl Request {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match *self {
+            Request::Create {
+                rows,
+                cols,
+                seed,
+                difficulty,
+            } => {
+                out.push(0x01);
+                out.extend_from_slice(&rows.to_be_bytes());
+                out.extend_from_slice(&cols.to_be_bytes());
+                out.extend_from_slice(&seed.to_be_bytes());
+                out.push(difficulty);
+            }
+            Request::Input { id, action } => {
+                out.push(0x02);
+                out.extend_from_slice(&id.to_be_bytes());
+                out.push(action);
+            }
+            Request::Frame { id } => {
+                out.push(0x03);
+                out.extend_from_slice(&id.to_be_bytes());
+            }
+            Request::Close { id } => {
+                out.push(0x04);
+                out.extend_from_slice(&id.to_be_bytes());
+            }
+        }
+        out
+    }
+
+    pub fn decode(message: &[u8]) -> Result<Request, ProtocolError> {
+        let mut f = Fields(message);
+        let request = match f.u8()? {
+            0x01 => Request::Create {
+                rows: f.u16()?,
+                cols: f.u16()?,
+                seed: f.u64()?,
+                difficulty: f.u8()?,
+            },
+            0x02 => Request::Input {
+                id: f.u64()?,
+                action: f.u8()?,
+            },
+            0x03 => Request::Frame { id: f.u64()? },
+            0x04 => Request::Close { id: f.u64()? },
+            _ => return Err(ProtocolError::Malformed),
+        };
+        f.end()?;
+        Ok(request)
+    }
+}
+
+impl Response {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Response::Session { id } => {
+                out.push(0x81);
+                out.extend_from_slice(&id.to_be_bytes());
+            }
+            Response::Frame {
+                score,
+                crashed,
+                player,
+                rows,
+                cols,
+                cells,
+            } => {
+                out.push(0x82);
+                out.extend_from_slice(&score.to_be_bytes());
+                out.push(u8::from(*crashed));
+                out.extend_from_slice(&player.to_be_bytes());
+                out.extend_from_slice(&rows.to_be_bytes());
+                out.extend_from_slice(&cols.to_be_bytes());
+                out.extend_from_slice(cells);
+            }
+            Response::Closed => out.push(0x83),
+            Response::Error(e) => {
+                out.push(0xff);
+                out.push(e.code());
+            }
+        }
+        out
+    }
+
+    pub fn decode(message: &[u8]) -> Result<Response, ProtocolError> {
+        let mut f = Fields(message);
+        let response = match f.u8()? {
+            0x81 => Response::Session { id: f.u64()? },
+            0x82 => {
+                let score = f.u64()?;
+                let crashed = f.u8()? != 0;
+                let player = f.u16()?;
+                let rows = f.u16()?;
+                let cols = f.u16()?;
+                let cells = f.0.to_vec();
+                if cells.len() != usize::from(rows) * usize::from(cols) {
+                    return Err(ProtocolError::Malformed);
+                }
+                return Ok(Response::Frame {
+                    score,
+                    crashed,
+                    player,
+                    rows,
+                    cols,
+                    cells,
+                });
+            }
+            0x83 => Response::Closed,
+            0xff => Response::Error(
+                ProtocolError::from_code(f.u8()?)
+                    .ok_or(ProtocolError::Malformed)?,
+            ),
+            _ => return Err(ProtocolError::Malformed),
+        };
+        f.end()?;
+        Ok(response)
+    }
+}
+
+// reads one message of at most `limit` bytes: MAX_REQUEST on the server,
+// MAX_RESPONSE on a client
+pub fn read_message(r: &mut impl Read, limit: u32) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            ProtocolError::Malformed,
+        ));
+    }
+    let mut message = vec![0; len as usize];
+    r.read_exact(&mut message)?;
+    Ok(message)
+}
+
+pub fn write_message(w: &mut impl Write, message: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(message.len())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    w.write_all(&len.to_be_bytes())?;
+    w.write_all(message)?;
+    w.flush()
+}
+
+// the sessions of every connection, each keyed by its id and tagged with
+// the connection that owns it; to any other, the id is unknown
+pub struct Server {
+    sessions: HashMap<u64, (u64, Env)>,
+    next_id: u64,
+    next_connection: u64,
+    max_sessions: usize,
+}
+
+impl Server {
+    pub fn new(max_sessions: usize) -> Server {
+        Server {
+            sessions: HashMap::new(),
+            next_id: 1,
+            next_connection: 1,
+            max_sessions,
+        }
+    }
+
+    pub fn sessions(&self) -> usize {
+        self.sessions.len()
+    }
+
+    // a new connection's key, to pass to handle and then disconnect
+    pub fn connect(&mut self) -> u64 {
+        let connection = self.next_connection;
+        self.next_connection += 1;
+        connection
+    }
+
+    // closes every session the connection still had open
+    pub fn disconnect(&mut self, connection: u64) {
+        self.sessions.retain(|_, (owner, _)| *owner != connection);
+    }
+
+    pub fn handle(&mut self, connection: u64, request: Request) -> Response {
+        match self.try_handle(connection, request) {
+            Ok(response) => response,
+            Err(e) => Response::Error(e),
+        }
+    }
+
+    fn try_handle(
+        &mut self,
+        connection: u64,
+        request: Request,
+    ) -> Result<Response, ProtocolError> {
+        match request {
+            Request::Create {
+                rows,
+                cols,
+                seed,
+                difficulty,
+            } => {
+                let difficulty = Difficulty::ALL
+                    .get(usize::from(difficulty))
+                    .ok_or(ProtocolError::BadArgument)?;
+                if rows > MAX_SIDE || cols > MAX_SIDE {
+                    return Err(ProtocolError::BadArgument);
+                }
+                if self.sessions.len() >= self.max_sessions {
+                    return Err(ProtocolError::TooManySessions);
+                }
+                let config = EnvConfig {
+                    rows,
+                    cols,
+                    tunnel: difficulty.config(),
+                    ..EnvConfig::default()
+                };
+                let id = self.next_id;
+                self.next_id += 1;
+                self.sessions
+                    .insert(id, (connection, Env::new(config, seed)));
+                Ok(Response::Session { id })
+            }
+            Request::Input { id, action } => {
+                let action = Action::from_code(action)
+                    .ok_or(ProtocolError::BadArgument)?;
+                let env = self.session(connection, id)?;
+                env.step(action);
+                Ok(frame(env))
+            }
+            Request::Frame { id } => Ok(frame(self.session(connection, id)?)),
+            Request::Close { id } => {
+                self.session(connection, id)?;
+                self.sessions.remove(&id);
+                Ok(Response::Closed)
+            }
+        }
+    }
+
+    fn session(
+        &mut self,
+        connection: u64,
+        id: u64,
+    ) -> Result<&mut Env, ProtocolError> {
+        match self.sessions.get_mut(&id) {
+            Some((owner, env)) if *owner == connection => Ok(env),
+            _ => Err(ProtocolError::UnknownSession),
+        }
+    }
+}
+
+fn frame(env: &Env) -> Response {
+    let t = env.tunnel();
+    let observation = env.observe();
+    Response::Frame {
+        score: env.steps() as u64,
+        crashed: t.is_collision(),
+        player: t.player(),
+        rows: u16::try_from(observation.rows).unwrap_or(u16::MAX),
+        cols: u16::try_from(observation.cols).unwrap_or(u16::MAX),
+        cells: observation.cells,
+    }
+}
+
+// answers requests until the peer hangs up; a malformed message gets an
+// error response, an oversized one ends the connection. Either way, the
+// sessions it left open go with it.
+pub fn handle_connection(
+    mut stream: TcpStream,
+    server: &Mutex<Server>,
+) -> io::Result<()> {
+    let lock = || server.lock().unwrap_or_else(PoisonError::into_inner);
+    let connection = lock().connect();
+    let result = answer(&mut stream, server, connection);
+    lock().disconnect(connection);
+    result
+}
+
+fn answer(
+    stream: &mut TcpStream,
+    server: &Mutex<Server>,
+    connection: u64,
+) -> io::Result<()> {
+    loop {
+        let message = match read_message(stream, MAX_REQUEST) {
+            Ok(message) => message,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let response = match Request::decode(&message) {
+            Ok(request) => server
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .handle(connection, request),
+            Err(e) => Response::Error(e),
+        };
+        write_message(stream, &response.encode())?;
+    }
+}
+
+// one thread per connection, for as long as the listener accepts
+pub fn serve(listener: TcpListener, server: Arc<Mutex<Server>>) {
+    for stream in listener.incoming().flatten() {
+        let server = Arc::clone(&server);
+        thread::spawn(move || handle_connection(stream, &server));
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let requests = [
+            Request::Create {
+                rows: 20,
+                cols: 30,
+                seed: u64::MAX,
+                difficulty: 2,
+            },
+            Request::Input { id: 7, action: 1 },
+            Request::Frame { id: 7 },
+            Request::Close { id: 7 },
+        ];
+        for r in requests {
+            assert!(r.encode().len() <= MAX_REQUEST as usize);
+            assert_eq!(Request::decode(&r.encode()), Ok(r));
+        }
+        let responses = [
+            Response::Session { id: 3 },
+            Response::Frame {
+                score: 9,
+                crashed: true,
+                player: 4,
+                rows: 1,
+                cols: 2,
+                cells: vec![1, 2],
+            },
+            Response::Closed,
+            Response::Error(ProtocolError::TooManySessions),
+        ];
+        for r in responses {
+            assert_eq!(Response::decode(&r.encode()), Ok(r));
+        }
+        assert_eq!(Request::decode(&[0x03, 0]), Err(ProtocolError::Malformed));
+        assert_eq!(Request::decode(&[]), Err(ProtocolError::Malformed));
+        let mut trailing = Request::Frame { id: 1 }.encode();
+        trailing.push(0);
+        assert_eq!(Request::decode(&trailing), Err(ProtocolError::Malformed));
+    }
+
+    #[test]
+    fn sessions_are_independent_and_bounded() {
+        let mut server = Server::new(2);
+        let me = server.connect();
+        let create = |seed| Request::Create {
+            rows: 12,
+            cols: 9,
+            seed,
+            difficulty: 1,
+        };
+        let Response::Session { id: a } = server.handle(me, create(1)) else {
+            panic!("no session");
+        };
+        let Response::Session { id: b } = server.handle(me, create(1)) else {
+            panic!("no session");
+        };
+        assert_eq!(
+            server.handle(me, create(1)),
+            Response::Error(ProtocolError::TooManySessions)
+        );
+
+        server.handle(me, Request::Input { id: a, action: 1 });
+        let Response::Frame { score, player, .. } =
+            server.handle(me, Request::Frame { id: a })
+        else {
+            panic!("no frame");
+        };
+        let Response::Frame { player: other, .. } =
+            server.handle(me, Request::Frame { id: b })
+        else {
+            panic!("no frame");
+        };
+        assert_eq!((score, player + 1), (1, other));
+
+        assert_eq!(
+            server.handle(me, Request::Close { id: a }),
+            Response::Closed
+        );
+        assert_eq!(
+            server.handle(me, Request::Frame { id: a }),
+            Response::Error(ProtocolError::UnknownSession)
+        );
+        assert_eq!(
+            server.handle(me, Request::Input { id: b, action: 5 }),
+            Response::Error(ProtocolError::BadArgument)
+        );
+        assert_eq!(server.sessions(), 1);
+
+        // another connection can't see b, and b goes when its own does
+        let them = server.connect();
+        assert_eq!(
+            server.handle(them, Request::Close { id: b }),
+            Response::Error(ProtocolError::UnknownSession)
+        );
+        assert!(matches!(
+            server.handle(them, create(2)),
+            Response::Session { .. }
+        ));
+        server.disconnect(me);
+        assert_eq!(server.sessions(), 1);
+    }
+
+    #[test]
+    fn serves_requests_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Mutex::new(Server::new(4)));
+        let serving = Arc::clone(&server);
+        thread::spawn(move || serve(listener, serving));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut call = |request: Request| {
+            write_message(&mut client, &request.encode()).unwrap();
+            let response = read_message(&mut client, MAX_RESPONSE).unwrap();
+            Response::decode(&response).unwrap()
+        };
+        let Response::Session { id } = call(Request::Create {
+            rows: 12,
+            cols: 9,
+            seed: 5,
+            difficulty: 1,
+        }) else {
+            panic!("no session");
+        };
+        let Response::Frame {
+            rows, cols, cells, ..
+        } = call(Request::Input { id, action: 0 })
+        else {
+            panic!("no frame");
+        };
+        assert_eq!(cols, 9);
+        assert_eq!(cells.len(), usize::from(rows) * 9);
+        assert_eq!(call(Request::Close { id }), Response::Closed);
+
+        // hanging up without closing doesn't leave the session behind
+        let Response::Session { .. } = call(Request::Create {
+            rows: 12,
+            cols: 9,
+            seed: 5,
+            difficulty: 1,
+        }) else {
+            panic!("no session");
+        };
+        drop(client);
+        let sessions = || server.lock().unwrap().sessions();
+        for _ in 0..100 {
+            if sessions() == 0 {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(sessions(), 0);
+
+        // nor can a request claim more than a request's worth of memory
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(&(MAX_REQUEST + 1).to_be_bytes()).unwrap();
+        assert!(read_message(&mut client, MAX_RESPONSE).is_err());
+    }
+}
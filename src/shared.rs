@@ -0,0 +1,121 @@
+// Tunnel<T> is Send + Sync, so one thread can step it while others draw it.
+// SharedTunnel packages that: the logic thread keeps the SharedTunnel and
+// writes, render or network threads get a TunnelReader that can only read.
+
+use crate::{Tunnel, TunnelBuilder};
+use std::ops::Deref;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+pub struct SharedTunnel<T> {
+    tunnel: Arc<RwLock<Tunnel<T>>>,
+}
+
+// a handle that can read the tunnel but never step or steer it
+pub struct TunnelReader<T> {
+    tunnel: Arc<RwLock<Tunnel<T>>>,
+}
+
+// a consistent snapshot for the duration of one frame; holding it blocks
+// the writer, so drop it before doing anything slow
+pub struct TunnelView<'a, T> {
+    guard: RwLockReadGuard<'a, Tunnel<T>>,
+}
+
+impl<T> SharedTunnel<T> {
+    pub fn new(tunnel: Tunnel<T>) -> SharedTunnel<T> {
+        SharedTunnel {
+            tunnel: Arc::new(RwLock::new(tunnel)),
+        }
+    }
+
+    pub fn reader(&self) -> TunnelReader<T> {
+        TunnelReader {
+            tunnel: Arc::clone(&self.tunnel),
+        }
+    }
+
+    pub fn read(&self) -> TunnelView<'_, T> {
+        read(&self.tunnel)
+    }
+
+    // a panic while writing leaves the tunnel at worst one step behind, so
+    // a poisoned lock is still usable
+    pub fn write(&self) -> RwLockWriteGuard<'_, Tunnel<T>> {
+        self.tunnel.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: crate::TunnelIndex> SharedTunnel<T> {
+    pub fn step(&self, b: &mut impl TunnelBuilder) {
+        self.write().step(b);
+    }
+}
+
+impl<T> TunnelReader<T> {
+    pub fn read(&self) -> TunnelView<'_, T> {
+        read(&self.tunnel)
+    }
+}
+
+impl<T> Clone for TunnelReader<T> {
+    fn clone(&self) -> Self {
+        TunnelReader {
+            tunnel: Arc::clone(&self.tunnel),
+        }
+    }
+}
+
+impl<T> Deref for TunnelView<'_, T> {
+    type Target = Tunnel<T>;
+
+    fn deref(&self) -> &Tunnel<T> {
+        &self.guard
+    }
+}
+
+fn read<T>(tunnel: &RwLock<Tunnel<T>>) -> TunnelView<'_, T> {
+    TunnelView {
+        guard: tunnel.read().unwrap_or_else(PoisonError::into_inner),
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::fixed::TunnelFixed;
+    use crate::pattern::PatternBuilder;
+    use std::thread;
+
+    fn assert_send_sync<S: Send + Sync>() {}
+
+    #[test]
+    fn tunnels_are_send_and_sync() {
+        assert_send_sync::<Tunnel<u8>>();
+        assert_send_sync::<Tunnel<u64>>();
+        assert_send_sync::<TunnelFixed<u16, 8, 8>>();
+        assert_send_sync::<SharedTunnel<u8>>();
+        assert_send_sync::<TunnelReader<u8>>();
+    }
+
+    #[test]
+    fn reader_sees_steps_from_another_thread() {
+        let mut builder = PatternBuilder::parse(">").unwrap();
+        let shared = SharedTunnel::new(Tunnel::<u8>::new(&mut builder, 8, 16));
+        let reader = shared.reader();
+        let before: Vec<_> = reader.read().iter().collect();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                for _ in 0..3 {
+                    shared.step(&mut builder);
+                }
+                shared.write().move_player_right();
+            });
+        });
+
+        let view = reader.clone().read().iter().collect::<Vec<_>>();
+        assert_ne!(view, before);
+        assert_eq!(shared.read().player(), reader.read().player());
+    }
+}
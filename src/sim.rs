@@ -0,0 +1,299 @@
+// Headless simulation of many independent games at once, for evaluating
+// builders and bots far faster than the terminal loop can. Instances share
+// a config and size; each has its own builder and policy. State is kept as
+// parallel arrays so stepping walks memory in order.
+
+use crate::{Direction, Tunnel, TunnelBuilder, TunnelConfig, TunnelIndex};
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Stay,
+    Left,
+    Right,
+    // Tunnel::dash_player
+    DashLeft,
+    DashRight,
+}
+
+impl Action {
+    // the wire and binding encoding: 0 stays, 1 moves left, 2 moves right,
+    // 3 dashes left, 4 dashes right
+    pub fn code(self) -> u8 {
+        match self {
+            Action::Stay => 0,
+            Action::Left => 1,
+            Action::Right => 2,
+            Action::DashLeft => 3,
+            Action::DashRight => 4,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Action> {
+        Some(match code {
+            0 => Action::Stay,
+            1 => Action::Left,
+            2 => Action::Right,
+            3 => Action::DashLeft,
+            4 => Action::DashRight,
+            _ => return None,
+        })
+    }
+
+    // moves the player; stepping is up to the caller
+    pub fn apply<T: TunnelIndex, const N: usize>(self, t: &mut Tunnel<T, N>) {
+        match self {
+            Action::Stay => {}
+            Action::Left => t.move_player_left(),
+            Action::Right => t.move_player_right(),
+            Action::DashLeft => t.dash_player(Direction::Left),
+            Action::DashRight => t.dash_player(Direction::Right),
+        }
+    }
+}
+
+pub trait Policy<T> {
+    fn choose(&mut self, t: &Tunnel<T>) -> Action;
+}
+
+impl<T, F: FnMut(&Tunnel<T>) -> Action> Policy<T> for F {
+    fn choose(&mut self, t: &Tunnel<T>) -> Action {
+        self(t)
+    }
+}
+
+// steps survived so far, and whether the instance is still playing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Running(usize),
+    Crashed(usize),
+}
+
+impl Outcome {
+    pub fn steps(self) -> usize {
+        match self {
+            Outcome::Running(steps) | Outcome::Crashed(steps) => steps,
+        }
+    }
+
+    pub fn is_running(self) -> bool {
+        matches!(self, Outcome::Running(_))
+    }
+}
+
+pub struct Batch<T, B, P> {
+    tunnels: Vec<Tunnel<T>>,
+    builders: Vec<B>,
+    policies: Vec<P>,
+    outcomes: Vec<Outcome>,
+}
+
+impl<T: TunnelIndex, B: TunnelBuilder, P: Policy<T>> Batch<T, B, P> {
+    pub fn new(
+        rows: T,
+        cols: T,
+        config: &TunnelConfig,
+        instances: impl IntoIterator<Item = (B, P)>,
+    ) -> Batch<T, B, P> {
+        let (mut builders, policies): (Vec<B>, Vec<P>) =
+            instances.into_iter().unzip();
+        let tunnels: Vec<_> = builders
+            .iter_mut()
+            .map(|b| Tunnel::with_config(b, rows, cols, config.clone()))
+            .collect();
+        let outcomes = tunnels
+            .iter()
+            .map(|t| match t.is_collision() {
+                true => Outcome::Crashed(0),
+                false => Outcome::Running(0),
+            })
+            .collect();
+        Batch {
+            tunnels,
+            builders,
+            policies,
+            outcomes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tunnels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tunnels.is_empty()
+    }
+
+    pub fn tunnel(&self, instance: usize) -> Option<&Tunnel<T>> {
+        self.tunnels.get(instance)
+    }
+
+    pub fn outcomes(&self) -> &[Outcome] {
+        &self.outcomes
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.outcomes.iter().any(|o| o.is_running())
+    }
+
+    // one step for every instance still running; crashed ones stay frozen
+    pub fn step_all(&mut self) {
+        let instances = self
+            .tunnels
+            .iter_mut()
+            .zip(&mut self.builders)
+            .zip(&mut self.policies)
+            .zip(&mut self.outcomes);
+        for (((t, b), p), outcome) in instances {
+            step_one(t, b, p, outcome);
+        }
+    }
+
+    // steps until every instance crashed or survived `max_steps` steps
+    pub fn run_to_completion(&mut self, max_steps: usize) -> &[Outcome] {
+        let instances = self
+            .tunnels
+            .iter_mut()
+            .zip(&mut self.builders)
+            .zip(&mut self.policies)
+            .zip(&mut self.outcomes);
+        for (((t, b), p), outcome) in instances {
+            while outcome.is_running() && outcome.steps() < max_steps {
+                step_one(t, b, p, outcome);
+            }
+        }
+        &self.outcomes
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T, B, P> Batch<T, B, P>
+where
+    T: TunnelIndex + Send + Sync,
+    B: TunnelBuilder + Send,
+    P: Policy<T> + Send,
+{
+    // step_all() spread across the rayon thread pool
+    pub fn par_step_all(&mut self) {
+        use rayon::prelude::*;
+        self.tunnels
+            .par_iter_mut()
+            .zip(&mut self.builders)
+            .zip(&mut self.policies)
+            .zip(&mut self.outcomes)
+            .for_each(|(((t, b), p), outcome)| step_one(t, b, p, outcome));
+    }
+
+    // run_to_completion() spread across the rayon thread pool
+    pub fn par_run_to_completion(&mut self, max_steps: usize) -> &[Outcome] {
+        use rayon::prelude::*;
+        self.tunnels
+            .par_iter_mut()
+            .zip(&mut self.builders)
+            .zip(&mut self.policies)
+            .zip(&mut self.outcomes)
+            .for_each(|(((t, b), p), outcome)| {
+                while outcome.is_running() && outcome.steps() < max_steps {
+                    step_one(t, b, p, outcome);
+                }
+            });
+        &self.outcomes
+    }
+}
+
+fn step_one<T: TunnelIndex>(
+    t: &mut Tunnel<T>,
+    b: &mut impl TunnelBuilder,
+    p: &mut impl Policy<T>,
+    outcome: &mut Outcome,
+) {
+    let Outcome::Running(steps) = *outcome else {
+        return;
+    };
+    p.choose(t).apply(t);
+    t.step(b);
+    *outcome = match t.is_collision() {
+        true => Outcome::Crashed(steps + 1),
+        false => Outcome::Running(steps + 1),
+    };
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    type Pick = fn(&Tunnel<u8>) -> Action;
+
+    // the corridor narrows from the right, so only hugging the left survives
+    fn hug_left(t: &Tunnel<u8>) -> Action {
+        if t.player() > 1 {
+            Action::Left
+        } else {
+            Action::Stay
+        }
+    }
+
+    fn stay(_: &Tunnel<u8>) -> Action {
+        Action::Stay
+    }
+
+    fn keep() -> PatternBuilder {
+        PatternBuilder::parse("K").unwrap()
+    }
+
+    #[test]
+    fn batch_reports_each_instance_outcome() {
+        let instances: [(PatternBuilder, Pick); 3] =
+            [(keep(), hug_left), (keep(), stay), (keep(), hug_left)];
+        let config = TunnelConfig::default();
+        let mut batch = Batch::new(10, 12, &config, instances);
+        assert_eq!(batch.len(), 3);
+        assert!(!batch.is_empty());
+
+        let outcomes = batch.run_to_completion(40).to_vec();
+        assert_eq!(outcomes[0], Outcome::Running(40));
+        assert_eq!(outcomes[2], Outcome::Running(40));
+        assert!(!outcomes[1].is_running());
+        assert!(outcomes[1].steps() < 40);
+        assert!(batch.tunnel(1).unwrap().is_collision());
+        assert!(batch.tunnel(3).is_none());
+    }
+
+    #[test]
+    fn batch_matches_single_games() {
+        let mut single_builder = keep();
+        let mut single = Tunnel::<u8>::new(&mut single_builder, 10, 12);
+        let mut batch =
+            Batch::new(10, 12, &TunnelConfig::default(), [(keep(), stay)]);
+        while batch.outcomes()[0].is_running() {
+            single.step(&mut single_builder);
+            batch.step_all();
+            assert!(single.iter().eq(batch.tunnel(0).unwrap().iter()));
+        }
+        assert!(single.is_collision());
+        batch.step_all();
+        assert!(batch.is_finished());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_batch_matches_serial() {
+        let instances = || {
+            (0..64).map(|i| -> (PatternBuilder, Pick) {
+                (keep(), if i % 3 == 0 { stay } else { hug_left })
+            })
+        };
+        let config = TunnelConfig::default();
+        let mut serial = Batch::new(10, 12, &config, instances());
+        let mut parallel = Batch::new(10, 12, &config, instances());
+        serial.step_all();
+        parallel.par_step_all();
+        assert_eq!(serial.outcomes(), parallel.outcomes());
+        assert_eq!(
+            serial.run_to_completion(30),
+            parallel.par_run_to_completion(30)
+        );
+    }
+}
@@ -0,0 +1,272 @@
+// The terminal game over SSH: `ssh play@host` with any user and any
+// credentials gets a game. Every session channel that asks for a shell
+// plays its own ansi::RemoteGame, sized to its pty, stepped by its own task
+// until the player quits or crashes. Serve it with the tunnel-ssh binary.
+
+use crate::ansi::{self, GameSettings, RemoteGame};
+use russh::keys::PrivateKey;
+use russh::server::{self, Auth, Handle, Msg, Session};
+use russh::{Channel, ChannelId, CryptoVec, MethodKind, MethodSet, Pty};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use std::vec::Vec;
+
+// a server config that lets everyone in
+pub fn config(key: PrivateKey) -> server::Config {
+    let methods: &[MethodKind] = &[
+        MethodKind::None,
+        MethodKind::Password,
+        MethodKind::PublicKey,
+    ];
+    server::Config {
+        methods: MethodSet::from(methods),
+        auth_rejection_time: Duration::ZERO,
+        keys: vec![key],
+        inactivity_timeout: Some(Duration::from_secs(3600)),
+        nodelay: true,
+        ..server::Config::default()
+    }
+}
+
+pub struct SshServer {
+    settings: GameSettings,
+    // each connection seeds its games from here, so no two are alike
+    next_seed: u64,
+}
+
+impl SshServer {
+    pub fn new(settings: GameSettings, seed: u64) -> SshServer {
+        SshServer {
+            settings,
+            next_seed: seed,
+        }
+    }
+}
+
+impl server::Server for SshServer {
+    type Handler = Player;
+
+    fn new_client(&mut self, _: Option<std::net::SocketAddr>) -> Player {
+        let seed = self.next_seed;
+        self.next_seed = self.next_seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        Player {
+            settings: self.settings.clone(),
+            seed,
+            sizes: HashMap::new(),
+            games: HashMap::new(),
+        }
+    }
+}
+
+type SharedGame = Arc<Mutex<RemoteGame>>;
+
+fn lock(game: &SharedGame) -> std::sync::MutexGuard<'_, RemoteGame> {
+    game.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// one connection
+pub struct Player {
+    settings: GameSettings,
+    seed: u64,
+    // (cols, rows) from each channel's pty request
+    sizes: HashMap<ChannelId, (u16, u16)>,
+    games: HashMap<ChannelId, SharedGame>,
+}
+
+impl server::Handler for Player {
+    type Error = russh::Error;
+
+    async fn auth_none(&mut self, _: &str) -> Result<Auth, Self::Error> {
+        Ok(Auth::Accept)
+    }
+
+    async fn auth_password(
+        &mut self,
+        _: &str,
+        _: &str,
+    ) -> Result<Auth, Self::Error> {
+        Ok(Auth::Accept)
+    }
+
+    async fn auth_publickey(
+        &mut self,
+        _: &str,
+        _: &russh::keys::PublicKey,
+    ) -> Result<Auth, Self::Error> {
+        Ok(Auth::Accept)
+    }
+
+    async fn channel_open_session(
+        &mut self,
+        _: Channel<Msg>,
+        _: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    async fn pty_request(
+        &mut self,
+        channel: ChannelId,
+        _: &str,
+        cols: u32,
+        rows: u32,
+        _: u32,
+        _: u32,
+        _: &[(Pty, u32)],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.sizes.insert(channel, ansi::fit(cols, rows));
+        session.channel_success(channel)
+    }
+
+    async fn shell_request(
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        // without a pty, assume the classic terminal
+        let (cols, rows) =
+            self.sizes.get(&channel).copied().unwrap_or((80, 24));
+        self.seed = self.seed.wrapping_add(1);
+        let game =
+            RemoteGame::new(rows, cols, self.seed, self.settings.difficulty);
+        let game = Arc::new(Mutex::new(game));
+        self.games.insert(channel, game.clone());
+        session.channel_success(channel)?;
+        tokio::spawn(play(session.handle(), channel, game, self.settings.tick));
+        Ok(())
+    }
+
+    async fn data(
+        &mut self,
+        channel: ChannelId,
+        data: &[u8],
+        _: &mut Session,
+    ) -> Result<(), Self::Error> {
+        if let Some(game) = self.games.get(&channel) {
+            lock(game).input(data);
+        }
+        Ok(())
+    }
+
+    async fn channel_close(
+        &mut self,
+        channel: ChannelId,
+        _: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.sizes.remove(&channel);
+        // play() sees the closed channel on its next send and stops
+        self.games.remove(&channel);
+        Ok(())
+    }
+}
+
+// sends frames until the game is over, then the summary, then hangs up
+async fn play(
+    handle: Handle,
+    channel: ChannelId,
+    game: SharedGame,
+    tick: Duration,
+) {
+    let mut out: Vec<u8> = ansi::ENTER.to_vec();
+    out.extend(lock(&game).frame());
+    loop {
+        if handle.data(channel, CryptoVec::from(out)).await.is_err() {
+            return;
+        }
+        let (over, wait) = {
+            let game = lock(&game);
+            (game.is_over(), game.tick_length(tick))
+        };
+        if over {
+            break;
+        }
+        tokio::time::sleep(wait).await;
+        out = lock(&game).tick();
+    }
+    let mut end = ansi::LEAVE.to_vec();
+    end.extend(lock(&game).summary());
+    let _ = handle.data(channel, CryptoVec::from(end)).await;
+    let _ = handle.exit_status_request(channel, 0).await;
+    let _ = handle.eof(channel).await;
+    let _ = handle.close(channel).await;
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use russh::client;
+    use russh::keys::ssh_key::rand_core::OsRng;
+    use russh::keys::{Algorithm, PublicKey};
+    use russh::server::Server as _;
+    use russh::{ChannelMsg, Disconnect};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    struct Client;
+
+    impl client::Handler for Client {
+        type Error = russh::Error;
+
+        async fn check_server_key(
+            &mut self,
+            _: &PublicKey,
+        ) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn a_session_plays_until_the_player_quits() {
+        let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let settings = GameSettings {
+            tick: Duration::from_millis(5),
+            ..GameSettings::default()
+        };
+        let mut server = SshServer::new(settings, 1);
+        tokio::spawn(async move {
+            server.run_on_socket(Arc::new(config(key)), &listener).await
+        });
+
+        let config = Arc::new(client::Config::default());
+        let mut c = client::connect(config, addr, Client).await.unwrap();
+        assert!(c.authenticate_none("play").await.unwrap().success());
+        let mut channel = c.channel_open_session().await.unwrap();
+        channel
+            .request_pty(true, "xterm", 20, 10, 0, 0, &[])
+            .await
+            .unwrap();
+        channel.request_shell(true).await.unwrap();
+
+        let mut seen = Vec::new();
+        let mut quit = false;
+        let mut status = None;
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { data } => {
+                    seen.extend_from_slice(&data);
+                    // frames are drawn to fit the pty: score on row 10
+                    if !quit && seen.windows(6).any(|w| w == b"\x1b[10;1") {
+                        channel.data(&b"q"[..]).await.unwrap();
+                        quit = true;
+                    }
+                }
+                ChannelMsg::ExitStatus { exit_status } => {
+                    status = Some(exit_status);
+                }
+                _ => {}
+            }
+        }
+        assert!(seen.starts_with(ansi::ENTER));
+        let text = String::from_utf8_lossy(&seen);
+        assert!(text.contains("Quitting ... Final score: "));
+        assert_eq!(status, Some(0));
+        c.disconnect(Disconnect::ByApplication, "", "")
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+// The terminal game for telnet and raw sockets, BBS style: each connection
+// gets its own ansi::RemoteGame on its own thread. Telnet clients are asked
+// to echo nothing, send keys as typed and report their window size (NAWS);
+// the game is sized to the report, or 80x24 if none comes. Plain sockets
+// (nc) never answer, and play the same game.
+
+use crate::ansi::{self, GameSettings, RemoteGame};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31;
+
+// the server echoes (nothing), there are no go-aheads, send your size
+pub const NEGOTIATION: &[u8] =
+    &[IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS];
+
+// how long to wait for a window size before playing 80x24
+pub const NAWS_WAIT: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    Keys(Vec<u8>),
+    // (cols, rows)
+    Size(u16, u16),
+}
+
+#[derive(Clone, Copy, Default)]
+enum State {
+    #[default]
+    Data,
+    Iac,
+    Option,
+    Sub,
+    SubIac,
+}
+
+// Strips telnet commands out of the byte stream, keeping what was typed
+// and the NAWS reports. Like ansi::KeyParser, it carries on where the
+// last read left off.
+#[derive(Default)]
+pub struct TelnetFilter {
+    state: State,
+    sub: Vec<u8>,
+}
+
+impl TelnetFilter {
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Input> {
+        let mut inputs = Vec::new();
+        let mut keys = Vec::new();
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Data, IAC) => State::Iac,
+                (State::Data, _) => {
+                    keys.push(byte);
+                    State::Data
+                }
+                // an escaped 255 is data
+                (State::Iac, IAC) => {
+                    keys.push(byte);
+                    State::Data
+                }
+                (State::Iac, WILL | WONT | DO | DONT) => State::Option,
+                (State::Iac, SB) => {
+                    self.sub.clear();
+                    State::Sub
+                }
+                (State::Iac | State::Option, _) => State::Data,
+                (State::Sub, IAC) => State::SubIac,
+                (State::Sub, _) => {
+                    self.sub.push(byte);
+                    State::Sub
+                }
+                (State::SubIac, SE) => {
+                    if let [NAWS, w0, w1, h0, h1] = self.sub[..] {
+                        // keys typed before the report come before it
+                        if !keys.is_empty() {
+                            inputs
+                                .push(Input::Keys(core::mem::take(&mut keys)));
+                        }
+                        let cols = u16::from_be_bytes([w0, w1]);
+                        let rows = u16::from_be_bytes([h0, h1]);
+                        inputs.push(Input::Size(cols, rows));
+                    }
+                    State::Data
+                }
+                (State::SubIac, _) => {
+                    self.sub.push(byte);
+                    State::Sub
+                }
+            };
+        }
+        if !keys.is_empty() {
+            inputs.push(Input::Keys(keys));
+        }
+        inputs
+    }
+}
+
+// Plays one game on `stream`: negotiates, waits briefly for the window
+// size, then steps the game every tick and steers it as keys arrive.
+pub fn handle_connection(
+    stream: TcpStream,
+    settings: &GameSettings,
+    seed: u64,
+) -> io::Result<()> {
+    let mut out = stream.try_clone()?;
+    out.write_all(NEGOTIATION)?;
+    let (tx, rx) = mpsc::channel();
+    let mut reader = stream;
+    thread::spawn(move || {
+        let mut filter = TelnetFilter::default();
+        let mut buf = [0; 512];
+        while let Ok(n @ 1..) = reader.read(&mut buf) {
+            for input in filter.feed(&buf[..n]) {
+                if tx.send(input).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    let mut size = (80, 24);
+    let deadline = Instant::now() + NAWS_WAIT;
+    let mut early = Vec::new();
+    loop {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(wait) {
+            Ok(Input::Size(cols, rows)) => {
+                size = ansi::fit(cols.into(), rows.into());
+                break;
+            }
+            Ok(Input::Keys(keys)) => early.extend(keys),
+            Err(RecvTimeoutError::Timeout) => break,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+
+    let (cols, rows) = size;
+    let mut game = RemoteGame::new(rows, cols, seed, settings.difficulty);
+    game.input(&early);
+    out.write_all(ansi::ENTER)?;
+    out.write_all(&game.frame())?;
+    let mut next = Instant::now() + game.tick_length(settings.tick);
+    while !game.is_over() {
+        let wait = next.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(wait) {
+            Ok(Input::Keys(keys)) => {
+                game.input(&keys);
+            }
+            // the tunnel keeps its size once made
+            Ok(Input::Size(..)) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                out.write_all(&game.tick())?;
+                next = Instant::now() + game.tick_length(settings.tick);
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+    out.write_all(ansi::LEAVE)?;
+    out.write_all(&game.summary())?;
+    out.shutdown(Shutdown::Both)
+}
+
+// One thread per connection, at most `max_players` at once; the rest are
+// told so and hung up on.
+pub fn serve(
+    listener: TcpListener,
+    settings: GameSettings,
+    max_players: usize,
+    seed: u64,
+) {
+    let settings = Arc::new(settings);
+    let players = Arc::new(AtomicUsize::new(0));
+    for (n, stream) in listener.incoming().enumerate() {
+        let Ok(mut stream) = stream else { continue };
+        if players.fetch_add(1, Ordering::SeqCst) >= max_players {
+            players.fetch_sub(1, Ordering::SeqCst);
+            let _ = stream.write_all(b"Too many players, try again later.\r\n");
+            continue;
+        }
+        let settings = settings.clone();
+        let players = players.clone();
+        let seed = seed.wrapping_add(n as u64);
+        thread::spawn(move || {
+            let _ = handle_connection(stream, &settings, seed);
+            players.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_strips_commands_and_reads_window_sizes() {
+        let mut f = TelnetFilter::default();
+        let mut bytes = vec![b'a', IAC, WILL, NAWS, IAC, SB, NAWS, 0, 100];
+        bytes.extend([0, IAC, IAC, IAC, SE, b'q', IAC, IAC]);
+        assert_eq!(
+            f.feed(&bytes[..10]),
+            [Input::Keys(vec![b'a'])],
+            "the subnegotiation is still open"
+        );
+        assert_eq!(
+            f.feed(&bytes[10..]),
+            [Input::Size(100, 255), Input::Keys(vec![b'q', IAC])]
+        );
+        assert_eq!(f.feed(b"\x1b[D"), [Input::Keys(b"\x1b[D".to_vec())]);
+    }
+
+    #[test]
+    fn a_connection_plays_until_the_player_quits() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let settings = GameSettings {
+            tick: Duration::from_millis(5),
+            ..GameSettings::default()
+        };
+        thread::spawn(move || serve(listener, settings, 1, 3));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut negotiation = [0; NEGOTIATION.len()];
+        client.read_exact(&mut negotiation).unwrap();
+        assert_eq!(negotiation, NEGOTIATION);
+        client
+            .write_all(&[IAC, SB, NAWS, 0, 20, 0, 10, IAC, SE])
+            .unwrap();
+
+        // a second player is turned away while the first plays
+        let mut refused = String::new();
+        let mut other = TcpStream::connect(addr).unwrap();
+        other.read_to_string(&mut refused).unwrap();
+        assert!(refused.starts_with("Too many players"));
+
+        let mut seen = Vec::new();
+        let mut buf = [0; 4096];
+        // sized to the report: the score is on row 10
+        while !seen.windows(6).any(|w| w == b"\x1b[10;1") {
+            let n = client.read(&mut buf).unwrap();
+            seen.extend_from_slice(&buf[..n]);
+        }
+        client.write_all(b"q").unwrap();
+        client.read_to_end(&mut seen).unwrap();
+        let text = String::from_utf8_lossy(&seen);
+        assert!(text.contains("Quitting ... Final score: "));
+    }
+}
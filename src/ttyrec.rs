@@ -0,0 +1,37 @@
+// ttyrec recordings: every chunk of terminal output as a 12-byte header
+// (seconds, microseconds, length; little-endian u32s) and the bytes
+// themselves, for ttyplay and the other standard players. Unlike cast, the
+// bytes are whatever the frontend wrote, escape sequences and all.
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+// `at` is conventionally the time since the Unix epoch; players only look
+// at the differences. Chunks over 4 GiB are cut short.
+pub fn record(at: Duration, data: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(data.len()).unwrap_or(u32::MAX);
+    let secs = u32::try_from(at.as_secs()).unwrap_or(u32::MAX);
+    let mut out = Vec::with_capacity(12 + data.len());
+    out.extend_from_slice(&secs.to_le_bytes());
+    out.extend_from_slice(&at.subsec_micros().to_le_bytes());
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(&data[..len as usize]);
+    out
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_is_seconds_micros_and_length() {
+        let at = Duration::new(1_700_000_000, 250_000_000);
+        let out = record(at, b"\x1b[2Jhi");
+        assert_eq!(out[..4], 1_700_000_000_u32.to_le_bytes());
+        assert_eq!(out[4..8], 250_000_u32.to_le_bytes());
+        assert_eq!(out[8..12], 6_u32.to_le_bytes());
+        assert_eq!(&out[12..], b"\x1b[2Jhi");
+        assert_eq!(record(Duration::ZERO, b"").len(), 12);
+    }
+}
@@ -0,0 +1,140 @@
+// wasm-bindgen bindings: a Tunnel<u16> grown by a Seeded builder, exported
+// to JS as `Tunnel`. cells() hands back the whole screen as one Uint8Array
+// of TunnelCellType::code()s, row-major, so a frame costs one copy across
+// the boundary.
+
+use crate::Tunnel;
+use crate::seeded::Seeded;
+use std::vec::Vec;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(js_name = Tunnel)]
+pub struct WasmTunnel {
+    tunnel: Tunnel<u16>,
+    builder: Seeded,
+}
+
+#[wasm_bindgen(js_class = Tunnel)]
+impl WasmTunnel {
+    #[wasm_bindgen(constructor)]
+    pub fn new(rows: u16, cols: u16, seed: u64) -> WasmTunnel {
+        let mut builder = Seeded::new(seed);
+        let tunnel = Tunnel::new(&mut builder, rows, cols);
+        WasmTunnel { tunnel, builder }
+    }
+
+    pub fn step(&mut self) {
+        self.tunnel.step(&mut self.builder);
+    }
+
+    #[wasm_bindgen(js_name = moveLeft)]
+    pub fn move_left(&mut self) {
+        self.tunnel.move_player_left();
+    }
+
+    #[wasm_bindgen(js_name = moveRight)]
+    pub fn move_right(&mut self) {
+        self.tunnel.move_player_right();
+    }
+
+    // steer from a KeyboardEvent.key; false for keys that do not steer
+    pub fn key(&mut self, key: &str) -> bool {
+        match key {
+            "ArrowLeft" | "a" | "A" => self.move_left(),
+            "ArrowRight" | "d" | "D" => self.move_right(),
+            _ => return false,
+        }
+        true
+    }
+
+    // steer toward a touch `x` pixels across a `width`-pixel wide canvas
+    pub fn touch(&mut self, x: f64, width: f64) {
+        let cols = f64::from(self.tunnel.screen_width);
+        let col = (x / width * cols).floor();
+        let player = f64::from(self.tunnel.player());
+        if col < player {
+            self.move_left();
+        } else if col > player {
+            self.move_right();
+        }
+    }
+
+    #[wasm_bindgen(js_name = isCollision)]
+    pub fn is_collision(&self) -> bool {
+        self.tunnel.is_collision()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn player(&self) -> u16 {
+        self.tunnel.player()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cols(&self) -> u16 {
+        self.tunnel.screen_width
+    }
+
+    // the height of the grid in cells(), which is less than the screen
+    #[wasm_bindgen(getter)]
+    pub fn rows(&self) -> usize {
+        self.tunnel.buffered_rows()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn generation(&self) -> u64 {
+        self.tunnel.generation()
+    }
+
+    #[wasm_bindgen(js_name = speedMultiplier)]
+    pub fn speed_multiplier(&self) -> usize {
+        self.tunnel.speed_multiplier()
+    }
+
+    pub fn cells(&self) -> Vec<u8> {
+        self.visible_cells(usize::MAX)
+    }
+
+    // cells(), with rows more than `radius` ahead of the player Hidden
+    #[wasm_bindgen(js_name = visibleCells)]
+    pub fn visible_cells(&self, radius: usize) -> Vec<u8> {
+        self.tunnel
+            .iter_visible(radius)
+            .map(|(_, _, cell_type)| cell_type.code())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_are_the_iterator_flattened() {
+        let mut w = WasmTunnel::new(12, 9, 3);
+        w.move_left();
+        w.step();
+        let cells = w.cells();
+        assert_eq!(cells.len(), w.rows() * 9);
+        let player = cells.iter().position(|c| *c == 2).unwrap();
+        assert_eq!(player, usize::from(w.player()));
+        let codes = w.tunnel.iter().map(|(_, _, c)| c.code());
+        assert!(codes.eq(cells));
+        assert!(w.visible_cells(1).contains(&6));
+    }
+
+    #[test]
+    fn keys_and_touches_steer() {
+        let mut w = WasmTunnel::new(12, 9, 3);
+        let start = w.player();
+        assert!(w.key("ArrowLeft"));
+        assert_eq!(w.player(), start - 1);
+        assert!(!w.key("Enter"));
+        assert_eq!(w.player(), start - 1);
+        // the right edge of a 90-pixel canvas is column 8
+        w.touch(89., 90.);
+        assert_eq!(w.player(), start);
+        w.touch(f64::from(start) * 10. + 5., 90.);
+        assert_eq!(w.player(), start);
+    }
+}
@@ -0,0 +1,178 @@
+// Draws a Tunnel<u16> into a ratatui Buffer, with the same glyphs as the
+// terminal game unless told otherwise. `&tunnel` renders with the default
+// style; wrap it in a TunnelWidget to restyle it or add fog.
+
+use crate::{Direction, Floor, Hazard, Tunnel, TunnelCellType};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::widgets::Widget;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    pub symbol: char,
+    pub style: Style,
+}
+
+impl Glyph {
+    pub const fn new(symbol: char, style: Style) -> Glyph {
+        Glyph { symbol, style }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TunnelStyle {
+    pub player: Glyph,
+    pub floor: Glyph,
+    pub wall: Glyph,
+    pub fuel: Glyph,
+    pub key: Glyph,
+    pub door: Glyph,
+    pub hidden: Glyph,
+    pub coin: Glyph,
+    pub pillar: Glyph,
+    // floor cells on rows with a RowInfo effect
+    pub ice: Glyph,
+    pub sticky: Glyph,
+    pub wind_left: Glyph,
+    pub wind_right: Glyph,
+    pub speed_zone: Glyph,
+    pub warning: Glyph,
+}
+
+impl Default for TunnelStyle {
+    fn default() -> TunnelStyle {
+        let fg = |c| Style::new().fg(c);
+        TunnelStyle {
+            player: Glyph::new('v', fg(Color::Green)),
+            floor: Glyph::new(' ', Style::new()),
+            wall: Glyph::new('O', Style::new()),
+            fuel: Glyph::new('+', fg(Color::Yellow)),
+            key: Glyph::new('k', fg(Color::Yellow)),
+            door: Glyph::new('#', fg(Color::Magenta)),
+            hidden: Glyph::new('.', fg(Color::DarkGray)),
+            coin: Glyph::new('*', fg(Color::Yellow)),
+            pillar: Glyph::new('X', fg(Color::Red)),
+            ice: Glyph::new('-', fg(Color::Cyan)),
+            sticky: Glyph::new('~', fg(Color::Yellow)),
+            wind_left: Glyph::new('<', fg(Color::DarkGray)),
+            wind_right: Glyph::new('>', fg(Color::DarkGray)),
+            speed_zone: Glyph::new('^', fg(Color::DarkGray)),
+            warning: Glyph::new('!', fg(Color::Red)),
+        }
+    }
+}
+
+pub struct TunnelWidget<'a> {
+    pub tunnel: &'a Tunnel<u16>,
+    pub style: TunnelStyle,
+    // rows further than this ahead of the player draw as `style.hidden`
+    pub fog: usize,
+}
+
+impl<'a> TunnelWidget<'a> {
+    pub fn new(tunnel: &'a Tunnel<u16>) -> TunnelWidget<'a> {
+        TunnelWidget {
+            tunnel,
+            style: TunnelStyle::default(),
+            fog: usize::MAX,
+        }
+    }
+
+    fn glyph(&self, row: u16, cell_type: TunnelCellType) -> Glyph {
+        let s = &self.style;
+        match cell_type {
+            TunnelCellType::Player => s.player,
+            TunnelCellType::Wall => s.wall,
+            TunnelCellType::Fuel => s.fuel,
+            TunnelCellType::Key => s.key,
+            TunnelCellType::Door => s.door,
+            TunnelCellType::Hidden => s.hidden,
+            TunnelCellType::Coin => s.coin,
+            TunnelCellType::Pillar => s.pillar,
+            TunnelCellType::Floor => {
+                let info =
+                    self.tunnel.row_info(usize::from(row)).unwrap_or_default();
+                match (info.floor, info.wind) {
+                    _ if info.hazard == Some(Hazard::Warning) => s.warning,
+                    (Floor::Ice, _) => s.ice,
+                    (Floor::Sticky, _) => s.sticky,
+                    (Floor::Normal, Some(wind)) => match wind.direction {
+                        Direction::Left => s.wind_left,
+                        Direction::Right => s.wind_right,
+                    },
+                    (Floor::Normal, None) if info.speed_zone => s.speed_zone,
+                    (Floor::Normal, None) => s.floor,
+                }
+            }
+        }
+    }
+}
+
+impl Widget for TunnelWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = area.intersection(buf.area);
+        for (row, col, cell_type) in self.tunnel.iter_visible(self.fog) {
+            if row >= area.height || col >= area.width {
+                continue;
+            }
+            let glyph = self.glyph(row, cell_type);
+            if let Some(cell) = buf.cell_mut((area.x + col, area.y + row)) {
+                cell.set_char(glyph.symbol).set_style(glyph.style);
+            }
+        }
+    }
+}
+
+impl Widget for &Tunnel<u16> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        TunnelWidget::new(self).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::pattern::PatternBuilder;
+
+    #[test]
+    fn renders_cells_at_the_area_offset() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u16>::new(&mut b, 6, 7);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 10));
+        (&t).render(Rect::new(2, 1, 7, 6), &mut buf);
+
+        let style = TunnelStyle::default();
+        for (row, col, cell_type) in t.iter() {
+            let cell = &buf[(col + 2, row + 1)];
+            let expected = match cell_type {
+                TunnelCellType::Player => style.player,
+                TunnelCellType::Wall => style.wall,
+                TunnelCellType::Pillar => style.pillar,
+                _ => style.floor,
+            };
+            assert_eq!(cell.symbol(), expected.symbol.to_string());
+            assert_eq!(cell.fg, expected.style.fg.unwrap_or(Color::Reset));
+        }
+        // nothing lands outside the area
+        assert_eq!(buf[(0, 0)].symbol(), " ");
+        assert_eq!(buf[(1, 3)].symbol(), " ");
+    }
+
+    #[test]
+    fn clips_to_a_smaller_area() {
+        let mut b = PatternBuilder::parse("L1 R1").unwrap();
+        let t = Tunnel::<u16>::new(&mut b, 6, 7);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 3, 2));
+        let widget = TunnelWidget {
+            style: TunnelStyle {
+                wall: Glyph::new('#', Style::new()),
+                ..TunnelStyle::default()
+            },
+            ..TunnelWidget::new(&t)
+        };
+        widget.render(buf.area, &mut buf);
+        assert_eq!(buf[(0, 0)].symbol(), "#");
+    }
+}
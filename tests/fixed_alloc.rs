@@ -0,0 +1,39 @@
+// TunnelFixed keeps its rows inline, so stepping one must never touch the
+// heap. This counts every allocation in the process, which is why it lives in
+// a binary of its own with no test harness: nothing else runs alongside it.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tunnel::fixed::TunnelFixed;
+use tunnel::pattern::PatternBuilder;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn main() {
+    let mut b = PatternBuilder::parse("L3 R5 S K2 { } M").unwrap();
+    let mut t = TunnelFixed::<u8, 16, 24>::new(&mut b);
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..100 {
+        t.move_player_left();
+        t.step(&mut b);
+        assert!(t.iter().count() <= TunnelFixed::<u8, 16, 24>::MAX_CELLS);
+    }
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), before);
+    println!("fixed_tunnel_steps_without_allocating ... ok");
+}